@@ -27,8 +27,11 @@ pub enum Error {
     #[error(transparent)]
     KaspaRpcClient(#[from] kaspa_wrpc_client::error::Error),
 
-    #[error("Indexer error: {0}")]
-    IndexerError(String),
+    #[error("No kasplex indexer is known for network `{0}`, please configure an indexer URL")]
+    UnsupportedNetwork(String),
+
+    #[error(transparent)]
+    IndexerError(#[from] crate::kasplex::transport::IndexerError),
 
     #[error("Listener error: {0}")]
     ListenerError(String),
@@ -1,4 +1,8 @@
+use crate::output::Format;
 use kaspa_consensus_core::network::{NetworkId, NetworkType};
+use sparkle_core::model::kasplex::v1::{krc20, Tick};
+use sparkle_core::prelude::Url;
+use sparkle_rs::kasplex::endpoint::Backend;
 
 #[derive(Debug)]
 pub enum BetaAction {
@@ -11,6 +15,12 @@ pub struct Args {
     pub trace_log_level: bool,
     pub enable_debug_mode: bool,
     pub network_id: NetworkId,
+    /// Indexer URL overriding the environment and the backend default.
+    pub url: Option<Url>,
+    /// Indexer backend overriding the environment.
+    pub backend: Option<Backend>,
+    /// Output format of command results.
+    pub format: Format,
     pub action: Action,
 }
 
@@ -18,6 +28,35 @@ pub struct Args {
 pub enum Action {
     List,
     Status,
+    /// Token details.
+    Token {
+        tick: Tick,
+    },
+    /// Top holders of a token.
+    Holders {
+        tick: Tick,
+    },
+    /// Token balances of an address, all tokens if `tick` is `None`.
+    Balance {
+        address: String,
+        tick: Option<Tick>,
+    },
+    /// Operations matching the filters, most recent first.
+    Ops {
+        tick: Option<Tick>,
+        address: Option<String>,
+        op: Option<krc20::Op>,
+        limit: usize,
+    },
+    /// Operation by reveal transaction hash or op score.
+    Op {
+        id: String,
+    },
+    /// Serve the mock indexer fixtures.
+    Mock {
+        listen: String,
+        page_size: usize,
+    },
 }
 
 impl Args {
@@ -44,8 +83,134 @@ impl Args {
                     .value_parser(clap::value_parser!(NetworkId))
                     .help("Network id (default 'testnet-11')"),
             )
+            .arg(
+                Arg::new("url")
+                    .long("url")
+                    .value_name("url")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .help(
+                        "Indexer URL (e.g. 'http://127.0.0.1:8787/v1', default the --backend URL, \
+                        $KASPLEX_INDEXER_URL or the kasplex URL for the network)",
+                    ),
+            )
+            .arg(
+                Arg::new("backend")
+                    .long("backend")
+                    .value_name("kasplex|sparkled")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .value_parser(|backend: &str| {
+                        backend.parse::<Backend>().map_err(|err| err.to_string())
+                    })
+                    .help(
+                        "Indexer backend, overriding $KASPLEX_INDEXER_URL: 'kasplex' or 'sparkled' \
+                        for a local sparkled started with --http (default 'kasplex')",
+                    ),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_name("table | json | csv")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .global(true)
+                    .value_parser(clap::value_parser!(Format))
+                    .help("Output format (default 'table')"),
+            )
             .subcommand(Command::new("status").about("Display indexer status"))
-            .subcommand(Command::new("list").about("List tokens"));
+            .subcommand(Command::new("list").about("List tokens"))
+            .subcommand(
+                Command::new("token")
+                    .about("Display token details")
+                    .arg(tick_arg().required(true)),
+            )
+            .subcommand(
+                Command::new("holders")
+                    .about("List top token holders")
+                    .arg(tick_arg().required(true)),
+            )
+            .subcommand(
+                Command::new("balance")
+                    .about("Display token balances of an address")
+                    .arg(
+                        Arg::new("address")
+                            .value_name("address")
+                            .required(true)
+                            .help("Holder address"),
+                    )
+                    .arg(tick_arg().help("Token tick (default all tokens)")),
+            )
+            .subcommand(
+                Command::new("ops")
+                    .about("List operations, most recent first")
+                    .arg(
+                        Arg::new("tick")
+                            .long("tick")
+                            .value_name("tick")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .value_parser(clap::value_parser!(Tick))
+                            .help("Operations on the token"),
+                    )
+                    .arg(
+                        Arg::new("address")
+                            .long("address")
+                            .value_name("address")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .help("Operations from or to the address"),
+                    )
+                    .arg(
+                        Arg::new("op")
+                            .long("op")
+                            .value_name("deploy | mint | transfer | ...")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .value_parser(|op: &str| {
+                                op.parse::<krc20::Op>().map_err(|err| err.to_string())
+                            })
+                            .help("Operation type"),
+                    )
+                    .arg(
+                        Arg::new("limit")
+                            .long("limit")
+                            .value_name("count")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .value_parser(clap::value_parser!(usize))
+                            .help("Maximum number of operations (default 100)"),
+                    ),
+            )
+            .subcommand(
+                Command::new("op").about("Display an operation").arg(
+                    Arg::new("id")
+                        .value_name("hash | op score")
+                        .required(true)
+                        .help("Reveal transaction hash or op score"),
+                ),
+            )
+            .subcommand(
+                Command::new("mock")
+                    .about("Serve a mock indexer from the recorded fixtures")
+                    .arg(
+                        Arg::new("listen")
+                            .long("listen")
+                            .value_name("address")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .help("Listen address (default '127.0.0.1:8787')"),
+                    )
+                    .arg(
+                        Arg::new("page-size")
+                            .long("page-size")
+                            .value_name("items")
+                            .num_args(0..=1)
+                            .require_equals(true)
+                            .value_parser(clap::value_parser!(usize))
+                            .help("Items per page of list endpoints (default 50)"),
+                    ),
+            );
 
         let matches = cmd.get_matches();
 
@@ -58,6 +223,17 @@ impl Args {
             .cloned()
             .unwrap_or(NetworkId::with_suffix(NetworkType::Testnet, 11));
 
+        let url = matches
+            .get_one::<String>("url")
+            .map(|url| Url::from(url.as_str()));
+
+        let backend = matches.get_one::<Backend>("backend").cloned();
+
+        let format = matches
+            .get_one::<Format>("format")
+            .cloned()
+            .unwrap_or_default();
+
         let action = if matches.get_one::<bool>("version").cloned().unwrap_or(false) {
             println!("v{}-{}", crate::VERSION, crate::GIT_DESCRIBE);
             std::process::exit(0);
@@ -65,6 +241,37 @@ impl Args {
             Action::Status
         } else if let Some(_matches) = matches.subcommand_matches("list") {
             Action::List
+        } else if let Some(matches) = matches.subcommand_matches("token") {
+            let tick = matches.get_one::<Tick>("tick").cloned().unwrap();
+            Action::Token { tick }
+        } else if let Some(matches) = matches.subcommand_matches("holders") {
+            let tick = matches.get_one::<Tick>("tick").cloned().unwrap();
+            Action::Holders { tick }
+        } else if let Some(matches) = matches.subcommand_matches("balance") {
+            let address = matches.get_one::<String>("address").cloned().unwrap();
+            let tick = matches.get_one::<Tick>("tick").cloned();
+            Action::Balance { address, tick }
+        } else if let Some(matches) = matches.subcommand_matches("ops") {
+            let tick = matches.get_one::<Tick>("tick").cloned();
+            let address = matches.get_one::<String>("address").cloned();
+            let op = matches.get_one::<krc20::Op>("op").cloned();
+            let limit = matches.get_one::<usize>("limit").cloned().unwrap_or(100);
+            Action::Ops {
+                tick,
+                address,
+                op,
+                limit,
+            }
+        } else if let Some(matches) = matches.subcommand_matches("op") {
+            let id = matches.get_one::<String>("id").cloned().unwrap();
+            Action::Op { id }
+        } else if let Some(matches) = matches.subcommand_matches("mock") {
+            let listen = matches
+                .get_one::<String>("listen")
+                .cloned()
+                .unwrap_or("127.0.0.1:8787".to_string());
+            let page_size = matches.get_one::<usize>("page-size").cloned().unwrap_or(50);
+            Action::Mock { listen, page_size }
         } else {
             println!("No command specified");
             std::process::exit(1);
@@ -72,9 +279,19 @@ impl Args {
 
         Args {
             network_id,
+            url,
+            backend,
+            format,
             action,
             enable_debug_mode,
             trace_log_level,
         }
     }
 }
+
+fn tick_arg() -> clap::Arg {
+    clap::Arg::new("tick")
+        .value_name("tick")
+        .value_parser(clap::value_parser!(Tick))
+        .help("Token tick")
+}
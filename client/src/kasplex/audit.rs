@@ -0,0 +1,434 @@
+//!
+//! Reconciliation of a local indexer against the kasplex reference indexer.
+//!
+//! Both sides are queried through the kasplex v1 API: the reference indexer
+//! (e.g. api.kasplex.org) and a candidate serving the same API, by default
+//! the KRC-20 ledger of the local sparkled. Every token known to either side
+//! is compared for
+//! supply, minted amount, state and holder balances, and its operations are
+//! matched by reveal transaction to find missing operations and differing
+//! `opError` results.
+//!
+
+use super::v1::Indexer;
+use crate::imports::*;
+use sparkle_core::hash::Hash;
+use sparkle_core::model::kasplex::v1::{krc20, State, Tick};
+use std::collections::{BTreeMap, BTreeSet};
+use workflow_log::prelude::*;
+
+/// Difference between the local and the reference indexer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Discrepancy {
+    /// Token deployed according to the reference indexer only.
+    MissingToken { tick: Tick },
+    /// Token deployed according to the local indexer only.
+    UnexpectedToken { tick: Tick },
+    State {
+        tick: Tick,
+        reference: State,
+        local: State,
+    },
+    Supply {
+        tick: Tick,
+        reference: TokenAmount,
+        local: TokenAmount,
+    },
+    Minted {
+        tick: Tick,
+        reference: TokenAmount,
+        local: TokenAmount,
+    },
+    /// Holder balance, zero if the address does not hold the token.
+    Balance {
+        tick: Tick,
+        address: String,
+        reference: TokenAmount,
+        local: TokenAmount,
+    },
+    /// Operation indexed by the reference indexer only.
+    MissingOp {
+        tick: Tick,
+        hash: Hash,
+        op: krc20::Op,
+    },
+    /// Operation indexed by the local indexer only.
+    UnexpectedOp {
+        tick: Tick,
+        hash: Hash,
+        op: krc20::Op,
+    },
+    OpError {
+        tick: Tick,
+        hash: Hash,
+        reference: Option<String>,
+        local: Option<String>,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none = |error: &Option<String>| error.clone().unwrap_or("none".to_string());
+        match self {
+            Discrepancy::MissingToken { tick } => write!(f, "{tick}: missing token"),
+            Discrepancy::UnexpectedToken { tick } => write!(f, "{tick}: unexpected token"),
+            Discrepancy::State {
+                tick,
+                reference,
+                local,
+            } => write!(f, "{tick}: state {local:?}, expected {reference:?}"),
+            Discrepancy::Supply {
+                tick,
+                reference,
+                local,
+            } => write!(f, "{tick}: supply {local}, expected {reference}"),
+            Discrepancy::Minted {
+                tick,
+                reference,
+                local,
+            } => write!(f, "{tick}: minted {local}, expected {reference}"),
+            Discrepancy::Balance {
+                tick,
+                address,
+                reference,
+                local,
+            } => write!(
+                f,
+                "{tick}: balance of {address} {local}, expected {reference}"
+            ),
+            Discrepancy::MissingOp { tick, hash, op } => {
+                write!(f, "{tick}: missing {op} operation {hash}")
+            }
+            Discrepancy::UnexpectedOp { tick, hash, op } => {
+                write!(f, "{tick}: unexpected {op} operation {hash}")
+            }
+            Discrepancy::OpError {
+                tick,
+                hash,
+                reference,
+                local,
+            } => write!(
+                f,
+                "{tick}: operation {hash} error {}, expected {}",
+                or_none(local),
+                or_none(reference)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// Number of tokens compared.
+    pub tokens: usize,
+    /// Number of holder balances compared.
+    pub balances: usize,
+    /// Number of operations compared.
+    pub ops: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl AuditReport {
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for discrepancy in &self.discrepancies {
+            writeln!(f, "{discrepancy}")?;
+        }
+        write!(
+            f,
+            "{} tokens, {} balances, {} operations compared: {} discrepancies",
+            self.tokens,
+            self.balances,
+            self.ops,
+            self.discrepancies.len()
+        )
+    }
+}
+
+pub struct Audit {
+    reference: Indexer,
+    local: Indexer,
+}
+
+impl Audit {
+    /// Audits the `local` candidate against `reference` (kasplex).
+    pub fn new(reference: Indexer, local: Indexer) -> Self {
+        Self { reference, local }
+    }
+
+    pub async fn run(&self) -> Result<AuditReport> {
+        let (reference, local) =
+            futures::try_join!(self.reference.get_token_list(), self.local.get_token_list())?;
+        let ticks = reference
+            .iter()
+            .chain(local.iter())
+            .filter_map(|token| match token.tick() {
+                Ok(tick) => Some(tick),
+                Err(err) => {
+                    log_warn!("Skipping token `{}`: {err}", token.tick);
+                    None
+                }
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut report = AuditReport::default();
+        for tick in ticks {
+            log_info!("Auditing {tick}...");
+            self.audit_token(&tick, &mut report).await?;
+        }
+        Ok(report)
+    }
+
+    /// Compares the state, holders and operations of `tick`.
+    pub async fn audit_token(&self, tick: &Tick, report: &mut AuditReport) -> Result<()> {
+        let (reference, local) = futures::try_join!(
+            self.reference.get_token(tick.as_str()),
+            self.local.get_token(tick.as_str())
+        )?;
+
+        if matches!(
+            (reference.state, local.state),
+            (State::Unused, State::Unused)
+        ) {
+            return Ok(());
+        }
+
+        report.tokens += 1;
+        match (reference.state, local.state) {
+            (_, State::Unused) => {
+                report
+                    .discrepancies
+                    .push(Discrepancy::MissingToken { tick: *tick });
+                return Ok(());
+            }
+            (State::Unused, _) => {
+                report
+                    .discrepancies
+                    .push(Discrepancy::UnexpectedToken { tick: *tick });
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        compare_token(tick, &reference, &local, report);
+
+        let filter = krc20::OpListFilter::default().tick(*tick);
+        let (reference, local) = futures::try_join!(
+            self.reference.get_op_list(&filter),
+            self.local.get_op_list(&filter)
+        )?;
+        compare_ops(tick, &reference, &local, report);
+
+        Ok(())
+    }
+}
+
+fn compare_token(
+    tick: &Tick,
+    reference: &krc20::TokenHolderResult,
+    local: &krc20::TokenHolderResult,
+    report: &mut AuditReport,
+) {
+    let tick = *tick;
+    if reference.state != local.state {
+        report.discrepancies.push(Discrepancy::State {
+            tick,
+            reference: reference.state,
+            local: local.state,
+        });
+    }
+    if (reference.max, reference.dec) != (local.max, local.dec) {
+        report.discrepancies.push(Discrepancy::Supply {
+            tick,
+            reference: reference.max_amount(),
+            local: local.max_amount(),
+        });
+    }
+    if reference.minted != local.minted {
+        report.discrepancies.push(Discrepancy::Minted {
+            tick,
+            reference: reference.minted_amount(),
+            local: local.minted_amount(),
+        });
+    }
+
+    let holders = |result: &krc20::TokenHolderResult| {
+        result
+            .holder
+            .iter()
+            .map(|holder| (holder.address.clone(), holder.amount))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (reference_holders, local_holders) = (holders(reference), holders(local));
+    let addresses = reference_holders
+        .keys()
+        .chain(local_holders.keys())
+        .collect::<BTreeSet<_>>();
+
+    for address in addresses {
+        report.balances += 1;
+        let expected = reference_holders.get(address).copied().unwrap_or_default();
+        let actual = local_holders.get(address).copied().unwrap_or_default();
+        if expected != actual {
+            report.discrepancies.push(Discrepancy::Balance {
+                tick,
+                address: address.clone(),
+                reference: TokenAmount::new(expected, reference.dec),
+                local: TokenAmount::new(actual, reference.dec),
+            });
+        }
+    }
+}
+
+fn compare_ops(
+    tick: &Tick,
+    reference: &[krc20::TokenTransaction],
+    local: &[krc20::TokenTransaction],
+    report: &mut AuditReport,
+) {
+    let tick = *tick;
+    let by_hash = |ops: &[krc20::TokenTransaction]| {
+        ops.iter()
+            .filter_map(|op| Some((op.hash_rev?, op)))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (reference, local) = (by_hash(reference), by_hash(local));
+
+    for (hash, expected) in &reference {
+        report.ops += 1;
+        let Some(actual) = local.get(hash) else {
+            report.discrepancies.push(Discrepancy::MissingOp {
+                tick,
+                hash: *hash,
+                op: expected.op,
+            });
+            continue;
+        };
+
+        let (expected_error, actual_error) = (op_error(expected), op_error(actual));
+        if expected_error != actual_error {
+            report.discrepancies.push(Discrepancy::OpError {
+                tick,
+                hash: *hash,
+                reference: expected_error,
+                local: actual_error,
+            });
+        }
+    }
+
+    for (hash, actual) in local {
+        if !reference.contains_key(&hash) {
+            report.ops += 1;
+            report.discrepancies.push(Discrepancy::UnexpectedOp {
+                tick,
+                hash,
+                op: actual.op,
+            });
+        }
+    }
+}
+
+/// Error of a rejected operation; kasplex reports accepted operations with
+/// an empty `opError`.
+fn op_error(op: &krc20::TokenTransaction) -> Option<String> {
+    op.op_error.clone().filter(|error| !error.is_empty())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::super::mock::{MockConfig, MockIndexer};
+    use super::*;
+    use serde_json::json;
+
+    const BOB: &str = "kaspatest:qzqmvd7clnfvdknrt8nfvvgn5ytsmeu4ujmjtwzdrc95elv7ckxwjmrgetehx";
+
+    #[tokio::test]
+    async fn test_audit() {
+        let config = MockConfig::default().with_page_size(3);
+        let reference = MockIndexer::start(config.clone()).await.unwrap();
+        let local = MockIndexer::start(config).await.unwrap();
+        let audit = Audit::new(
+            Indexer::try_new(reference.url()).unwrap(),
+            Indexer::try_new(local.url()).unwrap(),
+        );
+
+        let report = audit.run().await.unwrap();
+        assert!(report.is_consistent(), "{report}");
+        assert_eq!((report.tokens, report.balances, report.ops), (5, 6, 16));
+
+        let mut dropped = None;
+        local.edit(|fixtures| {
+            // NACHO is not indexed
+            fixtures.tokens.retain(|token| token["tick"] != "NACHO");
+            fixtures.ops.retain(|op| op["tick"] != "NACHO");
+
+            let toitoi = &mut fixtures.tokens[0];
+            toitoi["minted"] = json!("300000000000");
+            toitoi["holder"][1]["amount"] = json!("0");
+
+            // the first KEKE operation is missing
+            let index = fixtures
+                .ops
+                .iter()
+                .position(|op| op["tick"] == "KEKE")
+                .unwrap();
+            dropped = Some(fixtures.ops.remove(index));
+
+            // the failed KASPY mint is accepted
+            for op in fixtures.ops.iter_mut().filter(|op| op["tick"] == "KASPY") {
+                op["opError"] = json!("");
+            }
+        });
+
+        let report = audit.run().await.unwrap();
+        let dropped = dropped.unwrap();
+        let tick = |tick: &str| Tick::from_str(tick).unwrap();
+        assert_eq!(
+            report.discrepancies,
+            [
+                Discrepancy::OpError {
+                    tick: tick("KASPY"),
+                    hash: Hash::from_str(
+                        "ec1f16b4091dcbab3b4cf39d014bac09eb0bcfdbdb798f652719545a85ebdc26"
+                    )
+                    .unwrap(),
+                    reference: Some("insufficient fee".to_string()),
+                    local: None,
+                },
+                Discrepancy::MissingOp {
+                    tick: tick("KEKE"),
+                    hash: Hash::from_str(dropped["hashRev"].as_str().unwrap()).unwrap(),
+                    op: krc20::Op::Mint,
+                },
+                Discrepancy::MissingToken {
+                    tick: tick("NACHO")
+                },
+                Discrepancy::Minted {
+                    tick: tick("TOITOI"),
+                    reference: TokenAmount::new(400_000_000_000, 8),
+                    local: TokenAmount::new(300_000_000_000, 8),
+                },
+                Discrepancy::Balance {
+                    tick: tick("TOITOI"),
+                    address: BOB.to_string(),
+                    reference: TokenAmount::new(100_000_000_000, 8),
+                    local: TokenAmount::zero(8),
+                },
+            ]
+        );
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["discrepancies"][2]["kind"], "missing-token");
+        assert_eq!(
+            report.discrepancies[3].to_string(),
+            "TOITOI: minted 3,000, expected 4,000"
+        );
+    }
+}
@@ -1,17 +1,32 @@
 use crate::args::{Action, Args};
+use crate::output::{Cell, Format, Table};
+use futures::{StreamExt, TryStreamExt};
+use sparkle_core::hash::Hash;
+use sparkle_core::model::amount::DEFAULT_DECIMALS;
 use sparkle_core::model::kasplex::v1;
+use sparkle_core::model::TokenAmount;
 use sparkle_core::runtime::Runtime;
+use sparkle_rs::error::Error;
+use sparkle_rs::imports::Address;
+use sparkle_rs::kasplex::endpoint::Endpoint;
+use sparkle_rs::kasplex::mock::{MockConfig, MockIndexer};
 use sparkle_rs::kasplex::v1::Indexer;
 use sparkle_rs::result::Result;
+use std::collections::HashMap;
+use std::str::FromStr;
+use workflow_log::prelude::*;
 
 #[derive(Default)]
 pub struct Client;
 
 impl Client {
-    pub async fn run(&self, _runtime: &Runtime) -> Result<()> {
+    pub async fn run(&self, runtime: &Runtime) -> Result<()> {
         let Args {
             action,
             network_id,
+            url,
+            backend,
+            format,
             enable_debug_mode,
             trace_log_level,
         } = Args::parse();
@@ -24,41 +39,306 @@ impl Client {
 
         // ---
 
-        println!(
-            "kasplex indexer client v{}-{} (rusty-kaspa v{}) - {network_id}",
-            crate::VERSION,
-            crate::GIT_DESCRIBE,
-            kaspa_wallet_core::version()
-        );
+        // keep json and csv output machine readable
+        if format == Format::Table {
+            println!(
+                "kasplex indexer client v{}-{} (rusty-kaspa v{}) - {network_id}",
+                crate::VERSION,
+                crate::GIT_DESCRIBE,
+                kaspa_wallet_core::version()
+            );
+            println!();
+        }
 
-        let network = v1::Network::try_from(&network_id)?;
+        // resolved on use, serving the mock does not require an indexer
+        let endpoint = Endpoint::default().with_backend(backend).with_url(url);
+        let indexer = || Indexer::try_new(endpoint.resolve(&network_id)?);
 
         match action {
             Action::List => {
-                println!();
-
-                let indexer = Indexer::try_new(network.into())?;
+                let indexer = indexer()?;
                 let mut tokens = indexer.get_token_list().await?;
                 tokens.sort_by(|a, b| a.tick.cmp(&b.tick));
 
-                tokens.iter().for_each(|token| {
-                    println!("{}", token.tick);
-                });
+                let mut table = Table::new(&["tick", "state", "max", "minted", "dec"]);
+                for token in tokens.iter() {
+                    table.push(vec![
+                        token.tick.as_str().into(),
+                        token.state.to_string().into(),
+                        token.max_amount().into(),
+                        token.minted_amount().into(),
+                        token.dec.to_string().into(),
+                    ]);
+                }
+                print(&table, format);
 
-                println!();
-                println!("{} tokens", tokens.len());
-                println!();
+                if format == Format::Table {
+                    println!();
+                    println!("{} tokens", tokens.len());
+                }
             }
             Action::Status => {
-                let indexer = Indexer::try_new(network.into())?;
+                let indexer = indexer()?;
                 let result = indexer.get_indexer_status().await?;
+                if format == Format::Table {
+                    println!("{:>12}: {}", "Network", network_id);
+                    println!("{}", result.format(network_id.network_type()));
+                } else {
+                    let table = Table::record(
+                        &[
+                            "network",
+                            "daa_score",
+                            "op_score",
+                            "op_total",
+                            "token_total",
+                            "fee_total",
+                        ],
+                        vec![
+                            network_id.to_string().into(),
+                            result.daa_score.to_string().into(),
+                            result.op_score.to_string().into(),
+                            result.op_total.to_string().into(),
+                            result.token_total.to_string().into(),
+                            result.fee_total.to_string().into(),
+                        ],
+                    );
+                    print(&table, format);
+                }
+            }
+            Action::Token { tick } => {
+                let indexer = indexer()?;
+                let token = indexer.get_token(tick.as_str()).await?;
+                let table = Table::record(
+                    &[
+                        "tick",
+                        "state",
+                        "max",
+                        "limit",
+                        "minted",
+                        "dec",
+                        "holders",
+                        "mint_start_daa_score",
+                        "mint_end_daa_score",
+                        "op_score_add",
+                        "op_score_mod",
+                    ],
+                    vec![
+                        token.tick.as_str().into(),
+                        token.state.to_string().into(),
+                        token.max_amount().into(),
+                        TokenAmount::new(token.lim, token.dec).into(),
+                        token.minted_amount().into(),
+                        token.dec.to_string().into(),
+                        token.holder.len().to_string().into(),
+                        token.mint_start_daa_score.to_string().into(),
+                        token.mint_end_daa_score.to_string().into(),
+                        token.op_score_add.to_string().into(),
+                        token.op_score_mod.to_string().into(),
+                    ],
+                );
+                print(&table, format);
+            }
+            Action::Holders { tick } => {
+                let indexer = indexer()?;
+                let token = indexer.get_token(tick.as_str()).await?;
+
+                let mut table = Table::new(&["rank", "address", "amount", "share"]);
+                for (rank, holder) in token.holder.iter().enumerate() {
+                    let share = if token.minted > 0 {
+                        holder.amount as f64 * 100.0 / token.minted as f64
+                    } else {
+                        0.0
+                    };
+                    table.push(vec![
+                        (rank + 1).to_string().into(),
+                        holder.address.as_str().into(),
+                        token.holder_amount(holder).into(),
+                        format!("{share:.2}%").into(),
+                    ]);
+                }
+                print(&table, format);
+            }
+            Action::Balance { address, tick } => {
+                let indexer = indexer()?;
+                let address = Address::try_from(address.as_str())
+                    .map_err(|err| Error::custom(format!("invalid address `{address}`: {err}")))?;
+                let balances = match tick {
+                    Some(tick) => {
+                        indexer
+                            .get_token_balance_by_address(&address, tick.as_str())
+                            .await?
+                    }
+                    None => indexer.get_token_balance_list_by_address(&address).await?,
+                };
+
+                let mut table = Table::new(&["tick", "balance", "locked", "dec"]);
+                for balance in balances.iter() {
+                    table.push(vec![
+                        balance.tick.as_str().into(),
+                        balance.balance_amount().into(),
+                        balance.locked_amount().into(),
+                        balance.dec.to_string().into(),
+                    ]);
+                }
+                print(&table, format);
+            }
+            Action::Ops {
+                tick,
+                address,
+                op,
+                limit,
+            } => {
+                let indexer = indexer()?;
+                let filter = v1::krc20::OpListFilter { address, tick, op };
+                let ops = indexer
+                    .op_list_stream(&filter, None)
+                    .take(limit)
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                let decimals = token_decimals(&indexer, &ops).await;
+
+                let mut table = Table::new(&[
+                    "op_score", "op", "tick", "amount", "from", "to", "hash", "error",
+                ]);
+                for op in ops.iter() {
+                    table.push(vec![
+                        op.op_score.map(|score| score.to_string()).into(),
+                        op.op.to_string().into(),
+                        op.token_id().into(),
+                        amount(op.amount, op_decimals(op, &decimals)),
+                        op.from.clone().into(),
+                        op.to.clone().into(),
+                        op.hash_rev.map(|hash| hash.to_string()).into(),
+                        op.op_error.clone().into(),
+                    ]);
+                }
+                print(&table, format);
+            }
+            Action::Op { id } => {
+                let indexer = indexer()?;
+                let op = if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+                    let op_score = v1::OpScore::from_str(&id)
+                        .map_err(|err| Error::custom(format!("invalid op score `{id}`: {err}")))?;
+                    indexer.get_op_by_score(op_score).await?
+                } else {
+                    let hash = Hash::from_str(&id)
+                        .map_err(|err| Error::custom(format!("invalid hash `{id}`: {err}")))?;
+                    indexer.get_op_by_hash(&hash).await?
+                };
+                let op = op.ok_or_else(|| Error::custom(format!("operation `{id}` not found")))?;
+                let decimals = token_decimals(&indexer, std::slice::from_ref(&op)).await;
+                print(&op_record(&op, op_decimals(&op, &decimals)), format);
+            }
+            Action::Mock { listen, page_size } => {
+                let config = MockConfig::default().with_page_size(page_size);
+                let mock = MockIndexer::bind(&listen, config).await?;
+                println!("mock indexer serving fixtures at {}", mock.url());
                 println!();
-                println!("{:>12}: {}", "Network", network_id);
-                println!("{}", result.format(v1::Network::Testnet11));
-                println!();
+                runtime.run().await?;
+                mock.stop();
             }
         }
 
         Ok(())
     }
 }
+
+fn print(table: &Table, format: Format) {
+    if format == Format::Table && !table.is_record() && table.is_empty() {
+        println!("no results");
+    } else {
+        println!("{}", table.render(format));
+    }
+}
+
+/// Decimals of the tokens of `ops` not carrying their own, by token id.
+/// Tokens the indexer does not report are left out.
+async fn token_decimals(
+    indexer: &Indexer,
+    ops: &[v1::krc20::TokenTransaction],
+) -> HashMap<String, u8> {
+    let mut decimals = HashMap::new();
+    for op in ops.iter().filter(|op| op.op != v1::krc20::Op::Deploy) {
+        let token_id = op.token_id();
+        if decimals.contains_key(token_id) {
+            continue;
+        }
+        match indexer.get_token(token_id).await {
+            Ok(token) if token.state != v1::State::Unused => {
+                decimals.insert(token_id.to_string(), token.dec);
+            }
+            Ok(_) => {}
+            Err(err) => log_warn!("Unable to resolve the decimals of `{token_id}`: {err}"),
+        }
+    }
+    decimals
+}
+
+/// Decimals of the amounts of `op`: its own for a `deploy`, those of its
+/// token otherwise.
+fn op_decimals(op: &v1::krc20::TokenTransaction, decimals: &HashMap<String, u8>) -> Option<u8> {
+    match (op.op, op.dec) {
+        (v1::krc20::Op::Deploy, Some(dec)) => u8::try_from(dec).ok(),
+        (v1::krc20::Op::Deploy, None) => Some(DEFAULT_DECIMALS),
+        _ => decimals.get(op.token_id()).copied(),
+    }
+}
+
+/// Amount in the token decimals, or labeled as base units if the decimals
+/// are unknown.
+fn amount(raw: Option<u128>, dec: Option<u8>) -> Cell {
+    match (raw, dec) {
+        (Some(raw), Some(dec)) => TokenAmount::new(raw, dec).into(),
+        (Some(raw), None) => format!("{raw} base units").into(),
+        (None, _) => None::<String>.into(),
+    }
+}
+
+fn op_record(op: &v1::krc20::TokenTransaction, dec: Option<u8>) -> Table {
+    Table::record(
+        &[
+            "protocol",
+            "op",
+            "tick",
+            "mode",
+            "name",
+            "ca",
+            "max",
+            "limit",
+            "dec",
+            "amount",
+            "from",
+            "to",
+            "op_score",
+            "hash",
+            "fee",
+            "tx_accept",
+            "op_accept",
+            "error",
+            "added",
+            "modified",
+        ],
+        vec![
+            op.protocol.to_string().into(),
+            op.op.to_string().into(),
+            op.tick.as_str().into(),
+            op.mode.map(|mode| mode.to_string()).into(),
+            op.name.clone().into(),
+            op.ca.clone().into(),
+            amount(op.max, dec),
+            amount(op.limit, dec),
+            op.dec.map(|dec| dec.to_string()).into(),
+            amount(op.amount, dec),
+            op.from.clone().into(),
+            op.to.clone().into(),
+            op.op_score.map(|score| score.to_string()).into(),
+            op.hash_rev.map(|hash| hash.to_string()).into(),
+            op.fee_rev.clone().into(),
+            op.tx_accept.clone().into(),
+            op.op_accept.clone().into(),
+            op.op_error.clone().into(),
+            op.mts_add.map(|mts| mts.to_string()).into(),
+            op.mts_mod.map(|mts| mts.to_string()).into(),
+        ],
+    )
+}
@@ -0,0 +1,172 @@
+//!
+//! Selection of the indexer endpoint.
+//!
+//! The endpoint is resolved from, in order of precedence, an explicit URL
+//! (e.g. a command line flag), the default of an explicitly selected
+//! [`Backend`], the [`INDEXER_URL_ENV`] environment variable and the public
+//! kasplex indexer of the network.
+//!
+
+use crate::imports::*;
+use sparkle_core::model::kasplex::v1;
+
+/// Environment variable overriding the default indexer URL.
+pub const INDEXER_URL_ENV: &str = "KASPLEX_INDEXER_URL";
+
+/// kasplex v1 API served by a local sparkled started with `--http`.
+pub const SPARKLED_INDEXER_URL: &str = "http://127.0.0.1:7676/v1";
+
+/// Service providing the kasplex v1 API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Public kasplex indexer.
+    #[default]
+    Kasplex,
+    /// KRC-20 ledger of a local sparkled, see [`SPARKLED_INDEXER_URL`].
+    Sparkled,
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(backend: &str) -> Result<Self> {
+        match backend.to_lowercase().as_str() {
+            "kasplex" => Ok(Backend::Kasplex),
+            "sparkled" => Ok(Backend::Sparkled),
+            _ => Err(Error::custom(format!(
+                "Invalid indexer backend `{backend}`, expecting kasplex or sparkled"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Kasplex => write!(f, "kasplex"),
+            Backend::Sparkled => write!(f, "sparkled"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Endpoint {
+    /// Explicitly selected backend, overriding the environment.
+    pub backend: Option<Backend>,
+    /// URL overriding the backend and the environment.
+    pub url: Option<Url>,
+}
+
+impl Endpoint {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend: Some(backend),
+            url: None,
+        }
+    }
+
+    pub fn with_backend(self, backend: Option<Backend>) -> Self {
+        Self { backend, ..self }
+    }
+
+    pub fn with_url(self, url: Option<Url>) -> Self {
+        Self { url, ..self }
+    }
+
+    /// Resolves the indexer URL for `network_id`, failing if no URL is
+    /// configured and the backend has no default for the network.
+    pub fn resolve(&self, network_id: &NetworkId) -> Result<Url> {
+        self.resolve_with(network_id, std::env::var(INDEXER_URL_ENV).ok())
+    }
+
+    /// Resolves the indexer URL with `env` as the value of
+    /// [`INDEXER_URL_ENV`].
+    fn resolve_with(&self, network_id: &NetworkId, env: Option<String>) -> Result<Url> {
+        if let Some(url) = &self.url {
+            return Ok(url.clone());
+        }
+        if self.backend.is_some() {
+            return self.default_url(network_id);
+        }
+
+        match env {
+            Some(url) if !url.trim().is_empty() => Ok(Url::from(url.trim())),
+            _ => self.default_url(network_id),
+        }
+    }
+
+    /// URL of the backend for `network_id` ignoring any override.
+    pub fn default_url(&self, network_id: &NetworkId) -> Result<Url> {
+        match self.backend.unwrap_or_default() {
+            Backend::Kasplex => v1::Network::try_from(network_id)
+                .map(Url::from)
+                .map_err(|_| Error::UnsupportedNetwork(network_id.to_string())),
+            Backend::Sparkled => Ok(Url::from(SPARKLED_INDEXER_URL)),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backend() {
+        assert_eq!("Kasplex".parse::<Backend>().unwrap(), Backend::Kasplex);
+        assert_eq!("sparkled".parse::<Backend>().unwrap(), Backend::Sparkled);
+        assert_eq!(Backend::Kasplex.to_string(), "kasplex");
+        assert_eq!(Backend::Sparkled.to_string(), "sparkled");
+        assert!("kaspa".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let testnet10 = NetworkId::with_suffix(NetworkType::Testnet, 10);
+        let devnet = NetworkId::new(NetworkType::Devnet);
+
+        let kasplex = Endpoint::default();
+        assert_eq!(
+            String::from(kasplex.default_url(&testnet10).unwrap()),
+            "https://tn10api.kasplex.org/v1"
+        );
+        assert!(matches!(
+            kasplex.default_url(&devnet),
+            Err(Error::UnsupportedNetwork(network)) if network == "devnet"
+        ));
+
+        // sparkled is available on every network
+        let sparkled = Endpoint::new(Backend::Sparkled);
+        assert_eq!(
+            String::from(sparkled.default_url(&devnet).unwrap()),
+            SPARKLED_INDEXER_URL
+        );
+
+        // an explicit URL takes precedence over the backend and the environment
+        let local = Url::from("http://127.0.0.1:8787/v1");
+        let env = Some("http://10.0.0.1:8787/v1".to_string());
+        let explicit = Endpoint::new(Backend::Kasplex);
+        let endpoint = explicit.clone().with_url(Some(local.clone()));
+        assert_eq!(endpoint.resolve_with(&devnet, env.clone()).unwrap(), local);
+
+        // an explicit backend takes precedence over the environment
+        assert_eq!(
+            String::from(explicit.resolve_with(&testnet10, env.clone()).unwrap()),
+            "https://tn10api.kasplex.org/v1"
+        );
+        assert_eq!(
+            String::from(sparkled.resolve_with(&testnet10, env.clone()).unwrap()),
+            SPARKLED_INDEXER_URL
+        );
+
+        // the environment takes precedence over the backend default
+        assert_eq!(
+            String::from(kasplex.resolve_with(&devnet, env).unwrap()),
+            "http://10.0.0.1:8787/v1"
+        );
+        assert_eq!(
+            String::from(kasplex.resolve_with(&testnet10, Some(" ".into())).unwrap()),
+            "https://tn10api.kasplex.org/v1"
+        );
+    }
+}
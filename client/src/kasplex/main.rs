@@ -15,6 +15,7 @@ cfg_if::cfg_if! {
     } else {
         pub mod args;
         pub mod client;
+        pub mod output;
 
         use client::Client;
         use workflow_log::prelude::*;
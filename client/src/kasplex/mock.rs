@@ -0,0 +1,469 @@
+//!
+//! Mock kasplex indexer.
+//!
+//! Serves the v1 API from the fixtures recorded in `fixtures/kasplex` so that
+//! the [`Indexer`](super::v1::Indexer) and the `kasplex` CLI can be exercised
+//! offline. List endpoints are paginated with offset cursors of
+//! [`MockConfig::page_size`] items; failures and slow responses are simulated
+//! by queuing [`Fault`]s or configuring a response delay.
+//!
+
+use crate::imports::*;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use sparkle_core::model::kasplex::v1::Tick;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use workflow_log::prelude::*;
+
+const INFO: &str = include_str!("../../fixtures/kasplex/info.json");
+const TOKENS: &str = include_str!("../../fixtures/kasplex/tokens.json");
+const BALANCES: &str = include_str!("../../fixtures/kasplex/balances.json");
+const OPLIST: &str = include_str!("../../fixtures/kasplex/oplist.json");
+const MARKET: &str = include_str!("../../fixtures/kasplex/market.json");
+
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// Number of items per page of the list endpoints.
+    pub page_size: usize,
+    /// Delay applied to every response.
+    pub delay: Option<Duration>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 50,
+            delay: None,
+        }
+    }
+}
+
+impl MockConfig {
+    pub fn with_page_size(self, page_size: usize) -> Self {
+        Self {
+            page_size: page_size.max(1),
+            ..self
+        }
+    }
+
+    pub fn with_delay(self, delay: Option<Duration>) -> Self {
+        Self { delay, ..self }
+    }
+}
+
+/// Failure simulated on a single request.
+#[derive(Debug, Clone)]
+pub enum Fault {
+    /// Responds with the HTTP status code.
+    Status(u16),
+    /// Responds successfully with the indexer `message`.
+    Message(String),
+    /// Delays the response.
+    Delay(Duration),
+}
+
+/// Responses served by the mock, as raw JSON so that tests can alter them.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    /// `/info` response.
+    pub info: Value,
+    /// Token details including the `holder` list, served by both
+    /// `/krc20/tokenlist` and `/krc20/token/{tick}`.
+    pub tokens: Vec<Value>,
+    /// Token balances by address.
+    pub balances: HashMap<String, Vec<Value>>,
+    /// Operations, most recent first.
+    pub ops: Vec<Value>,
+    pub market: Vec<Value>,
+}
+
+impl Fixtures {
+    /// Loads the recorded fixtures.
+    pub fn load() -> Result<Self> {
+        let parse = |name: &str, json: &str| {
+            serde_json::from_str::<Value>(json)
+                .map_err(|err| Error::custom(format!("Invalid fixture `{name}`: {err}")))
+        };
+        let list = |name: &str, json: &str| -> Result<Vec<Value>> {
+            match parse(name, json)? {
+                Value::Array(items) => Ok(items),
+                _ => Err(Error::custom(format!("Fixture `{name}` is not a list"))),
+            }
+        };
+
+        let balances = match parse("balances.json", BALANCES)? {
+            Value::Object(balances) => balances
+                .into_iter()
+                .map(|(address, balances)| match balances {
+                    Value::Array(balances) => Ok((address, balances)),
+                    _ => Err(Error::custom(format!("Invalid balances of `{address}`"))),
+                })
+                .collect::<Result<HashMap<_, _>>>()?,
+            _ => return Err(Error::custom("Fixture `balances.json` is not a map")),
+        };
+
+        Ok(Self {
+            info: parse("info.json", INFO)?,
+            tokens: list("tokens.json", TOKENS)?,
+            balances,
+            ops: list("oplist.json", OPLIST)?,
+            market: list("market.json", MARKET)?,
+        })
+    }
+}
+
+struct Inner {
+    config: Mutex<MockConfig>,
+    fixtures: RwLock<Fixtures>,
+    faults: Mutex<VecDeque<Fault>>,
+    requests: AtomicUsize,
+}
+
+impl Inner {
+    /// Page of `items` at the `next` or `prev` offset cursor of `query`.
+    fn page(&self, items: Vec<Value>, query: &HashMap<String, String>) -> Response {
+        let page_size = self.config.lock().unwrap().page_size;
+        let start = match query.get("next").or_else(|| query.get("prev")) {
+            None => 0,
+            Some(cursor) => match cursor.parse::<usize>() {
+                Ok(start) => start.min(items.len()),
+                Err(_) => return message("cursor invalid"),
+            },
+        };
+        let end = (start + page_size).min(items.len());
+
+        let next = if end < items.len() {
+            end.to_string()
+        } else {
+            String::new()
+        };
+        let prev = if start > 0 {
+            start.saturating_sub(page_size).to_string()
+        } else {
+            String::new()
+        };
+
+        Json(json!({
+            "message": "successful",
+            "next": next,
+            "prev": prev,
+            "result": items[start..end],
+        }))
+        .into_response()
+    }
+}
+
+/// Mock kasplex indexer serving the recorded fixtures, shut down on drop.
+pub struct MockIndexer {
+    inner: Arc<Inner>,
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockIndexer {
+    /// Starts the mock on an ephemeral local port.
+    pub async fn start(config: MockConfig) -> Result<Self> {
+        Self::bind("127.0.0.1:0", config).await
+    }
+
+    pub async fn bind(address: &str, config: MockConfig) -> Result<Self> {
+        let inner = Arc::new(Inner {
+            config: Mutex::new(config),
+            fixtures: RwLock::new(Fixtures::load()?),
+            faults: Mutex::new(VecDeque::new()),
+            requests: AtomicUsize::new(0),
+        });
+
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let app = router(inner.clone());
+
+        let (shutdown, receiver) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    receiver.await.ok();
+                })
+                .await
+            {
+                log_error!("Mock indexer error (serve): {err}");
+            }
+        });
+
+        Ok(Self {
+            inner,
+            address,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Base URL of the v1 API, to be passed to
+    /// [`Indexer::try_new`](super::v1::Indexer::try_new).
+    pub fn url(&self) -> Url {
+        format!("http://{}/v1", self.address).into()
+    }
+
+    /// Number of requests received.
+    pub fn requests(&self) -> usize {
+        self.inner.requests.load(Ordering::SeqCst)
+    }
+
+    /// Simulates `fault` on the next request; queued faults apply to
+    /// consecutive requests.
+    pub fn inject(&self, fault: Fault) {
+        self.inner.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Simulates `fault` on the next `count` requests.
+    pub fn inject_n(&self, fault: Fault, count: usize) {
+        let mut faults = self.inner.faults.lock().unwrap();
+        faults.extend(std::iter::repeat(fault).take(count));
+    }
+
+    /// Alters the served fixtures.
+    pub fn edit<F: FnOnce(&mut Fixtures)>(&self, edit: F) {
+        edit(&mut self.inner.fixtures.write().unwrap());
+    }
+
+    pub fn set_delay(&self, delay: Option<Duration>) {
+        self.inner.config.lock().unwrap().delay = delay;
+    }
+
+    pub fn set_page_size(&self, page_size: usize) {
+        self.inner.config.lock().unwrap().page_size = page_size.max(1);
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+impl Drop for MockIndexer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn router(inner: Arc<Inner>) -> Router {
+    Router::new()
+        .route("/v1/info", get(info))
+        .route("/v1/krc20/tokenlist", get(token_list))
+        .route("/v1/krc20/token/:tick", get(token))
+        .route("/v1/krc20/address/:address/tokenlist", get(balance_list))
+        .route("/v1/krc20/address/:address/token/:tick", get(balance))
+        .route("/v1/krc20/oplist", get(op_list))
+        .route("/v1/krc20/op/:id", get(op))
+        .route("/v1/krc20/market/:tick", get(market))
+        .layer(middleware::from_fn_with_state(inner.clone(), simulate))
+        .with_state(inner)
+}
+
+/// Counts requests and applies the configured delay and queued faults.
+async fn simulate(State(inner): State<Arc<Inner>>, request: Request, next: Next) -> Response {
+    inner.requests.fetch_add(1, Ordering::SeqCst);
+
+    let delay = inner.config.lock().unwrap().delay;
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let fault = inner.faults.lock().unwrap().pop_front();
+    match fault {
+        Some(Fault::Status(status)) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, "simulated failure").into_response()
+        }
+        Some(Fault::Message(text)) => message(&text),
+        Some(Fault::Delay(delay)) => {
+            tokio::time::sleep(delay).await;
+            next.run(request).await
+        }
+        None => next.run(request).await,
+    }
+}
+
+fn message(message: &str) -> Response {
+    Json(json!({ "message": message })).into_response()
+}
+
+fn field<'v>(item: &'v Value, name: &str) -> &'v str {
+    item.get(name).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn matches_tick(item: &Value, tick: &str) -> bool {
+    field(item, "tick").eq_ignore_ascii_case(tick)
+}
+
+async fn info(State(inner): State<Arc<Inner>>) -> Response {
+    Json(inner.fixtures.read().unwrap().info.clone()).into_response()
+}
+
+async fn token_list(
+    State(inner): State<Arc<Inner>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let tokens = inner.fixtures.read().unwrap().tokens.clone();
+    inner.page(tokens, &query)
+}
+
+async fn token(State(inner): State<Arc<Inner>>, Path(tick): Path<String>) -> Response {
+    let Ok(tick) = Tick::from_str(&tick) else {
+        return message("tick invalid");
+    };
+
+    let token = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .tokens
+        .iter()
+        .find(|token| matches_tick(token, tick.as_str()))
+        .cloned()
+        // the indexer only reports the tick and state of undeployed tokens
+        .unwrap_or_else(|| json!({ "tick": tick, "state": "unused" }));
+
+    Json(json!({ "message": "successful", "result": token })).into_response()
+}
+
+async fn balance_list(
+    State(inner): State<Arc<Inner>>,
+    Path(address): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let balances = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .balances
+        .get(&address)
+        .cloned()
+        .unwrap_or_default();
+    inner.page(balances, &query)
+}
+
+async fn balance(
+    State(inner): State<Arc<Inner>>,
+    Path((address, tick)): Path<(String, String)>,
+) -> Response {
+    let balances = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .balances
+        .get(&address)
+        .into_iter()
+        .flatten()
+        .filter(|balance| matches_tick(balance, &tick))
+        .cloned()
+        .collect::<Vec<_>>();
+    Json(json!({ "message": "successful", "result": balances })).into_response()
+}
+
+async fn op_list(
+    State(inner): State<Arc<Inner>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let ops = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .ops
+        .iter()
+        .filter(|op| {
+            query.get("address").map_or(true, |address| {
+                field(op, "from") == address || field(op, "to") == address
+            }) && query
+                .get("tick")
+                .map_or(true, |tick| matches_tick(op, tick))
+                && query
+                    .get("op")
+                    .map_or(true, |name| field(op, "op").eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .collect();
+    inner.page(ops, &query)
+}
+
+async fn op(State(inner): State<Arc<Inner>>, Path(id): Path<String>) -> Response {
+    let ops = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .ops
+        .iter()
+        .filter(|op| field(op, "hashRev") == id || field(op, "opScore") == id)
+        .cloned()
+        .collect::<Vec<_>>();
+    Json(json!({ "message": "successful", "result": ops })).into_response()
+}
+
+async fn market(
+    State(inner): State<Arc<Inner>>,
+    Path(tick): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let listings = inner
+        .fixtures
+        .read()
+        .unwrap()
+        .market
+        .iter()
+        .filter(|listing| {
+            matches_tick(listing, &tick)
+                && query
+                    .get("address")
+                    .map_or(true, |address| field(listing, "from") == address)
+        })
+        .cloned()
+        .collect();
+    inner.page(listings, &query)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        let fixtures = Fixtures::load().unwrap();
+        assert_eq!(fixtures.tokens.len(), 5);
+        assert_eq!(
+            fixtures.info["result"]["tokenTotal"],
+            fixtures.tokens.len().to_string()
+        );
+        assert_eq!(
+            fixtures.info["result"]["opTotal"],
+            fixtures.ops.len().to_string()
+        );
+
+        // holder amounts add up to the minted supply
+        for token in &fixtures.tokens {
+            let held = token["holder"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|holder| field(holder, "amount").parse::<u128>().unwrap())
+                .sum::<u128>();
+            assert_eq!(held, field(token, "minted").parse::<u128>().unwrap());
+        }
+    }
+}
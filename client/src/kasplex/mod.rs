@@ -1,11 +1,28 @@
+pub mod audit;
+pub mod endpoint;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mock;
+pub mod paginator;
+pub mod transport;
+
+/// The indexer reports `successful` (older deployments `success`).
+pub(crate) fn is_success(message: &str) -> bool {
+    message == "successful" || message == "success"
+}
+
 pub mod v1 {
 
+    use super::paginator::{self, Cursor, Page};
+    use super::transport::{Transport, TransportConfig};
     use crate::imports::*;
     use crate::result::Result;
+    use futures::stream::{Stream, TryStreamExt};
+    use sparkle_core::hash::Hash;
     use sparkle_core::model::kasplex::v1;
 
     struct Inner {
         url: Url,
+        transport: Transport,
     }
 
     #[derive(Clone)]
@@ -15,83 +32,140 @@ pub mod v1 {
 
     impl Indexer {
         pub fn try_new(url: Url) -> Result<Self> {
+            Self::try_with_config(url, TransportConfig::default())
+        }
+
+        /// Creates an indexer client with custom timeout, retry, rate limit
+        /// and cache settings.
+        pub fn try_with_config(url: Url, config: TransportConfig) -> Result<Self> {
             Ok(Self {
-                inner: Arc::new(Inner { url }),
+                inner: Arc::new(Inner {
+                    url,
+                    transport: Transport::new(config),
+                }),
             })
         }
 
+        async fn get_json<T: serde::de::DeserializeOwned>(
+            &self,
+            url: impl Into<String>,
+        ) -> Result<T> {
+            self.inner.transport.get_json(url).await
+        }
+
         pub async fn get_indexer_status(&self) -> Result<v1::IndexerStatus> {
-            let response =
-                get_json::<v1::IndexerStatusResponse>(self.inner.url.join("/info")).await?;
+            let response = self
+                .get_json::<v1::IndexerStatusResponse>(self.inner.url.join("/info"))
+                .await?;
 
-            if response.message != "success" {
-                Err(Error::IndexerError(response.message))
-            } else {
-                Ok(response.result)
-            }
+            Ok(response.result)
         }
 
         pub async fn get_token_list_page(
             &self,
-            cursor: Option<String>,
+            cursor: Option<Cursor>,
         ) -> Result<v1::krc20::TokenListResponse> {
-            let mut url = self.inner.url.join("/krc20/tokenlist");
+            let url = self.inner.url.join("/krc20/tokenlist");
+            let params = cursor.iter().map(Cursor::param).collect::<Vec<_>>();
 
-            if let Some(cursor) = cursor {
-                url = url.join(format!("?next={}", cursor));
-            }
-
-            let response = get_json::<v1::krc20::TokenListResponse>(url).await?;
+            let response = self
+                .get_json::<v1::krc20::TokenListResponse>(url.with_query(&params))
+                .await?;
             Ok(response)
         }
 
+        /// Pages of the token list starting at `start`.
+        pub fn token_list_pages(
+            &self,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<Page<v1::krc20::Token>>> {
+            let indexer = self.clone();
+            paginator::pages(start, move |cursor| {
+                let indexer = indexer.clone();
+                async move { indexer.get_token_list_page(cursor).await }
+            })
+        }
+
+        pub fn token_list_stream(
+            &self,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<v1::krc20::Token>> {
+            paginator::items(self.token_list_pages(start))
+        }
+
         pub async fn get_token_list(&self) -> Result<Vec<v1::krc20::Token>> {
-            let mut list = Vec::new();
-            let mut cursor = None;
-            loop {
-                let v1::krc20::TokenListResponse {
-                    message,
-                    next,
-                    prev: _,
-                    result,
-                } = self.get_token_list_page(cursor).await?;
-
-                if message != "success" {
-                    return Err(Error::IndexerError(message));
-                }
+            self.token_list_stream(None).try_collect().await
+        }
 
-                let len = result.len();
-                list.extend(result.into_iter());
-                if len < 50 {
-                    break;
-                } else {
-                    cursor = Some(next);
-                }
-            }
+        pub async fn get_token(&self, tick: &str) -> Result<v1::krc20::TokenHolderResult> {
+            let url = self.inner.url.join(format!("/krc20/token/{tick}"));
+            let response = self.get_json::<v1::krc20::TokenHolderResponse>(url).await?;
+
+            Ok(response.result)
+        }
+
+        /// Returns the top holders of the token `tick`.
+        pub async fn get_token_holders(&self, tick: &str) -> Result<Vec<v1::krc20::TokenHolder>> {
+            Ok(self.get_token(tick).await?.holder)
+        }
 
-            Ok(list)
+        /// Returns the state of the token `tick` or `None` if it is not deployed.
+        pub async fn get_token_state(&self, tick: &str) -> Result<Option<v1::TokenState>> {
+            let state = v1::TokenState::try_from(&self.get_token(tick).await?)
+                .map_err(sparkle_core::error::Error::from)?;
+            Ok(state.is_deployed().then_some(state))
         }
 
         // https://tn11api.kasplex.org/v1/krc20/address/{address}/token/{tick}
         // https://tn11api.kasplex.org/v1/krc20/address/{address}/tokenlist
 
-        pub async fn get_token_balance_list_by_address(
+        pub async fn get_token_balance_list_page(
             &self,
             address: &Address,
-        ) -> Result<Vec<v1::krc20::TokenBalance>> {
+            cursor: Option<Cursor>,
+        ) -> Result<v1::krc20::TokenBalanceListByAddressResponse> {
             let url = self
                 .inner
                 .url
                 .join(format!("/krc20/address/{address}/tokenlist"));
+            let params = cursor.iter().map(Cursor::param).collect::<Vec<_>>();
 
-            // TODO: loop over paginated results
-            let response = get_json::<v1::krc20::TokenBalanceListByAddressResponse>(url).await?;
+            let response = self
+                .get_json::<v1::krc20::TokenBalanceListByAddressResponse>(url.with_query(&params))
+                .await?;
+            Ok(response)
+        }
 
-            if response.message != "success" {
-                Err(Error::IndexerError(response.message))
-            } else {
-                Ok(response.result)
-            }
+        /// Pages of the token balances of `address` starting at `start`.
+        pub fn token_balance_list_pages(
+            &self,
+            address: &Address,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<Page<v1::krc20::TokenBalance>>> {
+            let indexer = self.clone();
+            let address = address.clone();
+            paginator::pages(start, move |cursor| {
+                let indexer = indexer.clone();
+                let address = address.clone();
+                async move { indexer.get_token_balance_list_page(&address, cursor).await }
+            })
+        }
+
+        pub fn token_balance_list_stream(
+            &self,
+            address: &Address,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<v1::krc20::TokenBalance>> {
+            paginator::items(self.token_balance_list_pages(address, start))
+        }
+
+        pub async fn get_token_balance_list_by_address(
+            &self,
+            address: &Address,
+        ) -> Result<Vec<v1::krc20::TokenBalance>> {
+            self.token_balance_list_stream(address, None)
+                .try_collect()
+                .await
         }
 
         pub async fn get_token_balance_by_address(
@@ -103,13 +177,149 @@ pub mod v1 {
                 .inner
                 .url
                 .join(format!("/krc20/address/{address}/token/{tick}"));
-            let response = get_json::<v1::krc20::TokenBalanceResponse>(url).await?;
+            let response = self
+                .get_json::<v1::krc20::TokenBalanceResponse>(url)
+                .await?;
+
+            Ok(response.result)
+        }
+
+        pub async fn get_op_list_page(
+            &self,
+            filter: &v1::krc20::OpListFilter,
+            cursor: Option<Cursor>,
+        ) -> Result<v1::krc20::TokenTransactionResponse> {
+            let url = self.inner.url.join("/krc20/oplist");
+            let mut params = filter.params();
+            if let Some((name, value)) = cursor.as_ref().map(Cursor::param) {
+                params.push((name, value.to_string()));
+            }
+
+            let response = self
+                .get_json::<v1::krc20::TokenTransactionResponse>(url.with_query(&params))
+                .await?;
+            Ok(response)
+        }
+
+        /// Pages of the operations matching `filter` starting at `start`.
+        pub fn op_list_pages(
+            &self,
+            filter: &v1::krc20::OpListFilter,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<Page<v1::krc20::TokenTransaction>>> {
+            let indexer = self.clone();
+            let filter = filter.clone();
+            paginator::pages(start, move |cursor| {
+                let indexer = indexer.clone();
+                let filter = filter.clone();
+                async move { indexer.get_op_list_page(&filter, cursor).await }
+            })
+        }
+
+        pub fn op_list_stream(
+            &self,
+            filter: &v1::krc20::OpListFilter,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<v1::krc20::TokenTransaction>> {
+            paginator::items(self.op_list_pages(filter, start))
+        }
+
+        /// Returns all operations matching `filter`, most recent first.
+        pub async fn get_op_list(
+            &self,
+            filter: &v1::krc20::OpListFilter,
+        ) -> Result<Vec<v1::krc20::TokenTransaction>> {
+            self.op_list_stream(filter, None).try_collect().await
+        }
+
+        /// Returns the operation inscribed by the reveal transaction `hash`.
+        pub async fn get_op_by_hash(
+            &self,
+            hash: &Hash,
+        ) -> Result<Option<v1::krc20::TokenTransaction>> {
+            self.get_op(hash).await
+        }
 
-            if response.message != "success" {
-                Err(Error::IndexerError(response.message))
-            } else {
-                Ok(response.result)
+        pub async fn get_op_by_score(
+            &self,
+            op_score: v1::OpScore,
+        ) -> Result<Option<v1::krc20::TokenTransaction>> {
+            self.get_op(op_score).await
+        }
+
+        async fn get_op<I: fmt::Display>(
+            &self,
+            id: I,
+        ) -> Result<Option<v1::krc20::TokenTransaction>> {
+            let url = self.inner.url.join(format!("/krc20/op/{id}"));
+            let response = self
+                .get_json::<v1::krc20::TokenTransactionIdResponse>(url)
+                .await?;
+
+            Ok(response.result.into_iter().next())
+        }
+
+        pub async fn get_market_listing_page(
+            &self,
+            tick: &str,
+            seller: Option<&Address>,
+            cursor: Option<Cursor>,
+        ) -> Result<v1::krc20::MarketListingResponse> {
+            let url = self.inner.url.join(format!("/krc20/market/{tick}"));
+            let mut params = Vec::new();
+            if let Some(seller) = seller {
+                params.push(("address", seller.to_string()));
             }
+            if let Some((name, value)) = cursor.as_ref().map(Cursor::param) {
+                params.push((name, value.to_string()));
+            }
+
+            let response = self
+                .get_json::<v1::krc20::MarketListingResponse>(url.with_query(&params))
+                .await?;
+            Ok(response)
+        }
+
+        /// Pages of the open listings of `tick` starting at `start`.
+        pub fn market_listing_pages(
+            &self,
+            tick: &str,
+            seller: Option<&Address>,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<Page<v1::krc20::MarketListing>>> {
+            let indexer = self.clone();
+            let tick = tick.to_string();
+            let seller = seller.cloned();
+            paginator::pages(start, move |cursor| {
+                let indexer = indexer.clone();
+                let tick = tick.clone();
+                let seller = seller.clone();
+                async move {
+                    indexer
+                        .get_market_listing_page(&tick, seller.as_ref(), cursor)
+                        .await
+                }
+            })
+        }
+
+        pub fn market_listing_stream(
+            &self,
+            tick: &str,
+            seller: Option<&Address>,
+            start: Option<Cursor>,
+        ) -> impl Stream<Item = Result<v1::krc20::MarketListing>> {
+            paginator::items(self.market_listing_pages(tick, seller, start))
+        }
+
+        /// Returns the open listings of `tick`, optionally of a single seller.
+        pub async fn get_market_listings(
+            &self,
+            tick: &str,
+            seller: Option<&Address>,
+        ) -> Result<Vec<v1::krc20::MarketListing>> {
+            self.market_listing_stream(tick, seller, None)
+                .try_collect()
+                .await
         }
     }
 
@@ -117,20 +327,199 @@ pub mod v1 {
     #[cfg(test)]
     mod test {
 
+        use super::super::mock::{Fault, MockConfig, MockIndexer};
+        use super::super::transport::{IndexerError, TransportConfig};
         use super::*;
+        use std::time::Duration;
+
+        const BOB: &str = "kaspatest:qzqmvd7clnfvdknrt8nfvvgn5ytsmeu4ujmjtwzdrc95elv7ckxwjmrgetehx";
+        const CAROL: &str =
+            "kaspatest:qpxzdkg8fsna38k7tynsczkpfdc7qud32gu4r8m4ga9j7waxxjql2yf8crp6n";
+
+        fn config() -> TransportConfig {
+            TransportConfig::default()
+                .with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+                .with_rate_limit(None)
+                .with_cache(None, 0)
+        }
+
+        /// Mock indexer serving pages of 2 items and a client of it retrying
+        /// promptly, without rate limit nor cache.
+        async fn mock(config: TransportConfig) -> (MockIndexer, Indexer) {
+            let mock = MockIndexer::start(MockConfig::default().with_page_size(2))
+                .await
+                .unwrap();
+            let indexer = Indexer::try_with_config(mock.url(), config).unwrap();
+            (mock, indexer)
+        }
 
         #[tokio::test]
         async fn test_get_indexer_status() {
-            let indexer = Indexer::try_new(v1::Network::Testnet11.into()).unwrap();
+            let (_mock, indexer) = mock(config()).await;
             let result = indexer.get_indexer_status().await.unwrap();
-            println!("{:?}", result);
+            assert_eq!(result.token_total, 5);
+            assert_eq!(result.op_total, 16);
         }
 
         #[tokio::test]
         async fn test_get_token_list() {
-            let indexer = Indexer::try_new(v1::Network::Testnet11.into()).unwrap();
+            let (mock, indexer) = mock(config()).await;
             let result = indexer.get_token_list().await.unwrap();
-            println!("{:?}", result);
+            let ticks = result
+                .iter()
+                .map(|token| token.tick.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(ticks, ["TOITOI", "KEKE", "SPARKL", "NACHO", "KASPY"]);
+            assert_eq!(mock.requests(), 3);
+        }
+
+        #[tokio::test]
+        async fn test_get_token() {
+            let (_mock, indexer) = mock(config()).await;
+            let token = indexer.get_token("toitoi").await.unwrap();
+            assert_eq!(token.minted_amount().to_string(), "4,000");
+            assert_eq!(token.holder.len(), 3);
+
+            assert!(indexer.get_token_state("KEKE").await.unwrap().is_some());
+            assert!(indexer.get_token_state("ZZZZ").await.unwrap().is_none());
+            assert!(matches!(
+                indexer.get_token("KAS1").await,
+                Err(Error::IndexerError(IndexerError::Message(message))) if message == "tick invalid"
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_get_token_balances() {
+            let (_mock, indexer) = mock(config()).await;
+            let carol = Address::try_from(CAROL).unwrap();
+
+            let balances = indexer
+                .get_token_balance_list_by_address(&carol)
+                .await
+                .unwrap();
+            assert_eq!(balances.len(), 2);
+            assert_eq!(balances[0].balance_amount().to_string(), "40");
+            assert_eq!(balances[0].locked_amount().to_string(), "10");
+
+            let balances = indexer
+                .get_token_balance_by_address(&carol, "sparkl")
+                .await
+                .unwrap();
+            assert_eq!(balances[0].balance_amount().to_string(), "1,500");
+        }
+
+        #[tokio::test]
+        async fn test_get_op_list_page() {
+            let (_mock, indexer) = mock(config()).await;
+            let filter = v1::krc20::OpListFilter::default().op(v1::krc20::Op::Deploy);
+            let result = indexer.get_op_list_page(&filter, None).await.unwrap();
+            assert_eq!(result.result.len(), 2);
+            assert!(!result.next.is_empty());
+            assert_eq!(indexer.get_op_list(&filter).await.unwrap().len(), 5);
+
+            let filter = v1::krc20::OpListFilter::default()
+                .address(BOB)
+                .tick(v1::Tick::from_str("TOITOI").unwrap());
+            let ops = indexer.get_op_list(&filter).await.unwrap();
+            assert_eq!(ops.len(), 1);
+            assert_eq!(ops[0].op, v1::krc20::Op::Mint);
+
+            let op = &result.result[0];
+            let by_hash = indexer
+                .get_op_by_hash(op.hash_rev.as_ref().unwrap())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(by_hash.op_score, op.op_score);
+            let by_score = indexer
+                .get_op_by_score(op.op_score.unwrap())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(by_score.hash_rev, op.hash_rev);
+            assert!(indexer
+                .get_op_by_hash(&Hash::from([0; 32]))
+                .await
+                .unwrap()
+                .is_none());
+        }
+
+        #[tokio::test]
+        async fn test_get_market_listings() {
+            let (_mock, indexer) = mock(config()).await;
+            let listings = indexer.get_market_listings("TOITOI", None).await.unwrap();
+            assert_eq!(listings.len(), 1);
+            let bob = Address::try_from(BOB).unwrap();
+            let listings = indexer
+                .get_market_listings("TOITOI", Some(&bob))
+                .await
+                .unwrap();
+            assert!(listings.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_transport_retry() {
+            let (mock, indexer) = mock(config().with_retries(2)).await;
+
+            mock.inject_n(Fault::Status(503), 2);
+            assert!(indexer.get_indexer_status().await.is_ok());
+            assert_eq!(mock.requests(), 3);
+
+            mock.inject_n(Fault::Status(429), 3);
+            assert!(matches!(
+                indexer.get_indexer_status().await,
+                Err(Error::IndexerError(IndexerError::Status {
+                    status: 429,
+                    ..
+                }))
+            ));
+            assert_eq!(mock.requests(), 6);
+
+            // client errors are not retried
+            mock.inject(Fault::Status(404));
+            assert!(indexer.get_indexer_status().await.is_err());
+            assert_eq!(mock.requests(), 7);
+
+            mock.inject(Fault::Message("rate limited".to_string()));
+            assert!(matches!(
+                indexer.get_indexer_status().await,
+                Err(Error::IndexerError(IndexerError::Message(message))) if message == "rate limited"
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_transport_timeout() {
+            let config = config()
+                .with_timeout(Duration::from_millis(50))
+                .with_retries(1);
+            let (mock, indexer) = mock(config).await;
+
+            mock.set_delay(Some(Duration::from_millis(500)));
+            assert!(matches!(
+                indexer.get_indexer_status().await,
+                Err(Error::IndexerError(IndexerError::Timeout(_)))
+            ));
+            assert_eq!(mock.requests(), 2);
+
+            mock.set_delay(None);
+            mock.inject(Fault::Delay(Duration::from_millis(500)));
+            assert!(indexer.get_indexer_status().await.is_ok());
+        }
+
+        #[tokio::test]
+        async fn test_transport_cache() {
+            let config = config().with_cache(Some(Duration::from_secs(60)), 16);
+            let (mock, indexer) = mock(config).await;
+
+            indexer.get_token("TOITOI").await.unwrap();
+            indexer.get_token("toitoi").await.unwrap();
+            indexer.get_token("TOITOI").await.unwrap();
+            assert_eq!(mock.requests(), 2);
+
+            // failures are not cached
+            mock.inject(Fault::Message("rate limited".to_string()));
+            assert!(indexer.get_token("KEKE").await.is_err());
+            assert!(indexer.get_token("KEKE").await.is_ok());
         }
     }
 }
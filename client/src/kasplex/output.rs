@@ -0,0 +1,296 @@
+//!
+//! Output formats of the `kasplex` CLI.
+//!
+//! Commands build a [`Table`] of their results, rendered as an aligned text
+//! table for terminals or as JSON or CSV for scripts. Token amounts are
+//! rendered with thousands separators in text tables only.
+//!
+
+use pad::{Alignment, PadStr};
+use sparkle_core::model::TokenAmount;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "invalid format `{format}`, expecting table, json or csv"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Table => write!(f, "table"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+pub enum Cell {
+    Text(String),
+    Amount(TokenAmount),
+}
+
+impl Cell {
+    fn render(&self, format: Format) -> String {
+        match (self, format) {
+            (Cell::Text(text), _) => text.clone(),
+            (Cell::Amount(amount), Format::Table) => amount.to_string(),
+            (Cell::Amount(amount), _) => amount.to_string().replace(',', ""),
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<TokenAmount> for Cell {
+    fn from(amount: TokenAmount) -> Self {
+        Cell::Amount(amount)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(cell: Option<T>) -> Self {
+        cell.map(Into::into)
+            .unwrap_or_else(|| Cell::Text(String::new()))
+    }
+}
+
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+    /// Render a single record as `column: value` lines in text tables.
+    record: bool,
+}
+
+impl Table {
+    pub fn new(columns: &[&'static str]) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+            record: false,
+        }
+    }
+
+    /// Table of a single record.
+    pub fn record(columns: &[&'static str], cells: Vec<Cell>) -> Self {
+        let mut table = Self::new(columns);
+        table.push(cells);
+        Self {
+            record: true,
+            ..table
+        }
+    }
+
+    pub fn push(&mut self, cells: Vec<Cell>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.rows.push(cells);
+    }
+
+    pub fn is_record(&self) -> bool {
+        self.record
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.render(format))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        match format {
+            Format::Table if self.record => self.render_record(&rows),
+            Format::Table => self.render_table(&rows),
+            Format::Json => self.render_json(rows),
+            Format::Csv => self.render_csv(&rows),
+        }
+    }
+
+    fn render_record(&self, rows: &[Vec<String>]) -> String {
+        let width = self
+            .columns
+            .iter()
+            .map(|column| column.len())
+            .max()
+            .unwrap_or_default();
+        rows.iter()
+            .flat_map(|row| self.columns.iter().zip(row))
+            .map(|(column, value)| {
+                let column = column.pad_to_width_with_alignment(width, Alignment::Right);
+                format!("{column}: {value}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn render_table(&self, rows: &[Vec<String>]) -> String {
+        let widths = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                rows.iter()
+                    .map(|row| row[index].chars().count())
+                    .chain([column.len()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        let line = |cells: Vec<&str>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| cell.pad_to_width(*width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        let dashes = widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>();
+        let header = line(self.columns.clone());
+        let separator = line(dashes.iter().map(String::as_str).collect());
+        [header, separator]
+            .into_iter()
+            .chain(
+                rows.iter()
+                    .map(|row| line(row.iter().map(String::as_str).collect())),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn render_json(&self, rows: Vec<Vec<String>>) -> String {
+        let records = rows
+            .into_iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|column| column.to_string())
+                    .zip(row.into_iter().map(serde_json::Value::String))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect::<Vec<_>>();
+
+        if self.record && records.len() == 1 {
+            serde_json::to_string_pretty(&records[0])
+        } else {
+            serde_json::to_string_pretty(&records)
+        }
+        .unwrap_or_default()
+    }
+
+    fn render_csv(&self, rows: &[Vec<String>]) -> String {
+        let line = |cells: &mut dyn Iterator<Item = &str>| {
+            cells.map(csv_escape).collect::<Vec<_>>().join(",")
+        };
+
+        [line(&mut self.columns.iter().copied())]
+            .into_iter()
+            .chain(
+                rows.iter()
+                    .map(|row| line(&mut row.iter().map(String::as_str))),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["tick", "amount", "note"]);
+        table.push(vec![
+            "TOITOI".into(),
+            TokenAmount::new(250_000_000_000, 8).into(),
+            "a, \"quoted\" note".into(),
+        ]);
+        table.push(vec![
+            "KEKE".into(),
+            TokenAmount::new(5, 0).into(),
+            None::<String>.into(),
+        ]);
+        table
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("JSON".parse::<Format>(), Ok(Format::Json));
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let text = table().render(Format::Table);
+        assert_eq!(
+            text.lines().nth(2).unwrap(),
+            "TOITOI  2,500   a, \"quoted\" note"
+        );
+
+        let csv = table().render(Format::Csv);
+        assert_eq!(
+            csv,
+            "tick,amount,note\nTOITOI,2500,\"a, \"\"quoted\"\" note\"\nKEKE,5,"
+        );
+
+        let json =
+            serde_json::from_str::<serde_json::Value>(&table().render(Format::Json)).unwrap();
+        assert_eq!(json[0]["amount"], "2500");
+        assert_eq!(json[1]["tick"], "KEKE");
+
+        let record = Table::record(&["tick", "dec"], vec!["KEKE".into(), "8".into()]);
+        assert_eq!(record.render(Format::Table), "tick: KEKE\n dec: 8");
+        let json = serde_json::from_str::<serde_json::Value>(&record.render(Format::Json)).unwrap();
+        assert_eq!(json["dec"], "8");
+    }
+}
@@ -0,0 +1,233 @@
+//!
+//! Cursor pagination of kasplex list endpoints.
+//!
+//! List endpoints return a page of results along with `next` and `prev`
+//! cursors. [`pages`] follows the cursors in one direction until the
+//! listing is exhausted, yielding each page with the [`Cursor`] of the
+//! following one so that a listing can be resumed later; [`items`]
+//! flattens the pages into individual results.
+//!
+
+use super::transport::IndexerError;
+use crate::imports::*;
+use crate::result::Result;
+use futures::stream::{self, Stream, TryStreamExt};
+use sparkle_core::model::kasplex::v1::krc20;
+use std::future::Future;
+
+/// Position in a paginated listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cursor {
+    /// Page at a `next` cursor, the listing continues forward.
+    Next(String),
+    /// Page at a `prev` cursor, the listing continues backward.
+    Prev(String),
+}
+
+impl Cursor {
+    /// Query parameter selecting the page at this cursor.
+    pub fn param(&self) -> (&'static str, &str) {
+        match self {
+            Cursor::Next(cursor) => ("next", cursor),
+            Cursor::Prev(cursor) => ("prev", cursor),
+        }
+    }
+}
+
+/// Response of a paginated endpoint.
+pub trait Paginated {
+    type Item;
+
+    fn message(&self) -> &str;
+    fn next(&self) -> &str;
+    fn prev(&self) -> &str;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the following page; `None` once the listing is exhausted.
+    pub cursor: Option<Cursor>,
+}
+
+enum State {
+    Fetch(Option<Cursor>),
+    Done,
+}
+
+/// Pages of a listing starting at `start` (the first page if `None`),
+/// fetched by `fetch` and following cursors in the direction of `start`.
+pub fn pages<P, F, Fut>(
+    start: Option<Cursor>,
+    fetch: F,
+) -> impl Stream<Item = Result<Page<P::Item>>>
+where
+    P: Paginated,
+    F: FnMut(Option<Cursor>) -> Fut,
+    Fut: Future<Output = Result<P>>,
+{
+    stream::unfold(
+        (fetch, State::Fetch(start)),
+        |(mut fetch, state)| async move {
+            let cursor = match state {
+                State::Fetch(cursor) => cursor,
+                State::Done => return None,
+            };
+
+            let page = match fetch(cursor.clone()).await {
+                Ok(page) if super::is_success(page.message()) => page,
+                Ok(page) => {
+                    let err = IndexerError::Message(page.message().to_string()).into();
+                    return Some((Err(err), (fetch, State::Done)));
+                }
+                Err(err) => return Some((Err(err), (fetch, State::Done))),
+            };
+
+            let following = match cursor {
+                Some(Cursor::Prev(_)) => Cursor::Prev(page.prev().to_string()),
+                _ => Cursor::Next(page.next().to_string()),
+            };
+            let items = page.into_items();
+            // stop on empty pages and on cursors that do not advance
+            let following = (!items.is_empty()
+                && !following.param().1.is_empty()
+                && Some(&following) != cursor.as_ref())
+            .then_some(following);
+
+            let state = match &following {
+                Some(following) => State::Fetch(Some(following.clone())),
+                None => State::Done,
+            };
+            Some((
+                Ok(Page {
+                    items,
+                    cursor: following,
+                }),
+                (fetch, state),
+            ))
+        },
+    )
+}
+
+/// Flattens a stream of pages into a stream of items.
+pub fn items<T>(pages: impl Stream<Item = Result<Page<T>>>) -> impl Stream<Item = Result<T>> {
+    pages
+        .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+macro_rules! paginated {
+    ($response:ty, $item:ty) => {
+        impl Paginated for $response {
+            type Item = $item;
+
+            fn message(&self) -> &str {
+                &self.message
+            }
+
+            fn next(&self) -> &str {
+                &self.next
+            }
+
+            fn prev(&self) -> &str {
+                &self.prev
+            }
+
+            fn into_items(self) -> Vec<Self::Item> {
+                self.result
+            }
+        }
+    };
+}
+
+paginated!(krc20::TokenListResponse, krc20::Token);
+paginated!(
+    krc20::TokenBalanceListByAddressResponse,
+    krc20::TokenBalance
+);
+paginated!(krc20::TokenTransactionResponse, krc20::TokenTransaction);
+paginated!(krc20::MarketListingResponse, krc20::MarketListing);
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+
+    struct Response {
+        message: String,
+        next: String,
+        prev: String,
+        result: Vec<u32>,
+    }
+
+    paginated!(Response, u32);
+
+    /// Pages of 2 items out of 0..5, the cursor being the first item.
+    async fn fetch(cursor: Option<Cursor>) -> Result<Response> {
+        let start = match cursor {
+            None => 0,
+            Some(Cursor::Next(cursor)) | Some(Cursor::Prev(cursor)) => cursor.parse().unwrap(),
+        };
+        let result = (start..5).take(2).collect::<Vec<u32>>();
+        Ok(Response {
+            message: "successful".to_string(),
+            next: result
+                .last()
+                .map(|last| (last + 1).to_string())
+                .unwrap_or_default(),
+            prev: String::new(),
+            result,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_pages() {
+        let collected = pages(None, fetch).collect::<Vec<_>>().await;
+        let cursors = collected
+            .into_iter()
+            .map(|page| page.unwrap().cursor)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cursors,
+            [
+                Some(Cursor::Next("2".to_string())),
+                Some(Cursor::Next("4".to_string())),
+                Some(Cursor::Next("5".to_string())),
+                None
+            ]
+        );
+
+        let all = items(pages(None, fetch))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(all, [0, 1, 2, 3, 4]);
+
+        // resume from a saved cursor
+        let resumed = items(pages(Some(Cursor::Next("3".to_string())), fetch))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(resumed, [3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_pages_error() {
+        let failing = |_| async {
+            Ok(Response {
+                message: "rate limited".to_string(),
+                next: "1".to_string(),
+                prev: String::new(),
+                result: vec![0],
+            })
+        };
+        let pages = pages(None, failing).collect::<Vec<_>>().await;
+        assert_eq!(pages.len(), 1);
+        assert!(
+            matches!(&pages[0], Err(Error::IndexerError(IndexerError::Message(message))) if message == "rate limited")
+        );
+    }
+}
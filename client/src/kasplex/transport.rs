@@ -0,0 +1,369 @@
+//!
+//! HTTP transport of the kasplex indexer client.
+//!
+//! Requests are spaced by a client-side rate limit, bounded by a timeout and
+//! retried with exponential back-off when the indexer is unavailable (HTTP
+//! 5xx), throttling (HTTP 429) or unreachable. Successful responses are kept
+//! in a short-lived cache so that repeated lookups do not hit the indexer.
+//!
+
+use crate::imports::*;
+use futures::future::{self, Either};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::Duration;
+use thiserror::Error;
+use workflow_core::task::sleep;
+use workflow_core::time::Instant;
+use workflow_log::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IndexerError {
+    #[error("Indexer network error: {0}")]
+    Network(String),
+
+    #[error("Indexer request timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Indexer HTTP status {status}: {body}")]
+    Status { status: u16, body: String },
+
+    #[error("Indexer response decode error: {0}")]
+    Decode(String),
+
+    /// The indexer responded with a `message` other than `successful`.
+    #[error("Indexer error: {0}")]
+    Message(String),
+}
+
+impl IndexerError {
+    /// Returns `true` for failures that may succeed if the request is retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            IndexerError::Network(_) | IndexerError::Timeout(_) => true,
+            IndexerError::Status { status, .. } => *status == 429 || *status >= 500,
+            IndexerError::Decode(_) | IndexerError::Message(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    /// Timeout of a single request attempt.
+    pub timeout: Duration,
+    /// Number of retries after the first attempt.
+    pub retries: u32,
+    /// Delay before the first retry, doubled on each following retry.
+    pub backoff: Duration,
+    /// Upper bound of the retry delay.
+    pub max_backoff: Duration,
+    /// Maximum number of requests per second, unlimited if `None`.
+    pub rate_limit: Option<u32>,
+    /// Lifetime of cached responses, caching is disabled if `None`.
+    pub cache_ttl: Option<Duration>,
+    /// Maximum number of cached responses.
+    pub cache_capacity: u64,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(15),
+            retries: 4,
+            backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            rate_limit: Some(10),
+            cache_ttl: Some(Duration::from_secs(5)),
+            cache_capacity: 1024,
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub fn with_retries(self, retries: u32) -> Self {
+        Self { retries, ..self }
+    }
+
+    pub fn with_backoff(self, backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    pub fn with_rate_limit(self, rate_limit: Option<u32>) -> Self {
+        Self { rate_limit, ..self }
+    }
+
+    pub fn with_cache(self, cache_ttl: Option<Duration>, cache_capacity: u64) -> Self {
+        Self {
+            cache_ttl,
+            cache_capacity,
+            ..self
+        }
+    }
+
+    /// Delay before the retry following `attempt` (starting at 0).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// Spaces requests evenly so that at most `rate` are issued per second.
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(rate: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / rate.max(1),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Waits for the next request slot.
+    async fn acquire(&self) {
+        let delay = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + self.interval);
+            slot.duration_since(now)
+        };
+
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type ResponseCache = mini_moka::sync::Cache<String, String>;
+
+/// Subset of every indexer response used to detect failures.
+#[derive(Deserialize)]
+struct Status {
+    message: Option<String>,
+}
+
+pub struct Transport {
+    config: TransportConfig,
+    client: reqwest::Client,
+    limiter: Option<RateLimiter>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache: Option<ResponseCache>,
+}
+
+impl Transport {
+    pub fn new(config: TransportConfig) -> Self {
+        let limiter = config.rate_limit.map(RateLimiter::new);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let cache = config.cache_ttl.map(|ttl| {
+            ResponseCache::builder()
+                .max_capacity(config.cache_capacity)
+                .time_to_live(ttl)
+                .build()
+        });
+
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            limiter,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache,
+        }
+    }
+
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    /// Fetches and decodes the response at `url`, failing with
+    /// [`IndexerError::Message`] if the indexer does not report success.
+    pub async fn get_json<T: DeserializeOwned>(&self, url: impl Into<String>) -> Result<T> {
+        let url = url.into();
+
+        if let Some(body) = self.cached(&url) {
+            return Ok(decode(&body)?);
+        }
+
+        let body = self.get(&url).await?;
+        if let Ok(Status {
+            message: Some(message),
+        }) = serde_json::from_str::<Status>(&body)
+        {
+            if !super::is_success(&message) {
+                return Err(IndexerError::Message(message).into());
+            }
+        }
+
+        let response = decode(&body)?;
+        self.store(url, body);
+        Ok(response)
+    }
+
+    /// Fetches the body at `url`, retrying failures that may be transient.
+    async fn get(&self, url: &str) -> std::result::Result<String, IndexerError> {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+
+            match self.attempt(url).await {
+                Ok(body) => return Ok(body),
+                Err((err, _)) if !err.is_retryable() || attempt >= self.config.retries => {
+                    return Err(err)
+                }
+                Err((err, retry_after)) => {
+                    let delay = retry_after.unwrap_or_else(|| self.config.backoff(attempt));
+                    log_warn!("kasplex: {err}, retrying {url} in {delay:?}");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Single request attempt, returning the delay requested by the indexer
+    /// through `Retry-After` along with the failure.
+    async fn attempt(
+        &self,
+        url: &str,
+    ) -> std::result::Result<String, (IndexerError, Option<Duration>)> {
+        let request = async {
+            let response = self.client.get(url).send().await.map_err(network)?;
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(|secs| Duration::from_secs(secs).min(self.config.max_backoff));
+            let body = response.text().await.map_err(network)?;
+
+            if status.is_success() {
+                Ok(body)
+            } else {
+                let err = IndexerError::Status {
+                    status: status.as_u16(),
+                    body,
+                };
+                Err((err, retry_after))
+            }
+        };
+
+        timeout(self.config.timeout, request)
+            .await
+            .unwrap_or(Err((IndexerError::Timeout(self.config.timeout), None)))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn cached(&self, url: &str) -> Option<String> {
+        self.cache.as_ref()?.get(&url.to_string())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn store(&self, url: String, body: String) {
+        if let Some(cache) = &self.cache {
+            cache.insert(url, body);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn cached(&self, _url: &str) -> Option<String> {
+        None
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn store(&self, _url: String, _body: String) {}
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::new(TransportConfig::default())
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &str) -> std::result::Result<T, IndexerError> {
+    serde_json::from_str(body).map_err(|err| IndexerError::Decode(err.to_string()))
+}
+
+fn network(err: reqwest::Error) -> (IndexerError, Option<Duration>) {
+    (IndexerError::Network(err.to_string()), None)
+}
+
+/// Resolves to `None` if `future` does not complete within `duration`.
+async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let future = std::pin::pin!(future);
+    let sleep = std::pin::pin!(sleep(duration));
+    match future::select(future, sleep).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let config = TransportConfig::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let delays = (0..6)
+            .map(|attempt| config.backoff(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+        assert_eq!(config.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_retryable() {
+        let status = |status| IndexerError::Status {
+            status,
+            body: String::new(),
+        };
+        assert!(status(429).is_retryable());
+        assert!(status(503).is_retryable());
+        assert!(!status(404).is_retryable());
+        assert!(IndexerError::Network("reset".to_string()).is_retryable());
+        assert!(IndexerError::Timeout(Duration::from_secs(1)).is_retryable());
+        assert!(!IndexerError::Decode("eof".to_string()).is_retryable());
+        assert!(!IndexerError::Message("tick invalid".to_string()).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limiter = RateLimiter::new(20);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // the first request is immediate, the following ones 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let slow = sleep(Duration::from_secs(5));
+        assert!(timeout(Duration::from_millis(10), slow).await.is_none());
+        let fast = async { 42 };
+        assert_eq!(timeout(Duration::from_secs(1), fast).await, Some(42));
+    }
+}
@@ -1,4 +1,12 @@
+use kaspa_addresses::Address;
 use kaspa_consensus_core::network::{NetworkId, NetworkType};
+use kaspa_hashes::Hash;
+use sparkle_core::model::amount::DEFAULT_DECIMALS;
+use sparkle_core::model::kasplex::v1::Tick;
+use sparkle_core::model::sparkle::{Metadata, SparkleTransaction};
+use sparkle_core::prelude::Url;
+use sparkle_rs::kasplex::endpoint::{Backend, Endpoint};
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub enum BetaAction {
@@ -13,6 +21,8 @@ pub struct Args {
     pub node_url: Option<String>,
     pub sparkled_url: Option<String>,
     pub network_id: NetworkId,
+    /// Kasplex indexer used by wallet operations.
+    pub indexer: Endpoint,
     pub wallet_file: Option<String>,
     pub action: Action,
 }
@@ -20,13 +30,69 @@ pub struct Args {
 #[derive(Debug)]
 pub enum Action {
     Ping,
-    Wallet { action: WalletAction },
+    Audit {
+        /// Reference indexer URL, the kasplex indexer of the network if `None`.
+        indexer_url: Option<String>,
+        /// URL of the kasplex v1 API audited against the reference, the
+        /// local sparkled if `None`.
+        candidate_url: Option<String>,
+        /// JSON report file.
+        report: Option<String>,
+    },
+    Wallet {
+        action: WalletAction,
+    },
+    Script {
+        action: ScriptAction,
+    },
+    Krc20 {
+        action: Krc20Action,
+    },
+    /// Inscription of a sparkle token operation.
+    Token {
+        transaction: SparkleTransaction,
+        index: u32,
+    },
+}
+
+#[derive(Debug)]
+pub enum Krc20Action {
+    List {
+        tick: Tick,
+        amount: String,
+        price: String,
+        index: u32,
+    },
+    Buy {
+        pskt: String,
+        index: u32,
+    },
+    Cancel {
+        pskt: String,
+        index: u32,
+    },
+}
+
+#[derive(Debug)]
+pub enum ScriptAction {
+    Decode { input: String, block: Option<Hash> },
 }
 
 #[derive(Debug)]
 pub enum WalletAction {
-    Test,
+    Test {
+        index: u32,
+    },
     Test2,
+    MintBatch {
+        index: u32,
+        count: u32,
+    },
+    Recover {
+        index: u32,
+        window: u32,
+        sweep: bool,
+    },
 }
 
 impl Args {
@@ -69,7 +135,190 @@ impl Args {
                     .require_equals(true)
                     .help("wRPC URL of the rusty kaspa node"),
             )
+            .arg(
+                Arg::new("indexer-url")
+                    .long("indexer-url")
+                    .value_name("url")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .help(
+                        "Kasplex indexer URL (default the --indexer-backend URL, \
+                        $KASPLEX_INDEXER_URL or the kasplex URL for the network)",
+                    ),
+            )
+            .arg(
+                Arg::new("indexer-backend")
+                    .long("indexer-backend")
+                    .value_name("kasplex|sparkled")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .value_parser(|backend: &str| {
+                        backend.parse::<Backend>().map_err(|err| err.to_string())
+                    })
+                    .help(
+                        "Kasplex indexer backend, overriding $KASPLEX_INDEXER_URL: 'kasplex' or \
+                        'sparkled' for a local sparkled started with --http (default 'kasplex')",
+                    ),
+            )
             .subcommand(Command::new("ping").about("Ping sparkle daemon"))
+            .subcommand(
+                Command::new("audit")
+                    .about("Reconcile the sparkled KRC-20 ledger (or another kasplex-compatible indexer) with the reference indexer")
+                    .arg(
+                        Arg::new("indexer")
+                            .long("indexer")
+                            .value_name("url")
+                            .require_equals(true)
+                            .help("Reference indexer URL (default: kasplex indexer of the network)"),
+                    )
+                    .arg(
+                        Arg::new("candidate")
+                            .long("candidate")
+                            .value_name("url")
+                            .require_equals(true)
+                            .help("kasplex v1 API URL of the audited indexer (default: local sparkled started with --http)"),
+                    )
+                    .arg(
+                        Arg::new("report")
+                            .long("report")
+                            .value_name("file")
+                            .require_equals(true)
+                            .help("Write the discrepancies to a JSON report"),
+                    ),
+            )
+            .subcommand(
+                Command::new("script")
+                    .about("Script utilities")
+                    .subcommand(
+                        Command::new("decode")
+                            .about("Disassemble a script or the scripts of a transaction")
+                            .arg(
+                                Arg::new("input")
+                                    .required(true)
+                                    .value_name("hex | txid")
+                                    .help("Hex-encoded script or transaction id"),
+                            )
+                            .arg(
+                                Arg::new("block")
+                                    .long("block")
+                                    .value_name("hash")
+                                    .require_equals(true)
+                                    .value_parser(|hash: &str| {
+                                        Hash::from_str(hash).map_err(|err| err.to_string())
+                                    })
+                                    .help(
+                                        "Block containing the transaction (the node does not \
+                                        index transactions, only mempool transactions are \
+                                        found without it)",
+                                    ),
+                            ),
+                    ),
+            )
+            .subcommand(
+                Command::new("krc20")
+                    .about("KRC-20 marketplace operations")
+                    .subcommand(
+                        Command::new("list")
+                            .about("List tokens for sale and print the partially signed transaction")
+                            .arg(
+                                Arg::new("tick")
+                                    .required(true)
+                                    .value_parser(clap::value_parser!(Tick))
+                                    .help("Token tick"),
+                            )
+                            .arg(
+                                Arg::new("amount")
+                                    .required(true)
+                                    .help("Token amount (e.g. 1,000.5)"),
+                            )
+                            .arg(
+                                Arg::new("price")
+                                    .required(true)
+                                    .help("Price in KAS"),
+                            )
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("buy")
+                            .about("Complete the purchase of a listing")
+                            .arg(pskt_arg())
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg().help("Funding key index (default 0)")),
+                    )
+                    .subcommand(
+                        Command::new("cancel")
+                            .about("Cancel a listing, returning the tokens to the seller")
+                            .arg(pskt_arg())
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    ),
+            )
+            .subcommand(
+                Command::new("token")
+                    .about("Sparkle token operations (amounts in the smallest token unit)")
+                    .subcommand(
+                        Command::new("create")
+                            .about("Create a token")
+                            .arg(tick_arg())
+                            .arg(
+                                Arg::new("max")
+                                    .required(true)
+                                    .value_parser(clap::value_parser!(u128))
+                                    .help("Max supply"),
+                            )
+                            .arg(
+                                Arg::new("limit")
+                                    .required(true)
+                                    .value_parser(clap::value_parser!(u128))
+                                    .help("Mint limit"),
+                            )
+                            .arg(
+                                Arg::new("dec")
+                                    .long("dec")
+                                    .value_name("n")
+                                    .require_equals(true)
+                                    .value_parser(clap::value_parser!(u8))
+                                    .help("Token decimals (default 8)"),
+                            )
+                            .args(metadata_args())
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("mint")
+                            .about("Mint tokens to the inscription key address")
+                            .arg(tick_arg())
+                            .arg(amount_arg())
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("transfer")
+                            .about("Transfer tokens from the inscription key address")
+                            .arg(tick_arg())
+                            .arg(amount_arg())
+                            .arg(
+                                Arg::new("to")
+                                    .required(true)
+                                    .value_name("address")
+                                    .value_parser(|address: &str| {
+                                        Address::try_from(address).map_err(|err| err.to_string())
+                                    })
+                                    .help("Recipient address"),
+                            )
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("metadata")
+                            .about("Update the metadata of a token created with the inscription key")
+                            .arg(tick_arg())
+                            .args(metadata_args())
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    ),
+            )
             .subcommand(
                 Command::new("wallet")
                     .about("Perform wallet operation")
@@ -77,7 +326,37 @@ impl Args {
                     .subcommand(
                         Command::new("test")
                             .about("Account tests")
-                            .arg(Arg::new("wallet-file").help("Wallet file name")),
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("mint-batch")
+                            .about("Mint tokens using a single commit transaction")
+                            .arg(
+                                Arg::new("count")
+                                    .required(true)
+                                    .value_parser(clap::value_parser!(u32))
+                                    .help("Number of mint inscriptions"),
+                            )
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg()),
+                    )
+                    .subcommand(
+                        Command::new("recover")
+                            .about("Reveal or sweep stranded inscription commit outputs")
+                            .arg(Arg::new("wallet-file").help("Wallet file name"))
+                            .arg(index_arg())
+                            .arg(
+                                Arg::new("window")
+                                    .long("window")
+                                    .value_name("n")
+                                    .require_equals(true)
+                                    .value_parser(clap::value_parser!(u32))
+                                    .help("Number of key indices to scan (default 64)"),
+                            )
+                            .arg(
+                                arg!(--sweep "Return the funds to the account without the protocol fee"),
+                            ),
                     ), // .subcommand(
                        //     // Command::new("accounts")
                        //     Command::new("test").about("List wallet accounts").arg(
@@ -101,6 +380,13 @@ impl Args {
 
         let node_url = matches.get_one::<String>("node-rpc").cloned();
         let sparkled_url = matches.get_one::<String>("rpc").cloned();
+        let indexer = Endpoint::default()
+            .with_backend(matches.get_one::<Backend>("indexer-backend").cloned())
+            .with_url(
+                matches
+                    .get_one::<String>("indexer-url")
+                    .map(|url| Url::from(url.as_str())),
+            );
         let mut wallet_file = None;
 
         let action = if matches.get_one::<bool>("version").cloned().unwrap_or(false) {
@@ -108,11 +394,103 @@ impl Args {
             std::process::exit(0);
         } else if let Some(_matches) = matches.subcommand_matches("ping") {
             Action::Ping
+        } else if let Some(matches) = matches.subcommand_matches("audit") {
+            Action::Audit {
+                indexer_url: matches.get_one::<String>("indexer").cloned(),
+                candidate_url: matches.get_one::<String>("candidate").cloned(),
+                report: matches.get_one::<String>("report").cloned(),
+            }
+        } else if let Some(matches) = matches.subcommand_matches("script") {
+            if let Some(matches) = matches.subcommand_matches("decode") {
+                let input = matches.get_one::<String>("input").cloned().unwrap();
+                let block = matches.get_one::<Hash>("block").cloned();
+                Action::Script {
+                    action: ScriptAction::Decode { input, block },
+                }
+            } else {
+                println!("No script action specified");
+                std::process::exit(1);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("krc20") {
+            let (name, matches) = matches.subcommand().unwrap_or_else(|| {
+                println!("No krc20 action specified");
+                std::process::exit(1);
+            });
+            wallet_file = matches.get_one::<String>("wallet-file").cloned();
+            let index = matches.get_one::<u32>("index").cloned().unwrap_or(0);
+            let action = match name {
+                "list" => Krc20Action::List {
+                    tick: matches.get_one::<Tick>("tick").cloned().unwrap(),
+                    amount: matches.get_one::<String>("amount").cloned().unwrap(),
+                    price: matches.get_one::<String>("price").cloned().unwrap(),
+                    index,
+                },
+                "buy" => Krc20Action::Buy {
+                    pskt: matches.get_one::<String>("pskt").cloned().unwrap(),
+                    index,
+                },
+                _ => Krc20Action::Cancel {
+                    pskt: matches.get_one::<String>("pskt").cloned().unwrap(),
+                    index,
+                },
+            };
+            Action::Krc20 { action }
+        } else if let Some(matches) = matches.subcommand_matches("token") {
+            let (name, matches) = matches.subcommand().unwrap_or_else(|| {
+                println!("No token action specified");
+                std::process::exit(1);
+            });
+            wallet_file = matches.get_one::<String>("wallet-file").cloned();
+            let index = matches.get_one::<u32>("index").cloned().unwrap_or(0);
+            let tick = matches.get_one::<Tick>("tick").cloned().unwrap();
+            let transaction = match name {
+                "create" => SparkleTransaction::create(
+                    &tick,
+                    matches.get_one::<u128>("max").cloned().unwrap(),
+                    matches.get_one::<u128>("limit").cloned().unwrap(),
+                    matches
+                        .get_one::<u8>("dec")
+                        .cloned()
+                        .unwrap_or(DEFAULT_DECIMALS),
+                )
+                .with_metadata(metadata(matches)),
+                "mint" => SparkleTransaction::mint(
+                    &tick,
+                    matches.get_one::<u128>("amount").cloned().unwrap(),
+                ),
+                "transfer" => SparkleTransaction::transfer(
+                    &tick,
+                    matches.get_one::<u128>("amount").cloned().unwrap(),
+                    matches.get_one::<Address>("to").unwrap(),
+                ),
+                _ => SparkleTransaction::update_metadata(&tick, metadata(matches)),
+            };
+            Action::Token { transaction, index }
         } else if let Some(matches) = matches.subcommand_matches("wallet") {
             if let Some(matches) = matches.subcommand_matches("test") {
                 wallet_file = matches.get_one::<String>("wallet-file").cloned();
+                let index = matches.get_one::<u32>("index").cloned().unwrap_or(0);
                 Action::Wallet {
-                    action: WalletAction::Test,
+                    action: WalletAction::Test { index },
+                }
+            } else if let Some(matches) = matches.subcommand_matches("mint-batch") {
+                wallet_file = matches.get_one::<String>("wallet-file").cloned();
+                let count = matches.get_one::<u32>("count").cloned().unwrap();
+                let index = matches.get_one::<u32>("index").cloned().unwrap_or(0);
+                Action::Wallet {
+                    action: WalletAction::MintBatch { index, count },
+                }
+            } else if let Some(matches) = matches.subcommand_matches("recover") {
+                wallet_file = matches.get_one::<String>("wallet-file").cloned();
+                let index = matches.get_one::<u32>("index").cloned().unwrap_or(0);
+                let window = matches.get_one::<u32>("window").cloned().unwrap_or(64);
+                let sweep = matches.get_one::<bool>("sweep").cloned().unwrap_or(false);
+                Action::Wallet {
+                    action: WalletAction::Recover {
+                        index,
+                        window,
+                        sweep,
+                    },
                 }
             } else {
                 println!("No wallet action specified");
@@ -129,8 +507,57 @@ impl Args {
             node_url,
             sparkled_url,
             network_id,
+            indexer,
             wallet_file,
             action,
         }
     }
 }
+
+fn index_arg() -> clap::Arg {
+    clap::Arg::new("index")
+        .long("index")
+        .value_name("n")
+        .require_equals(true)
+        .value_parser(clap::value_parser!(u32))
+        .help("Inscription key index (default 0)")
+}
+
+fn tick_arg() -> clap::Arg {
+    clap::Arg::new("tick")
+        .required(true)
+        .value_parser(clap::value_parser!(Tick))
+        .help("Token tick")
+}
+
+fn amount_arg() -> clap::Arg {
+    clap::Arg::new("amount")
+        .required(true)
+        .value_parser(clap::value_parser!(u128))
+        .help("Token amount")
+}
+
+fn metadata_args() -> [clap::Arg; 3] {
+    ["name", "description", "uri"].map(|name| {
+        clap::Arg::new(name)
+            .long(name)
+            .value_name(name)
+            .require_equals(true)
+            .help(format!("Token {name}"))
+    })
+}
+
+fn metadata(matches: &clap::ArgMatches) -> Metadata {
+    Metadata {
+        name: matches.get_one::<String>("name").cloned(),
+        description: matches.get_one::<String>("description").cloned(),
+        uri: matches.get_one::<String>("uri").cloned(),
+    }
+}
+
+fn pskt_arg() -> clap::Arg {
+    clap::Arg::new("pskt")
+        .required(true)
+        .value_name("pskt")
+        .help("Partially signed transaction of the listing")
+}
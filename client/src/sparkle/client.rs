@@ -1,11 +1,14 @@
-use crate::args::{Action, Args, WalletAction};
+use crate::args::{Action, Args, Krc20Action, ScriptAction, WalletAction};
 use crate::wallet::*;
 use cliclack::intro;
 use console::style;
-use sparkle_core::model::kasplex;
+use kaspa_wallet_core::utils::try_kaspa_str_to_sompi;
+use sparkle_core::inscription::Pskt;
 use sparkle_core::runtime::Runtime;
 use sparkle_rpc_client::prelude::*;
 use sparkle_rs::imports::*;
+use sparkle_rs::kasplex::audit::Audit;
+use sparkle_rs::kasplex::endpoint::{Endpoint, SPARKLED_INDEXER_URL};
 use sparkle_rs::kasplex::v1::Indexer as KasplexIndexer;
 use sparkle_rs::result::Result;
 use workflow_log::prelude::*;
@@ -21,6 +24,7 @@ impl Client {
             node_url,
             sparkled_url,
             network_id,
+            indexer,
             enable_debug_mode,
             trace_log_level,
         } = Args::parse();
@@ -61,6 +65,87 @@ impl Client {
                 println!("🥂 Ok...");
                 client.disconnect().await?;
             }
+            Action::Audit {
+                indexer_url,
+                candidate_url,
+                report,
+            } => {
+                println!("{}", version);
+
+                let reference = Endpoint::default()
+                    .with_url(indexer_url.map(Url::from))
+                    .resolve(&network_id)?;
+                let audit = Audit::new(
+                    KasplexIndexer::try_new(reference)?,
+                    KasplexIndexer::try_new(Url::from(
+                        candidate_url.as_deref().unwrap_or(SPARKLED_INDEXER_URL),
+                    ))?,
+                );
+                let result = audit.run().await?;
+                println!("{result}");
+
+                if let Some(report) = report {
+                    let json = serde_json::to_string_pretty(&result)
+                        .map_err(|err| Error::custom(err.to_string()))?;
+                    std::fs::write(&report, json)?;
+                    println!("Report written to {report}");
+                }
+            }
+            Action::Script {
+                action: ScriptAction::Decode { input, block },
+            } => {
+                crate::script::decode(&input, block, network_id, node_url).await?;
+            }
+            Action::Krc20 { action } => {
+                println!();
+                crate::log::init();
+                intro(style(version).on_black().cyan())?;
+
+                let ctx = Context {
+                    network_id,
+                    node_url,
+                    indexer,
+                    wallet_file,
+                };
+
+                match action {
+                    Krc20Action::List {
+                        tick,
+                        amount,
+                        price,
+                        index,
+                    } => {
+                        let price = try_kaspa_str_to_sompi(price.as_str())?
+                            .ok_or_else(|| Error::custom("Missing price"))?;
+                        let wallet = Wallet::try_new(ctx, true).await?;
+                        wallet.list(&tick, &amount, price, index).await?;
+                    }
+                    Krc20Action::Buy { pskt, index } => {
+                        let pskt = Pskt::from_str(&pskt)?;
+                        let wallet = Wallet::try_new(ctx, true).await?;
+                        wallet.buy(&pskt, index).await?;
+                    }
+                    Krc20Action::Cancel { pskt, index } => {
+                        let pskt = Pskt::from_str(&pskt)?;
+                        let wallet = Wallet::try_new(ctx, true).await?;
+                        wallet.cancel(&pskt, index).await?;
+                    }
+                }
+            }
+            Action::Token { transaction, index } => {
+                println!();
+                crate::log::init();
+                intro(style(version).on_black().cyan())?;
+
+                let ctx = Context {
+                    network_id,
+                    node_url,
+                    indexer,
+                    wallet_file,
+                };
+                let wallet = Wallet::try_new(ctx, true).await?;
+                wallet.inscribe(&transaction, index).await?;
+            }
             Action::Wallet { action } => {
                 println!();
                 crate::log::init();
@@ -69,19 +154,37 @@ impl Client {
                 let ctx = Context {
                     network_id,
                     node_url,
+                    indexer,
                     wallet_file,
                 };
 
                 match action {
-                    WalletAction::Test => {
+                    WalletAction::Test { index } => {
                         let wallet = Wallet::try_new(ctx, true).await?;
                         // wallet.wallet.utxo_processor();
                         log_info!("{:#?}", wallet.account);
-                        wallet.demo_deploy().await;
-                        wallet.demo_mint().await;
+                        wallet.demo_deploy(index).await?;
+                        wallet.demo_mint(index + 1).await?;
+                    }
+                    WalletAction::MintBatch { index, count } => {
+                        let wallet = Wallet::try_new(ctx, true).await?;
+                        let report = wallet.demo_mint_batch(index, count).await?;
+                        log_info!(
+                            "{} reveal(s) submitted, {} failed",
+                            report.submitted.len(),
+                            report.failed.len()
+                        );
+                    }
+                    WalletAction::Recover {
+                        index,
+                        window,
+                        sweep,
+                    } => {
+                        let wallet = Wallet::try_new(ctx, true).await?;
+                        wallet.recover(index, window, sweep).await?;
                     }
                     WalletAction::Test2 => {
-                        let _wallet = Wallet::try_new(ctx, true).await?;
+                        let wallet = Wallet::try_new(ctx, true).await?;
                         // wallet.wallet.utxo_processor();
                         // log_info!("{:#?}", wallet.account);
                         // wallet.demo_deploy().await;
@@ -91,14 +194,20 @@ impl Client {
                         let address = match network_id.network_type() {
                             NetworkType::Testnet => Address::try_from("kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya").unwrap(),
                             NetworkType::Mainnet => Address::try_from("kaspa:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkx9awp4e").unwrap(),
-                            _ => panic!("Unsupported network"),
+                            _ => return Err(Error::UnsupportedNetwork(network_id.to_string())),
                         };
 
-                        let network = kasplex::v1::Network::try_from(&network_id)?;
-                        let indexer = KasplexIndexer::try_new(network.into())?;
+                        let indexer = wallet.indexer();
                         let mut tokens =
                             indexer.get_token_balance_list_by_address(&address).await?;
                         tokens.sort_by(|a, b| a.tick.cmp(&b.tick));
+                        tokens.iter().for_each(|token| {
+                            log_info!(
+                                "{} (locked {})",
+                                token.balance_amount().format_with_suffix(&token.tick),
+                                token.locked_amount()
+                            );
+                        });
                     }
                 }
             }
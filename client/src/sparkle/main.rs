@@ -17,6 +17,7 @@ cfg_if::cfg_if! {
         pub mod client;
         pub mod wallet;
         pub mod log;
+        pub mod script;
 
         use client::Client;
         use workflow_log::prelude::*;
@@ -0,0 +1,98 @@
+use kaspa_consensus_core::tx::TransactionId;
+use kaspa_hashes::Hash;
+use kaspa_rpc_core::RpcTransaction;
+use kaspa_wrpc_client::prelude::*;
+use sparkle_core::script::inspect;
+use sparkle_rs::imports::*;
+use sparkle_rs::result::Result;
+
+/// Decodes a hex-encoded script or, if `input` is a transaction id, the
+/// signature scripts and output scripts of the transaction fetched from the
+/// node. The node does not index transactions: a transaction that left the
+/// mempool is only found in the `block` containing it.
+pub async fn decode(
+    input: &str,
+    block: Option<Hash>,
+    network_id: NetworkId,
+    node_url: Option<String>,
+) -> Result<()> {
+    let input = input.trim();
+    if input.len() == 64 {
+        if let Ok(txid) = TransactionId::from_str(input) {
+            return decode_transaction(txid, block, network_id, node_url).await;
+        }
+    }
+
+    let script = Vec::<u8>::from_hex(input)
+        .map_err(|err| Error::custom(format!("Invalid script hex: {err}")))?;
+    print!("{}", inspect(&script));
+    Ok(())
+}
+
+async fn decode_transaction(
+    txid: TransactionId,
+    block: Option<Hash>,
+    network_id: NetworkId,
+    node_url: Option<String>,
+) -> Result<()> {
+    let (resolver, url) = if let Some(url) = node_url {
+        (None, Some(url))
+    } else {
+        (Some(Resolver::default()), None)
+    };
+
+    let client = KaspaRpcClient::new_with_args(
+        WrpcEncoding::Borsh,
+        url.as_deref(),
+        resolver,
+        Some(network_id),
+        None,
+    )?;
+    client.connect(None).await?;
+
+    let transaction = find_transaction(&client, txid, block).await;
+    client.disconnect().await?;
+    let transaction = transaction?;
+
+    for (index, input) in transaction.inputs.iter().enumerate() {
+        println!("Input {index} ({})", input.previous_outpoint);
+        println!("{}", inspect(&input.signature_script));
+    }
+    for (index, output) in transaction.outputs.iter().enumerate() {
+        println!("Output {index} ({} sompi)", output.value);
+        println!("{}", inspect(output.script_public_key.script()));
+    }
+
+    Ok(())
+}
+
+/// Looks up `txid` in the mempool, then in `block` if given.
+async fn find_transaction(
+    client: &KaspaRpcClient,
+    txid: TransactionId,
+    block: Option<Hash>,
+) -> Result<RpcTransaction> {
+    let err = match client.get_mempool_entry(txid, true, false).await {
+        Ok(entry) => return Ok(entry.transaction),
+        Err(err) => err,
+    };
+
+    let Some(block) = block else {
+        return Err(Error::custom(format!(
+            "Transaction {txid} not found in the mempool: {err} (use --block=<hash> for confirmed transactions)"
+        )));
+    };
+
+    client
+        .get_block(block, true)
+        .await?
+        .transactions
+        .into_iter()
+        .find(|transaction| {
+            transaction
+                .verbose_data
+                .as_ref()
+                .is_some_and(|verbose_data| verbose_data.transaction_id == txid)
+        })
+        .ok_or_else(|| Error::custom(format!("Transaction {txid} not found in block {block}")))
+}
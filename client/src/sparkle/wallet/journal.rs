@@ -0,0 +1,124 @@
+//!
+//! Journal of the redeem scripts committed by an account.
+//!
+//! The P2SH address of an inscription can only be spent with its redeem
+//! script, which can not be re-derived from the key index alone. Each script
+//! is recorded before its commit transaction is submitted so that stranded
+//! commit outputs can be recovered later.
+//!
+
+use sparkle_rs::imports::*;
+use sparkle_rs::result::Result;
+use std::path::PathBuf;
+use workflow_core::dirs::home_dir;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Inscription key index.
+    pub index: u32,
+    /// Hex-encoded redeem script.
+    pub redeem_script: String,
+    /// Protocol fee paid by the reveal transaction.
+    pub protocol_fee: u64,
+}
+
+impl JournalEntry {
+    pub fn new(index: u32, redeem_script: &[u8], protocol_fee: u64) -> Self {
+        Self {
+            index,
+            redeem_script: redeem_script.to_hex(),
+            protocol_fee,
+        }
+    }
+
+    pub fn redeem_script(&self) -> Result<Vec<u8>> {
+        Vec::<u8>::from_hex(&self.redeem_script)
+            .map_err(|err| Error::custom(format!("Invalid journal redeem script: {err}")))
+    }
+}
+
+pub struct Journal {
+    path: PathBuf,
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Opens the journal of the account `account_id` on `network_id`
+    /// stored in `~/.sparkle/journal`.
+    pub fn open(network_id: &NetworkId, account_id: &str) -> Result<Self> {
+        let folder = home_dir()
+            .ok_or_else(|| Error::custom("Unable to locate the home directory"))?
+            .join(".sparkle")
+            .join("journal");
+        Self::load(folder.join(format!("{network_id}-{account_id}.json")))
+    }
+
+    /// Loads the journal at `path`, which is created on the first record.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let entries = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?).map_err(|err| {
+                Error::custom(format!("Invalid journal `{}`: {err}", path.display()))
+            })?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Records the redeem scripts of `entries`, skipping known ones, and
+    /// writes the journal back.
+    pub fn record(&mut self, entries: impl IntoIterator<Item = JournalEntry>) -> Result<()> {
+        for entry in entries {
+            if !self.entries.contains(&entry) {
+                self.entries.push(entry);
+            }
+        }
+
+        if let Some(folder) = self.path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        let json = serde_json::to_string_pretty(&self.entries)
+            .map_err(|err| Error::custom(err.to_string()))?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    /// Entries of the key indices `start..end`.
+    pub fn entries(&self, start: u32, end: u32) -> impl Iterator<Item = &JournalEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| (start..end).contains(&entry.index))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let path =
+            std::env::temp_dir().join(format!("sparkle-journal-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut journal = Journal::load(path.clone()).unwrap();
+        journal
+            .record([
+                JournalEntry::new(3, &[0x20, 0xab], 1000),
+                JournalEntry::new(7, &[0x51], 0),
+            ])
+            .unwrap();
+        journal
+            .record([JournalEntry::new(3, &[0x20, 0xab], 1000)])
+            .unwrap();
+
+        let journal = Journal::load(path.clone()).unwrap();
+        let entries = journal.entries(0, 5).collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].redeem_script().unwrap(), vec![0x20, 0xab]);
+        assert_eq!(journal.entries(0, 10).count(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
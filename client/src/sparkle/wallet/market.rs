@@ -0,0 +1,163 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::await_utxo_inclusion;
+use super::{confirm, Wallet};
+use cliclack::log;
+use kaspa_consensus_core::tx::{TransactionOutpoint, UtxoEntry};
+use kaspa_txscript::pay_to_script_hash_script;
+use kaspa_wallet_core::prelude::sompi_to_kaspa_string_with_suffix;
+use sparkle_core::envelope;
+use sparkle_core::inscription::builder::p2sh_address;
+use sparkle_core::inscription::market::{
+    funding_address, list_transaction, send_transaction, token_redeem_script,
+};
+use sparkle_core::inscription::{InscriptionBuilder, Pskt};
+use sparkle_core::model::kasplex::v1::Tick;
+use sparkle_core::model::TokenAmount;
+use sparkle_rs::imports::*;
+use sparkle_rs::result::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::task::JoinHandle;
+
+impl Wallet {
+    /// Lists `amount` (in the token decimals, e.g. `1,000.5`) of `tick` for
+    /// `price` sompi using the inscription key `index` and returns the
+    /// partially signed transaction for the buyer.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn list(&self, tick: &Tick, amount: &str, price: u64, index: u32) -> Result<Pskt> {
+        let state = self.token_state(tick).await?;
+        let amount =
+            TokenAmount::parse(amount, state.dec).map_err(sparkle_core::error::Error::from)?;
+        let listing = list_transaction(tick, amount.raw);
+        self.validate(&listing).await?;
+
+        let network_id = self.wallet.network_id()?;
+        let (secret_key, public_key) = self.inscription_keys().await?.keypair(index)?;
+        let list_script = token_redeem_script(&public_key, &listing)?;
+        let send_script = token_redeem_script(&public_key, &send_transaction(tick))?;
+
+        // the reveal of the `list` inscription locks its payback in the `send` P2SH;
+        // kasplex only charges a protocol fee for `deploy` and `mint`
+        let builder = self
+            .inscription_builder(&self.core_account().await?, 0)
+            .await?
+            .with_reveal_address(p2sh_address(&send_script, &network_id)?);
+        let reveal_txid = self
+            .commit_reveal_chain(&builder, index, list_script, secret_key)
+            .await?;
+
+        let entry = UtxoEntry {
+            amount: builder.fee_policy().payback,
+            script_public_key: pay_to_script_hash_script(&send_script),
+            block_daa_score: 0,
+            is_coinbase: false,
+        };
+        let pskt = builder.sign_listing(
+            &listing,
+            price,
+            TransactionOutpoint::new(reveal_txid, 0),
+            entry,
+            &send_script,
+            &secret_key,
+        )?;
+        log::success(format!(
+            "Listing {} (key index {index}):\n{pskt}",
+            amount.format_with_suffix(tick)
+        ))?;
+
+        Ok(pskt)
+    }
+
+    /// Completes the purchase of `pskt`, funding it through the P2PK address
+    /// of the funding key `index`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn buy(&self, pskt: &Pskt, index: u32) -> Result<()> {
+        let account = self.core_account().await?;
+        let network_id = self.wallet.network_id()?;
+        let rpc_api = self.wallet.rpc_api();
+        let kas = |sompi| sompi_to_kaspa_string_with_suffix(sompi, &network_id.into());
+
+        if pskt.network_id != network_id {
+            return Err(Error::custom(format!(
+                "PSKT is for `{}`, wallet is on `{network_id}`",
+                pskt.network_id
+            )));
+        }
+        pskt.verify()?;
+
+        let tokens = self.token_state(&pskt.tick).await?.amount(pskt.amount);
+        // a purchase is not an inscription, no protocol fee is due
+        let builder = self.inscription_builder(&account, 0).await?;
+        let (fee, mass) = builder.buy_fee(pskt)?;
+        log::info(format!(
+            "Buy {} from {}\nPrice: {}\nNetwork fee: {} (mass {mass})",
+            tokens.format_with_suffix(&pskt.tick),
+            pskt.seller,
+            kas(pskt.price),
+            kas(fee),
+        ))?;
+        confirm("Proceed?")?;
+
+        let (secret_key, public_key) = self.inscription_keys().await?.funding_keypair(index)?;
+        let address = funding_address(&public_key, &network_id);
+        // the payback is paid to the output receiving the tokens
+        let amount = pskt.price + builder.fee_policy().payback + fee;
+        let (funding, outpoint) = builder.fund(&address, amount)?;
+
+        let monitor_handle: JoinHandle<_> = await_utxo_inclusion(address, amount, rpc_api.clone());
+        for transaction in funding.iter() {
+            transaction.try_sign()?;
+            transaction.try_submit(&rpc_api).await?;
+        }
+        let funding_txid = monitor_handle
+            .await
+            .map_err(|err| Error::custom(err.to_string()))??;
+        if funding_txid != outpoint.transaction_id {
+            return Err(Error::custom(format!(
+                "Unexpected funding transaction {funding_txid}"
+            )));
+        }
+        log::success(format!("Funding transaction accepted {funding_txid}"))?;
+
+        let purchase = builder.buy(pskt, outpoint, amount, &secret_key)?;
+        let txid = rpc_api
+            .submit_transaction((&purchase.transaction).into(), false)
+            .await?;
+        log::success(format!("Purchase transaction submitted {txid}"))?;
+
+        Ok(())
+    }
+
+    /// Cancels the listing of `pskt` created with the inscription key `index`,
+    /// returning the listed balance and the locked funds to the account.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn cancel(&self, pskt: &Pskt, index: u32) -> Result<()> {
+        let account = self.core_account().await?;
+        let network_id = self.wallet.network_id()?;
+        pskt.verify()?;
+
+        let (secret_key, public_key) = self.inscription_keys().await?.keypair(index)?;
+        let redeem_script = envelope::decode_redeem_script(&pskt.redeem_script)
+            .map_err(sparkle_core::error::Error::from)?;
+        if redeem_script.pubkey != public_key.x_only_public_key().0.serialize() {
+            return Err(Error::custom(format!(
+                "PSKT was not listed with key index {index}"
+            )));
+        }
+
+        let cancel = InscriptionBuilder::new(network_id, account.change_address()?).recover(
+            pskt.outpoint,
+            pskt.entry.amount,
+            pskt.entry.block_daa_score,
+            &pskt.redeem_script,
+            &secret_key,
+        )?;
+        let txid = self
+            .wallet
+            .rpc_api()
+            .submit_transaction((&cancel.transaction).into(), false)
+            .await?;
+        log::success(format!("Cancel transaction submitted {txid}"))?;
+
+        Ok(())
+    }
+}
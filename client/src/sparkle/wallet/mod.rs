@@ -1,34 +1,60 @@
 use cliclack::log;
-use kaspa_consensus_core::tx::TransactionId;
-use kaspa_wallet_core::prelude::AccountsSendRequest;
+use futures::future::join_all;
+use kaspa_consensus_core::tx::{TransactionId, TransactionOutpoint};
+use kaspa_wallet_core::account::{Account as CoreAccount, DerivationCapableAccount};
 use kaspa_wallet_core::prelude::{
-    AccountDescriptor, AccountId, ConnectRequest, Wallet as CoreWallet, WalletDescriptor,
+    sompi_to_kaspa_string_with_suffix, AccountDescriptor, AccountId, ConnectRequest,
+    Wallet as CoreWallet, WalletDescriptor,
 };
 use kaspa_wallet_core::rpc::RpcApi;
-use kaspa_wallet_core::tx::PaymentOutputs;
+use kaspa_wallet_core::tx::Signer;
+use kaspa_wallet_core::utxo::UtxoIterator;
 use pad::{Alignment, PadStr};
 use sparkle_core::inscription::{
-    demo_keypair, deploy_token_demo, mint_token_demo, reveal_transaction, TransactionDetails,
+    builder::p2sh_address, deploy_token_demo, deploy_transaction_demo, mint_token_demo,
+    mint_transaction_demo, FeeEstimate, FeePolicy, InscriptionBatch, InscriptionBuilder,
+    InscriptionKeys,
 };
+use sparkle_core::model::kasplex::v1::{self as kasplex, krc20::TokenTransaction};
 use sparkle_rs::imports::*;
+use sparkle_rs::kasplex::endpoint::Endpoint;
+use sparkle_rs::kasplex::v1::Indexer as KasplexIndexer;
 use sparkle_rs::monitor::monitor;
 use sparkle_rs::result::Result;
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::task::JoinHandle;
 mod account;
 use account::Account;
+mod journal;
+use journal::{Journal, JournalEntry};
+mod market;
+mod token;
 
 type AccountHashMap = HashMap<AccountId, Arc<AccountDescriptor>>;
 
+/// Amount returned to the account by each reveal transaction.
+const PAYBACK_AMOUNT: u64 = 10 * SOMPI_PER_KASPA;
+
+/// Outcome of the reveal transactions of an inscription batch. A failed
+/// reveal does not affect the others; its P2SH output remains spendable.
+#[derive(Default)]
+pub struct BatchReport {
+    pub submitted: Vec<(TransactionOutpoint, TransactionId)>,
+    pub failed: Vec<(TransactionOutpoint, Error)>,
+}
+
 pub struct Context {
     pub network_id: NetworkId,
     pub node_url: Option<String>,
+    pub indexer: Endpoint,
     pub wallet_file: Option<String>,
 }
 
 pub struct Wallet {
     pub wallet: Arc<CoreWallet>,
     pub account: Option<Arc<AccountDescriptor>>,
+    wallet_secret: Secret,
+    indexer: KasplexIndexer,
 }
 
 impl Wallet {
@@ -36,9 +62,14 @@ impl Wallet {
         let Context {
             network_id,
             node_url,
+            indexer,
             wallet_file,
         } = context;
 
+        // one client per wallet so that the transport cache and rate
+        // limiter are shared by all indexer requests
+        let indexer = KasplexIndexer::try_new(indexer.resolve(&network_id)?)?;
+
         let wallet = CoreWallet::default()
             .with_resolver(Default::default())
             .with_url(node_url.as_deref())
@@ -92,10 +123,11 @@ impl Wallet {
             }
         };
 
-        let password = cliclack::password("Enter wallet password:")
+        let wallet_secret: Secret = cliclack::password("Enter wallet password:")
             .mask('▪')
             .interact()
-            .map_err(|_| Error::UserAbort)?;
+            .map_err(|_| Error::UserAbort)?
+            .into();
 
         let spinner = cliclack::spinner();
         spinner.start("Loading wallet...");
@@ -103,7 +135,7 @@ impl Wallet {
         // let accounts =
         wallet
             .as_api()
-            .wallet_open(password.into(), Some(wallet_file), true, true)
+            .wallet_open(wallet_secret.clone(), Some(wallet_file), true, true)
             .await?
             .unwrap();
 
@@ -184,156 +216,423 @@ impl Wallet {
 
         let account = account_map.get(&account_id).cloned(); //.unwrap().cloned();
 
-        Ok(Self { wallet, account })
+        Ok(Self {
+            wallet,
+            account,
+            wallet_secret,
+            indexer,
+        })
+    }
+
+    /// Returns the wallet-core account matching the selected account descriptor.
+    pub async fn core_account(&self) -> Result<Arc<dyn CoreAccount>> {
+        let descriptor = self
+            .account
+            .as_ref()
+            .ok_or_else(|| Error::custom("No account selected"))?;
+        self.wallet
+            .get_account_by_id(&descriptor.account_id)
+            .await?
+            .ok_or_else(|| Error::custom(format!("Account not found: {}", descriptor.account_id)))
+    }
+
+    /// Returns the inscription key chain of the selected account.
+    pub async fn inscription_keys(&self) -> Result<InscriptionKeys> {
+        let account = self.core_account().await?;
+        let keydata = account.prv_key_data(self.wallet_secret.clone()).await?;
+        let xprv = keydata.get_xprv(None)?;
+        let account_index = account.clone().as_derivation_capable()?.account_index();
+        Ok(InscriptionKeys::try_new(&xprv, account_index)?)
+    }
+
+    /// Returns the redeem script journal of the selected account.
+    pub fn journal(&self) -> Result<Journal> {
+        let descriptor = self
+            .account
+            .as_ref()
+            .ok_or_else(|| Error::custom("No account selected"))?;
+        Journal::open(&self.wallet.network_id()?, &descriptor.account_id.to_hex())
+    }
+
+    /// Returns the client of the configured indexer, either the kasplex
+    /// indexer or the KRC-20 ledger of sparkled.
+    pub fn indexer(&self) -> &KasplexIndexer {
+        &self.indexer
+    }
+
+    /// Returns the state of the deployed token `tick` as reported by the
+    /// configured indexer.
+    pub async fn token_state(&self, tick: &kasplex::Tick) -> Result<kasplex::TokenState> {
+        self.indexer()
+            .get_token_state(tick.as_str())
+            .await?
+            .ok_or_else(|| {
+                sparkle_core::error::Error::from(kasplex::ValidationError::TokenNotFound(
+                    tick.to_string(),
+                ))
+                .into()
+            })
+    }
+
+    /// Checks `transaction` against the protocol rules and the token state
+    /// reported by the configured indexer endpoint, the kasplex indexer or
+    /// the local sparkled ledger selected with `--indexer-backend=sparkled`.
+    pub async fn validate(&self, transaction: &TokenTransaction) -> Result<()> {
+        transaction
+            .validate()
+            .map_err(sparkle_core::error::Error::from)?;
+
+        let state = self.indexer().get_token_state(&transaction.tick).await?;
+        transaction
+            .validate_with_state(state.as_ref())
+            .map_err(sparkle_core::error::Error::from)?;
+        Ok(())
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub async fn demo_deploy(&self) {
-        let (secret_key, public_key) = demo_keypair();
-        let (redeem_lock, script_sig) = deploy_token_demo(&public_key);
-        let p2sh = redeem_lock.clone();
-        self.commit_reveal_chain(p2sh, redeem_lock, script_sig, secret_key, FEE_DEPLOY)
-            .await;
+    pub async fn demo_deploy(&self, index: u32) -> Result<()> {
+        self.validate(&deploy_transaction_demo()).await?;
+        let (secret_key, public_key) = self.inscription_keys().await?.keypair(index)?;
+        let (_, script_sig) = deploy_token_demo(&public_key, &self.wallet.network_id()?)?;
+        let builder = self
+            .inscription_builder(&self.core_account().await?, FEE_DEPLOY)
+            .await?;
+        self.commit_reveal_chain(&builder, index, script_sig, secret_key)
+            .await?;
+        Ok(())
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub async fn demo_mint(&self) {
-        let (secret_key, public_key) = demo_keypair();
-        let (redeem_lock, script_sig) = mint_token_demo(&public_key);
-        let p2sh: Address = redeem_lock.clone();
-        self.commit_reveal_chain(p2sh, redeem_lock, script_sig, secret_key, FEE_MINT)
-            .await;
+    pub async fn demo_mint(&self, index: u32) -> Result<()> {
+        self.validate(&mint_transaction_demo()).await?;
+        let (secret_key, public_key) = self.inscription_keys().await?.keypair(index)?;
+        let (_, script_sig) = mint_token_demo(&public_key, &self.wallet.network_id()?)?;
+        let builder = self
+            .inscription_builder(&self.core_account().await?, FEE_MINT)
+            .await?;
+        self.commit_reveal_chain(&builder, index, script_sig, secret_key)
+            .await?;
+        Ok(())
     }
 
+    /// Mints `count` inscriptions using the consecutive key indices starting at `index`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn demo_mint_batch(&self, index: u32, count: u32) -> Result<BatchReport> {
+        let end = index.checked_add(count).ok_or_else(|| {
+            Error::custom(format!(
+                "Key index range {index}+{count} exceeds the u32 range"
+            ))
+        })?;
+        self.validate(&mint_transaction_demo()).await?;
+        let keys = self.inscription_keys().await?;
+        let network_id = self.wallet.network_id()?;
+        let inscriptions = (index..end)
+            .map(|index| {
+                let (secret_key, public_key) = keys.keypair(index)?;
+                let (_, script_sig) = mint_token_demo(&public_key, &network_id)?;
+                Ok((index, script_sig, secret_key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.commit_reveal_batch(&inscriptions, FEE_MINT).await
+    }
+
+    /// Scans the P2SH addresses of the inscriptions journaled for the key
+    /// indices `start..start + window` for unspent commit outputs and spends
+    /// them. Unless `sweep` is set, the original envelope is revealed along
+    /// with the protocol fee; a sweep skips the protocol fee (the operation is
+    /// rejected by the indexer) and returns the funds to the account.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn recover(&self, start: u32, window: u32, sweep: bool) -> Result<()> {
+        let account = self.core_account().await?;
+        let network_id = self.wallet.network_id()?;
+        let rpc_api = self.wallet.rpc_api();
+        let keys = self.inscription_keys().await?;
+
+        let journal = self.journal()?;
+
+        let mut candidates = HashMap::new();
+        for entry in journal.entries(start, start.saturating_add(window)) {
+            let (secret_key, _) = keys.keypair(entry.index)?;
+            let redeem_script = entry.redeem_script()?;
+            let p2sh = p2sh_address(&redeem_script, &network_id)?;
+            candidates.insert(
+                p2sh,
+                (entry.index, redeem_script, secret_key, entry.protocol_fee),
+            );
+        }
+
+        if candidates.is_empty() {
+            log::info(format!(
+                "No inscriptions recorded for key indices {start}..{}",
+                start.saturating_add(window)
+            ))?;
+            return Ok(());
+        }
+
+        let entries = rpc_api
+            .get_utxos_by_addresses(candidates.keys().cloned().collect())
+            .await?;
+        if entries.is_empty() {
+            log::info(format!(
+                "No stranded commit outputs found for key indices {start}..{}",
+                start.saturating_add(window)
+            ))?;
+            return Ok(());
+        }
+
+        for entry in entries {
+            let Some((index, redeem_script, secret_key, protocol_fee)) = entry
+                .address
+                .as_ref()
+                .and_then(|address| candidates.get(address))
+            else {
+                continue;
+            };
+
+            let fee_policy = if sweep {
+                FeePolicy::default()
+            } else {
+                FeePolicy::new(*protocol_fee)
+            };
+            let outpoint: TransactionOutpoint = entry.outpoint.into();
+            log::info(format!(
+                "Found {} at {outpoint} (key index {index})",
+                sompi_to_kaspa_string_with_suffix(entry.utxo_entry.amount, &network_id.into()),
+            ))?;
+
+            let reveal = InscriptionBuilder::new(network_id, account.change_address()?)
+                .with_fee_policy(fee_policy)
+                .recover(
+                    outpoint,
+                    entry.utxo_entry.amount,
+                    entry.utxo_entry.block_daa_score,
+                    redeem_script,
+                    secret_key,
+                );
+
+            let result = match reveal {
+                Ok(reveal) => rpc_api
+                    .submit_transaction((&reveal.transaction).into(), false)
+                    .await
+                    .map_err(Error::from),
+                Err(err) => Err(err.into()),
+            };
+
+            match result {
+                Ok(txid) if sweep => log::success(format!("Sweep transaction submitted {txid}"))?,
+                Ok(txid) => log::success(format!("Reveal transaction submitted {txid}"))?,
+                Err(err) => log::error(format!("Unable to recover {outpoint}: {err}"))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn inscription_builder(
+        &self,
+        account: &Arc<dyn CoreAccount>,
+        protocol_fee: u64,
+    ) -> Result<InscriptionBuilder> {
+        let network_id = self.wallet.network_id()?;
+        let keydata = account.prv_key_data(self.wallet_secret.clone()).await?;
+        let signer = Arc::new(Signer::new(account.clone(), keydata, None));
+        let feerate = self
+            .wallet
+            .rpc_api()
+            .get_fee_estimate()
+            .await?
+            .priority_bucket
+            .feerate;
+
+        Ok(
+            InscriptionBuilder::new(network_id, account.change_address()?)
+                .with_utxo_entries(UtxoIterator::new(account.utxo_context()))
+                .with_fee_policy(
+                    FeePolicy::new(protocol_fee)
+                        .with_payback(PAYBACK_AMOUNT)
+                        .with_feerate(feerate),
+                )
+                .with_signer(signer),
+        )
+    }
+
+    /// Submits the commit transactions of the inscription and, once the
+    /// commit is accepted, its reveal transaction. Returns the reveal id.
     #[cfg(not(target_arch = "wasm32"))]
     async fn commit_reveal_chain(
         &self,
-        p2sh: Address,
-        redeem_lock: Address,
+        builder: &InscriptionBuilder,
+        index: u32,
         script_sig: Vec<u8>,
         secret_key: secp256k1::SecretKey,
-        reveal_fee: u64,
-    ) {
-        let payback_amount = 10 * SOMPI_PER_KASPA;
-        let commit_fee = (SOMPI_PER_KASPA as f64 * 0.20) as u64;
-        let commit_total_amount = reveal_fee + payback_amount;
-        let account = self.account.clone().unwrap();
-        let recipient = account.receive_address.clone().unwrap();
-        println!("Destination address {}", recipient.clone());
+    ) -> Result<TransactionId> {
+        let network_id = self.wallet.network_id()?;
+        let rpc_api = self.wallet.rpc_api();
+
+        confirm_estimate(&builder.estimate(&script_sig)?, &network_id)?;
+        let inscription = builder.build(&script_sig, &secret_key)?;
+
+        log::info(format!(
+            "Commit {} to {} (reveal fee {}, mass {})",
+            sompi_to_kaspa_string_with_suffix(inscription.commit_amount, &network_id.into()),
+            inscription.p2sh,
+            sompi_to_kaspa_string_with_suffix(inscription.reveal.fee, &network_id.into()),
+            inscription.reveal.mass,
+        ))?;
+
+        // the commit output can only be recovered with its redeem script
+        self.journal()?.record([JournalEntry::new(
+            index,
+            &script_sig,
+            builder.fee_policy().protocol_fee,
+        )])?;
+
+        let monitor_handle: JoinHandle<_> = await_utxo_inclusion(
+            inscription.p2sh.clone(),
+            inscription.commit_amount,
+            rpc_api.clone(),
+        );
 
-        let monitor_handle: JoinHandle<_> =
-            await_utxo_inclusion(p2sh, commit_total_amount, self.wallet.rpc_api());
-
-        let send_request = AccountsSendRequest {
-            account_id: account.account_id,
-            wallet_secret: "111".into(),
-            payment_secret: None,
-            destination: PaymentOutputs::from((redeem_lock, commit_total_amount)).into(),
-            priority_fee_sompi: commit_fee.into(),
-            payload: None,
-        };
-        let txsent = self
-            .wallet
-            .as_api()
-            .accounts_send(send_request)
+        for transaction in inscription.commit.iter() {
+            transaction.try_sign()?;
+            transaction.try_submit(&rpc_api).await?;
+        }
+
+        // Wait for commit UTXO
+        let commit_txid = monitor_handle
             .await
-            .unwrap();
+            .map_err(|err| Error::custom(err.to_string()))??;
+        if commit_txid != inscription.commit_outpoint.transaction_id {
+            return Err(Error::custom(format!(
+                "Unexpected commit transaction {commit_txid}"
+            )));
+        }
+        log::success(format!("Commit transaction accepted {commit_txid}"))?;
 
-        let commit_txid = txsent.final_transaction_id().unwrap();
+        let reveal_txid = rpc_api
+            .submit_transaction((&inscription.reveal.transaction).into(), false)
+            .await?;
+        log::success(format!("Reveal transaction submitted {reveal_txid}"))?;
 
-        // Wait for commit UTXO
-        match monitor_handle.await.unwrap() {
-            Ok(tid) => {
-                println!("Monitor task  01 completed successfully");
-
-                assert!(commit_txid == tid);
-
-                // Assume latest transaction ID is commit transaction.
-                let txs = self
-                    .wallet
-                    .as_api()
-                    .transactions_data_get_range(
-                        account.account_id,
-                        self.wallet.network_id().unwrap(),
-                        0..10,
-                    )
-                    .await
-                    .unwrap();
-
-                let prev_tx: &Arc<kaspa_wallet_core::prelude::TransactionRecord> = txs
-                    .transactions
-                    .iter()
-                    .find(|&tx| tx.id == tid)
-                    .expect("Commit transaction");
-
-                // prev_tx.transaction_data.id()
-                println!("prev_tx tid {:?}", prev_tx.id);
-                println!("prev_tx daa score {:?}", prev_tx.block_daa_score);
-
-                let (transaction, _, _) = reveal_transaction(
-                    TransactionDetails {
-                        script_sig,
-                        recipient: recipient.clone(),
-                        secret_key,
-                        prev_tx_tid: prev_tx.id,
-                        prev_tx_score: prev_tx.block_daa_score,
-                    },
-                    payback_amount,
-                    reveal_fee,
-                    self.wallet.network_id().unwrap(),
-                );
+        Ok(reveal_txid)
+    }
 
-                let submitted_reveal_tid = self
-                    .wallet
-                    .rpc_api()
-                    .submit_transaction(transaction.rpc_transaction(), false)
+    /// Funds all `inscriptions` with a single commit transaction and submits
+    /// their reveal transactions in parallel once the commit is accepted.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn commit_reveal_batch(
+        &self,
+        inscriptions: &[(u32, Vec<u8>, secp256k1::SecretKey)],
+        protocol_fee: u64,
+    ) -> Result<BatchReport> {
+        let account = self.core_account().await?;
+        let network_id = self.wallet.network_id()?;
+        let rpc_api = self.wallet.rpc_api();
+
+        let builder = self.inscription_builder(&account, protocol_fee).await?;
+        let estimate = builder.estimate_batch(
+            inscriptions
+                .iter()
+                .map(|(_, redeem_script, _)| redeem_script.as_slice()),
+        )?;
+        confirm_estimate(&estimate, &network_id)?;
+        let InscriptionBatch { commit, reveals } = builder.build_batch(
+            &inscriptions
+                .iter()
+                .map(|(_, redeem_script, secret_key)| (redeem_script.clone(), *secret_key))
+                .collect::<Vec<_>>(),
+        )?;
+
+        let first = reveals
+            .first()
+            .ok_or_else(|| Error::custom("Empty inscription batch"))?;
+        let commit_amount = reveals.iter().map(|reveal| reveal.amount).sum::<u64>();
+        log::info(format!(
+            "Commit {} to {} P2SH outputs (reveal fee {}, mass {})",
+            sompi_to_kaspa_string_with_suffix(commit_amount, &network_id.into()),
+            reveals.len(),
+            sompi_to_kaspa_string_with_suffix(first.reveal.fee, &network_id.into()),
+            first.reveal.mass,
+        ))?;
+
+        self.journal()?
+            .record(inscriptions.iter().map(|(index, redeem_script, _)| {
+                JournalEntry::new(*index, redeem_script, protocol_fee)
+            }))?;
+
+        // all P2SH outputs are created by the same transaction
+        let monitor_handle: JoinHandle<_> =
+            await_utxo_inclusion(first.p2sh.clone(), first.amount, rpc_api.clone());
+
+        for transaction in commit.iter() {
+            transaction.try_sign()?;
+            transaction.try_submit(&rpc_api).await?;
+        }
+
+        let commit_txid = monitor_handle
+            .await
+            .map_err(|err| Error::custom(err.to_string()))??;
+        if commit_txid != first.outpoint.transaction_id {
+            return Err(Error::custom(format!(
+                "Unexpected commit transaction {commit_txid}"
+            )));
+        }
+        log::success(format!("Commit transaction accepted {commit_txid}"))?;
+
+        let results = join_all(reveals.iter().map(|reveal| {
+            let rpc_api = rpc_api.clone();
+            async move {
+                rpc_api
+                    .submit_transaction((&reveal.reveal.transaction).into(), false)
                     .await
-                    .expect("Reveal transaction submit");
-
-                println!("Reveal transaction submitted {:?}", submitted_reveal_tid);
-
-                println!();
-                let t = self
-                    .wallet
-                    .rpc_api()
-                    .get_mempool_entry(transaction.id(), false, false)
-                    .await;
-                println!("Mempool fetch {:?}", t);
-                println!();
-
-                let monitor_handle: JoinHandle<_> =
-                    await_utxo_inclusion(recipient, payback_amount, self.wallet.rpc_api());
-
-                match monitor_handle.await.unwrap() {
-                    Ok(reveal_tid) => {
-                        println!();
-                        println!("Monitor task 02 completed successfully");
-
-                        assert!(reveal_tid == submitted_reveal_tid);
-
-                        let txs = self
-                            .wallet
-                            .as_api()
-                            .transactions_data_get_range(
-                                account.account_id,
-                                self.wallet.network_id().unwrap(),
-                                0..10,
-                            )
-                            .await
-                            .unwrap();
-                        let reveal_tx: &Arc<kaspa_wallet_core::prelude::TransactionRecord> = txs
-                            .transactions
-                            .iter()
-                            .find(|&tx| tx.id == reveal_tid)
-                            .expect("Reveal transaction");
-
-                        println!("reveal tx {:?}", reveal_tx);
-                    }
-                    Err(e) => eprintln!("Monitor task failed: {:?}", e),
+            }
+        }))
+        .await;
+
+        let mut report = BatchReport::default();
+        for (reveal, result) in reveals.iter().zip(results) {
+            match result {
+                Ok(reveal_txid) => {
+                    log::success(format!("Reveal transaction submitted {reveal_txid}"))?;
+                    report.submitted.push((reveal.outpoint, reveal_txid));
+                }
+                Err(err) => {
+                    log::error(format!("Reveal of {} failed: {err}", reveal.outpoint))?;
+                    report.failed.push((reveal.outpoint, Error::KaspaRpc(err)));
                 }
             }
-            Err(e) => eprintln!("Monitor task failed: {:?}", e),
         }
+
+        Ok(report)
+    }
+}
+
+/// Displays the fee breakdown of an inscription and asks the user to proceed.
+fn confirm_estimate(estimate: &FeeEstimate, network_id: &NetworkId) -> Result<()> {
+    let kas = |sompi| sompi_to_kaspa_string_with_suffix(sompi, &(*network_id).into());
+    log::info(format!(
+        "Protocol fee: {}\nCommit network fee: {} (mass {})\nReveal network fee: {} (mass {})\nTotal: {}",
+        kas(estimate.protocol_fee),
+        kas(estimate.commit_fee),
+        estimate.commit_mass,
+        kas(estimate.reveal_fee),
+        estimate.reveal_mass,
+        kas(estimate.total()),
+    ))?;
+
+    confirm("Proceed?")
+}
+
+/// Asks the user to confirm, failing with [`Error::UserAbort`] otherwise.
+fn confirm(prompt: &str) -> Result<()> {
+    if cliclack::confirm(prompt)
+        .interact()
+        .map_err(|_| Error::UserAbort)?
+    {
+        Ok(())
+    } else {
+        Err(Error::UserAbort)
     }
 }
 
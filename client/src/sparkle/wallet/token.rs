@@ -0,0 +1,29 @@
+use super::Wallet;
+use kaspa_consensus_core::tx::TransactionId;
+use sparkle_core::inscription::sparkle_redeem_script;
+use sparkle_core::model::sparkle::SparkleTransaction;
+use sparkle_rs::result::Result;
+
+impl Wallet {
+    /// Inscribes the sparkle `transaction` with the inscription key `index`.
+    /// The operation is credited to and debited from the P2PK address of
+    /// that key. The sparkle protocol charges no protocol fee.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn inscribe(
+        &self,
+        transaction: &SparkleTransaction,
+        index: u32,
+    ) -> Result<TransactionId> {
+        transaction
+            .validate()
+            .map_err(sparkle_core::error::Error::from)?;
+
+        let (secret_key, public_key) = self.inscription_keys().await?.keypair(index)?;
+        let redeem_script = sparkle_redeem_script(&public_key, transaction)?;
+        let builder = self
+            .inscription_builder(&self.core_account().await?, 0)
+            .await?;
+        self.commit_reveal_chain(&builder, index, redeem_script, secret_key)
+            .await
+    }
+}
@@ -0,0 +1,490 @@
+//!
+//! Inscription envelope codec.
+//!
+//! An envelope is a script fragment that carries protocol data inside
+//! an unexecuted conditional branch:
+//!
+//! ```text
+//! OpFalse OpIf <namespace> <metadata> <payload> OpEndIf
+//! ```
+//!
+//! The metadata carries the content type of the payload (empty for
+//! kasplex JSON operations). Elements are pushed with explicit data
+//! opcodes (never folded into small integer opcodes).
+//!
+//! Inscriptions embed the envelope in a P2SH redeem script of the form
+//! `<pubkey> OpCheckSig <envelope>`, which is revealed in the signature
+//! script of the transaction spending the P2SH output. The redeem script is
+//! revealed as a single push, so the whole script, payload included, is
+//! limited to [`MAX_SCRIPT_ELEMENT_SIZE`] (520) bytes. This is a hard limit:
+//! larger payloads can not be inscribed. The decoder nevertheless accepts
+//! payloads split into consecutive pushes by other encoders.
+//!
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use kaspa_consensus_core::tx::PopulatedTransaction;
+use kaspa_txscript::opcodes::codes::*;
+use kaspa_txscript::opcodes::{deserialize_next_opcode, OpCodeImplementation};
+use kaspa_txscript::script_builder::{ScriptBuilder, ScriptBuilderError};
+use kaspa_txscript::MAX_SCRIPT_ELEMENT_SIZE;
+use kaspa_txscript_errors::TxScriptError;
+use thiserror::Error;
+
+/// Content type of JSON payloads (implied by empty metadata).
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+/// Content type of borsh-encoded payloads.
+pub const CONTENT_TYPE_BORSH: &str = "application/borsh";
+/// Content type of UTF-8 text payloads.
+pub const CONTENT_TYPE_TEXT: &str = "text/plain;charset=utf-8";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("Script does not contain an envelope")]
+    NotFound,
+
+    #[error("Malformed opcode at offset {offset}: {err}")]
+    Opcode { offset: usize, err: TxScriptError },
+
+    #[error("Unexpected end of script at offset {offset}, expecting {expected}")]
+    UnexpectedEnd {
+        offset: usize,
+        expected: &'static str,
+    },
+
+    #[error("Unexpected opcode 0x{opcode:02x} at offset {offset}, expecting {expected}")]
+    UnexpectedOpcode {
+        offset: usize,
+        opcode: u8,
+        expected: &'static str,
+    },
+
+    #[error("Unexpected trailing data at offset {0}")]
+    TrailingData(usize),
+
+    #[error("Invalid public key length: {0}")]
+    PubKeyLength(usize),
+
+    #[error(
+        "Redeem script of {0} bytes exceeds the hard limit of {MAX_SCRIPT_ELEMENT_SIZE} bytes, \
+        the payload is too large to be inscribed"
+    )]
+    RedeemScriptSize(usize),
+
+    #[error("Invalid content type: {0}")]
+    ContentType(String),
+
+    #[error("Borsh error: {0}")]
+    Borsh(String),
+
+    #[error(transparent)]
+    ScriptBuilder(#[from] ScriptBuilderError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+type OpCode = Box<dyn OpCodeImplementation<PopulatedTransaction<'static>>>;
+
+/// Decoded inscription envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub namespace: Vec<u8>,
+    pub metadata: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn new(namespace: &[u8], metadata: &[u8], payload: &[u8]) -> Self {
+        Self {
+            namespace: namespace.to_vec(),
+            metadata: metadata.to_vec(),
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// Creates an envelope carrying a payload of the given content type
+    /// (e.g. `image/png`).
+    pub fn with_content_type(namespace: &[u8], content_type: &str, payload: &[u8]) -> Self {
+        Self::new(namespace, content_type.as_bytes(), payload)
+    }
+
+    /// Creates an envelope carrying a borsh-encoded `value`.
+    pub fn with_borsh<T: BorshSerialize>(namespace: &[u8], value: &T) -> Result<Self> {
+        let payload = borsh::to_vec(value).map_err(|err| Error::Borsh(err.to_string()))?;
+        Ok(Self::with_content_type(
+            namespace,
+            CONTENT_TYPE_BORSH,
+            &payload,
+        ))
+    }
+
+    /// Content type of the payload; JSON if the metadata is empty.
+    pub fn content_type(&self) -> Result<&str> {
+        if self.metadata.is_empty() {
+            Ok(CONTENT_TYPE_JSON)
+        } else {
+            std::str::from_utf8(&self.metadata)
+                .map_err(|_| Error::ContentType(String::from_utf8_lossy(&self.metadata).into()))
+        }
+    }
+
+    /// Decodes a borsh-encoded payload.
+    pub fn try_borsh<T: BorshDeserialize>(&self) -> Result<T> {
+        match self.content_type()? {
+            CONTENT_TYPE_BORSH => {
+                borsh::from_slice(&self.payload).map_err(|err| Error::Borsh(err.to_string()))
+            }
+            content_type => Err(Error::ContentType(content_type.to_string())),
+        }
+    }
+
+    /// Case-insensitive namespace comparison (`kasplex` == `KASPLEX`).
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.namespace.eq_ignore_ascii_case(namespace.as_bytes())
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        encode(&self.namespace, &self.metadata, &self.payload)
+    }
+}
+
+/// Redeem script of a P2SH inscription: `<pubkey> OpCheckSig <envelope>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedeemScript {
+    pub pubkey: Vec<u8>,
+    pub envelope: Envelope,
+}
+
+/// Serializes an envelope into `OpFalse OpIf <namespace> <metadata> <payload> OpEndIf`.
+///
+/// The script is not size checked, see [`encode_redeem_script`].
+pub fn encode(namespace: &[u8], metadata: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    let mut script = vec![OpFalse, OpIf];
+    add_push(&mut script, namespace);
+    add_push(&mut script, metadata);
+    add_push(&mut script, payload);
+    script.push(OpEndIf);
+    Ok(script)
+}
+
+/// Appends a push of `data` with an explicit data opcode. Unlike
+/// [`ScriptBuilder::add_data`], single bytes are not folded into small
+/// integer opcodes (`[0x00]` would otherwise decode as an empty push).
+/// Minimal push rules only apply to executed branches.
+fn add_push(script: &mut Vec<u8>, data: &[u8]) {
+    let len = data.len();
+    match len {
+        0 => script.push(OpFalse),
+        1..=75 => script.push(OpData1 + (len - 1) as u8),
+        76..=0xff => script.extend([OpPushData1, len as u8]),
+        0x100..=0xffff => {
+            script.push(OpPushData2);
+            script.extend((len as u16).to_le_bytes());
+        }
+        _ => {
+            script.push(OpPushData4);
+            script.extend((len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+}
+
+/// Parses a script consisting of exactly one envelope.
+pub fn decode(script: &[u8]) -> Result<Envelope> {
+    let mut parser = Parser::new(script);
+    let envelope = parser.envelope()?;
+    parser.end()?;
+    Ok(envelope)
+}
+
+/// Serializes a P2SH redeem script locking the envelope to a schnorr `pubkey`.
+///
+/// Fails with [`Error::RedeemScriptSize`] if the script exceeds
+/// [`MAX_SCRIPT_ELEMENT_SIZE`] (520 bytes), the hard limit of the push
+/// revealing it, which leaves room for payloads of about 470 bytes.
+pub fn encode_redeem_script(pubkey: &[u8], envelope: &Envelope) -> Result<Vec<u8>> {
+    if pubkey.len() != 32 {
+        return Err(Error::PubKeyLength(pubkey.len()));
+    }
+
+    let mut script = ScriptBuilder::new()
+        .add_data(pubkey)?
+        .add_op(OpCheckSig)?
+        .drain();
+    script.extend(envelope.encode()?);
+    if script.len() > MAX_SCRIPT_ELEMENT_SIZE {
+        return Err(Error::RedeemScriptSize(script.len()));
+    }
+    Ok(script)
+}
+
+/// Parses a redeem script produced by [`encode_redeem_script`].
+pub fn decode_redeem_script(script: &[u8]) -> Result<RedeemScript> {
+    let mut parser = Parser::new(script);
+
+    let pubkey = parser.expect_data("public key")?;
+    if pubkey.len() != 32 {
+        return Err(Error::PubKeyLength(pubkey.len()));
+    }
+    parser.expect_op(OpCheckSig, "OpCheckSig")?;
+
+    let envelope = parser.envelope()?;
+    parser.end()?;
+
+    Ok(RedeemScript { pubkey, envelope })
+}
+
+/// Extracts the envelope from the signature script of a P2SH input spending
+/// an inscription commit (`<signature> <redeem script>`).
+///
+/// Returns [`Error::NotFound`] if the signature script does not end with a
+/// redeem script carrying an envelope.
+pub fn decode_signature_script(script: &[u8]) -> Result<RedeemScript> {
+    let mut parser = Parser::new(script);
+    let mut redeem_script = None;
+    while let Some((_, opcode)) = parser.next()? {
+        redeem_script = push_data(&opcode);
+        if redeem_script.is_none() {
+            return Err(Error::NotFound);
+        }
+    }
+
+    match redeem_script {
+        Some(redeem_script) if is_inscription(&redeem_script) => {
+            decode_redeem_script(&redeem_script)
+        }
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// Checks for the `<pubkey> OpCheckSig OpFalse OpIf` redeem script prefix.
+fn is_inscription(redeem_script: &[u8]) -> bool {
+    redeem_script.len() > 36
+        && redeem_script[0] == OpData32
+        && redeem_script[33] == OpCheckSig
+        && redeem_script[34] == OpFalse
+        && redeem_script[35] == OpIf
+}
+
+/// Returns the data pushed by `opcode` or `None` if it is not a push opcode.
+fn push_data(opcode: &OpCode) -> Option<Vec<u8>> {
+    match opcode.value() {
+        OpFalse => Some(vec![]),
+        OpData1..=OpPushData4 => Some(opcode.get_data().to_vec()),
+        Op1Negate => Some(vec![0x81]),
+        OpTrue..=Op16 => Some(vec![opcode.value() - OpTrue + 1]),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    script: &'a [u8],
+    iter: std::slice::Iter<'a, u8>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(script: &'a [u8]) -> Self {
+        Self {
+            script,
+            iter: script.iter(),
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.script.len() - self.iter.as_slice().len()
+    }
+
+    fn next(&mut self) -> Result<Option<(usize, OpCode)>> {
+        let offset = self.offset();
+        match deserialize_next_opcode(&mut self.iter) {
+            Some(Ok(opcode)) => Ok(Some((offset, opcode))),
+            Some(Err(err)) => Err(Error::Opcode { offset, err }),
+            None => Ok(None),
+        }
+    }
+
+    fn expect(&mut self, expected: &'static str) -> Result<(usize, OpCode)> {
+        let offset = self.offset();
+        self.next()?
+            .ok_or(Error::UnexpectedEnd { offset, expected })
+    }
+
+    fn expect_op(&mut self, value: u8, expected: &'static str) -> Result<()> {
+        let (offset, opcode) = self.expect(expected)?;
+        if opcode.value() != value {
+            Err(Error::UnexpectedOpcode {
+                offset,
+                opcode: opcode.value(),
+                expected,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn expect_data(&mut self, expected: &'static str) -> Result<Vec<u8>> {
+        let (offset, opcode) = self.expect(expected)?;
+        push_data(&opcode).ok_or(Error::UnexpectedOpcode {
+            offset,
+            opcode: opcode.value(),
+            expected,
+        })
+    }
+
+    fn envelope(&mut self) -> Result<Envelope> {
+        self.expect_op(OpFalse, "OpFalse")?;
+        self.expect_op(OpIf, "OpIf")?;
+        let namespace = self.expect_data("namespace")?;
+        let metadata = self.expect_data("metadata")?;
+        let mut payload = self.expect_data("payload")?;
+        // reassemble payloads split into multiple pushes
+        loop {
+            let (offset, opcode) = self.expect("OpEndIf")?;
+            match push_data(&opcode) {
+                Some(data) => payload.extend(data),
+                None if opcode.value() == OpEndIf => break,
+                None => {
+                    return Err(Error::UnexpectedOpcode {
+                        offset,
+                        opcode: opcode.value(),
+                        expected: "OpEndIf",
+                    })
+                }
+            }
+        }
+
+        Ok(Envelope {
+            namespace,
+            metadata,
+            payload,
+        })
+    }
+
+    fn end(&self) -> Result<()> {
+        if self.iter.as_slice().is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingData(self.offset()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let payload = br#"{"p":"krc-20","op":"mint","tick":"SPARKL"}"#;
+        let envelope = Envelope::new(b"kasplex", &[], payload);
+
+        let script = envelope.encode().unwrap();
+        assert_eq!(&script[..2], &[OpFalse, OpIf]);
+        assert_eq!(*script.last().unwrap(), OpEndIf);
+        assert_eq!(decode(&script).unwrap(), envelope);
+
+        let redeem_script = encode_redeem_script(&PUBKEY, &envelope).unwrap();
+        let decoded = decode_redeem_script(&redeem_script).unwrap();
+        assert_eq!(decoded.pubkey, PUBKEY);
+        assert_eq!(decoded.envelope, envelope);
+        assert!(decoded.envelope.has_namespace("KASPLEX"));
+    }
+
+    #[test]
+    fn test_envelope_errors() {
+        let script = encode(b"kasplex", &[], b"payload").unwrap();
+
+        assert_eq!(
+            decode(&script[..script.len() - 1]),
+            Err(Error::UnexpectedEnd {
+                offset: script.len() - 1,
+                expected: "OpEndIf"
+            })
+        );
+
+        let mut trailing = script.clone();
+        trailing.push(OpFalse);
+        assert_eq!(decode(&trailing), Err(Error::TrailingData(script.len())));
+
+        let mut not_false = script.clone();
+        not_false[0] = OpTrue;
+        assert!(matches!(
+            decode(&not_false),
+            Err(Error::UnexpectedOpcode {
+                offset: 0,
+                opcode: OpTrue,
+                ..
+            })
+        ));
+
+        // truncated push of the payload
+        let truncated = &script[..script.len() - 3];
+        assert!(matches!(decode(truncated), Err(Error::Opcode { .. })));
+    }
+
+    #[test]
+    fn test_envelope_explicit_pushes() {
+        // a single zero byte must not fold into `OpFalse`
+        let envelope = Envelope::with_content_type(b"sparkle", "image/png", &[0]);
+
+        let script = envelope.encode().unwrap();
+        let mut parser = Parser::new(&script);
+        let mut pushes = vec![];
+        while let Some((_, opcode)) = parser.next().unwrap() {
+            pushes.push(opcode.value());
+        }
+        assert_eq!(pushes, [OpFalse, OpIf, OpData7, OpData9, OpData1, OpEndIf]);
+
+        let decoded = decode(&script).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.content_type(), Ok("image/png"));
+
+        let empty = Envelope::new(b"sparkle", &[1], &[]);
+        assert_eq!(decode(&empty.encode().unwrap()).unwrap(), empty);
+
+        // payloads split by other encoders are reassembled
+        let mut split = envelope.encode().unwrap();
+        split.pop();
+        split.extend([OpData2, 1, 2, OpEndIf]);
+        assert_eq!(decode(&split).unwrap().payload, [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_redeem_script_size() {
+        let envelope = |len: usize| Envelope::new(b"sparkle", &[], &vec![0x2a; len]);
+
+        // 34 bytes of `<pubkey> OpCheckSig`, 12 bytes of envelope framing
+        // and a 3 byte `OpPushData2` payload push
+        let redeem_script = encode_redeem_script(&PUBKEY, &envelope(471)).unwrap();
+        assert_eq!(redeem_script.len(), 520);
+        assert_eq!(
+            decode_redeem_script(&redeem_script).unwrap().envelope,
+            envelope(471)
+        );
+
+        assert_eq!(
+            encode_redeem_script(&PUBKEY, &envelope(472)),
+            Err(Error::RedeemScriptSize(521))
+        );
+    }
+
+    #[test]
+    fn test_envelope_borsh() {
+        let value = (42u64, "sparkle".to_string());
+        let envelope = Envelope::with_borsh(b"sparkle", &value).unwrap();
+        assert_eq!(envelope.content_type(), Ok(CONTENT_TYPE_BORSH));
+        assert_eq!(envelope.try_borsh::<(u64, String)>(), Ok(value));
+
+        let json = Envelope::new(b"kasplex", &[], b"{}");
+        assert_eq!(json.content_type(), Ok(CONTENT_TYPE_JSON));
+        assert!(json.try_borsh::<(u64, String)>().is_err());
+    }
+
+    #[test]
+    fn test_signature_script_without_envelope() {
+        let signature = ScriptBuilder::new().add_data(&[1; 65]).unwrap().drain();
+        assert_eq!(decode_signature_script(&signature), Err(Error::NotFound));
+        assert_eq!(decode_signature_script(&[]), Err(Error::NotFound));
+    }
+}
@@ -39,6 +39,30 @@ pub enum Error {
 
     #[error("Invalid network id : {0}")]
     NetworkId(String),
+
+    #[error("Non-standard script: {0}")]
+    NonStandardScript(#[from] kaspa_txscript_errors::TxScriptError),
+
+    #[error("Envelope error: {0}")]
+    Envelope(#[from] crate::envelope::Error),
+
+    #[error(transparent)]
+    ScriptBuilder(#[from] kaspa_txscript::script_builder::ScriptBuilderError),
+
+    #[error(transparent)]
+    Wallet(#[from] kaspa_wallet_core::error::Error),
+
+    #[error("BIP32 error: {0}")]
+    Bip32(#[from] kaspa_bip32::Error),
+
+    #[error("Invalid KRC-20 operation: {0}")]
+    Validation(#[from] crate::model::kasplex::v1::ValidationError),
+
+    #[error("Invalid token amount: {0}")]
+    Amount(#[from] crate::model::AmountError),
+
+    #[error("Invalid sparkle operation: {0}")]
+    Sparkle(#[from] crate::model::sparkle::SparkleError),
 }
 
 impl Error {
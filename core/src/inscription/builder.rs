@@ -0,0 +1,701 @@
+//!
+//! Commit/reveal transaction builder.
+//!
+//! The commit transaction is produced by the wallet [`Generator`] from the
+//! supplied UTXO set and funds a P2SH output locked by the inscription
+//! redeem script. The reveal transaction spends that output, exposing the
+//! envelope in its signature script and burning the protocol fee.
+//!
+
+use super::sign_redeem_input;
+use crate::error::Error;
+use crate::result::Result;
+use kaspa_addresses::Address;
+use kaspa_consensus_client::UtxoEntry as ClientUTXO;
+use kaspa_consensus_core::config::params::Params;
+use kaspa_consensus_core::constants::SOMPI_PER_KASPA;
+use kaspa_consensus_core::network::NetworkId;
+use kaspa_consensus_core::subnets::SubnetworkId;
+use kaspa_consensus_core::tx::{
+    ScriptPublicKey, Transaction, TransactionId, TransactionInput, TransactionOutpoint,
+    TransactionOutput, UtxoEntry,
+};
+use kaspa_txscript::{
+    extract_script_pub_key_address, pay_to_address_script, pay_to_script_hash_script,
+};
+use kaspa_wallet_core::tx::{
+    Generator, GeneratorSettings, MassCalculator, PaymentDestination, PaymentOutput,
+    PaymentOutputs, PendingTransaction, SignerT,
+};
+use kaspa_wallet_core::utxo::{NetworkParams, UtxoEntryReference};
+use secp256k1::SecretKey;
+use std::sync::Arc;
+
+/// Size of the `OpData65 <signature + sighash type>` push prepended to the redeem script.
+const SIGNATURE_PUSH_SIZE: usize = 66;
+
+/// Fees paid by an inscription.
+#[derive(Debug, Clone, Copy)]
+pub struct FeePolicy {
+    /// Protocol fee burned by the reveal transaction (e.g. [`FEE_DEPLOY`](crate::constants::FEE_DEPLOY)).
+    pub protocol_fee: u64,
+    /// Priority fee added on top of the minimum network fee of each transaction.
+    pub priority_fee: u64,
+    /// Amount returned to the change address by the reveal transaction.
+    pub payback: u64,
+    /// Fee rate (sompi per gram of mass) applied to the commit and reveal
+    /// transactions, typically taken from the node's fee estimate. The
+    /// minimum network fee is charged if it exceeds the fee rate based fee.
+    pub feerate: Option<f64>,
+}
+
+impl FeePolicy {
+    pub fn new(protocol_fee: u64) -> Self {
+        Self {
+            protocol_fee,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_priority_fee(self, priority_fee: u64) -> Self {
+        Self {
+            priority_fee,
+            ..self
+        }
+    }
+
+    pub fn with_payback(self, payback: u64) -> Self {
+        Self { payback, ..self }
+    }
+
+    pub fn with_feerate(self, feerate: f64) -> Self {
+        Self {
+            feerate: Some(feerate),
+            ..self
+        }
+    }
+
+    /// Network fee of a transaction of the given `mass` under the fee rate.
+    pub(crate) fn feerate_fee(&self, mass: u64) -> u64 {
+        self.feerate
+            .map(|feerate| (mass as f64 * feerate).ceil() as u64)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self {
+            protocol_fee: 0,
+            priority_fee: 0,
+            // keeps the storage mass of the reveal transaction low
+            payback: SOMPI_PER_KASPA,
+            feerate: None,
+        }
+    }
+}
+
+/// Signed reveal transaction along with the UTXO entries it spends.
+#[derive(Debug, Clone)]
+pub struct Reveal {
+    pub transaction: Transaction,
+    pub entries: Vec<UtxoEntry>,
+    pub mass: u64,
+    pub fee: u64,
+}
+
+impl Reveal {
+    pub fn id(&self) -> TransactionId {
+        self.transaction.id()
+    }
+}
+
+/// Mass and fee breakdown of an inscription, computed before anything is signed.
+#[derive(Debug, Clone, Copy)]
+pub struct FeeEstimate {
+    /// Protocol fee burned by the reveal transaction.
+    pub protocol_fee: u64,
+    /// Combined mass of the commit transactions.
+    pub commit_mass: u64,
+    /// Network fees paid by the commit transactions.
+    pub commit_fee: u64,
+    pub reveal_mass: u64,
+    /// Network fee paid by the reveal transaction (excluding the protocol fee).
+    pub reveal_fee: u64,
+}
+
+impl FeeEstimate {
+    pub fn network_fee(&self) -> u64 {
+        self.commit_fee + self.reveal_fee
+    }
+
+    /// Total cost of the inscription (protocol and network fees).
+    pub fn total(&self) -> u64 {
+        self.protocol_fee + self.network_fee()
+    }
+}
+
+/// Commit and reveal transactions of a single inscription.
+pub struct Inscription {
+    /// P2SH address funded by the commit transaction.
+    pub p2sh: Address,
+    /// Commit transactions produced by the generator; any batch (compound)
+    /// transactions precede the final transaction funding the P2SH output.
+    pub commit: Vec<PendingTransaction>,
+    /// Outpoint of the P2SH output within the final commit transaction.
+    pub commit_outpoint: TransactionOutpoint,
+    /// Value locked in the P2SH output.
+    pub commit_amount: u64,
+    pub reveal: Reveal,
+}
+
+/// Reveal of a single inscription within a batch.
+pub struct BatchReveal {
+    /// P2SH address funded by the commit transaction.
+    pub p2sh: Address,
+    /// Outpoint of the P2SH output within the final commit transaction.
+    pub outpoint: TransactionOutpoint,
+    /// Value locked in the P2SH output.
+    pub amount: u64,
+    pub reveal: Reveal,
+}
+
+/// Commit transactions funding multiple inscriptions and their reveals.
+pub struct InscriptionBatch {
+    pub commit: Vec<PendingTransaction>,
+    pub reveals: Vec<BatchReveal>,
+}
+
+pub struct InscriptionBuilder {
+    network_id: NetworkId,
+    change_address: Address,
+    reveal_address: Option<Address>,
+    utxo_entries: Vec<UtxoEntryReference>,
+    fee_policy: FeePolicy,
+    signer: Option<Arc<dyn SignerT>>,
+}
+
+impl InscriptionBuilder {
+    pub fn new(network_id: NetworkId, change_address: Address) -> Self {
+        Self {
+            network_id,
+            change_address,
+            reveal_address: None,
+            utxo_entries: vec![],
+            fee_policy: FeePolicy::default(),
+            signer: None,
+        }
+    }
+
+    /// UTXO set funding the commit transaction.
+    pub fn with_utxo_entries<I>(self, utxo_entries: I) -> Self
+    where
+        I: IntoIterator<Item = UtxoEntryReference>,
+    {
+        Self {
+            utxo_entries: utxo_entries.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Destination of the reveal payback if other than the change address
+    /// (e.g. the P2SH output of a marketplace listing).
+    pub fn with_reveal_address(self, reveal_address: Address) -> Self {
+        Self {
+            reveal_address: Some(reveal_address),
+            ..self
+        }
+    }
+
+    pub fn with_fee_policy(self, fee_policy: FeePolicy) -> Self {
+        Self { fee_policy, ..self }
+    }
+
+    /// Signer used by the generator for the commit transactions.
+    pub fn with_signer(self, signer: Arc<dyn SignerT>) -> Self {
+        Self {
+            signer: Some(signer),
+            ..self
+        }
+    }
+
+    pub fn network_id(&self) -> NetworkId {
+        self.network_id
+    }
+
+    pub fn change_address(&self) -> &Address {
+        &self.change_address
+    }
+
+    /// Address receiving the payback of reveal transactions.
+    pub fn reveal_address(&self) -> &Address {
+        self.reveal_address.as_ref().unwrap_or(&self.change_address)
+    }
+
+    pub fn fee_policy(&self) -> &FeePolicy {
+        &self.fee_policy
+    }
+
+    pub(crate) fn mass_calculator(&self) -> MassCalculator {
+        let network_params = NetworkParams::from(self.network_id);
+        MassCalculator::new(&Params::from(self.network_id), &network_params)
+    }
+
+    /// Returns the P2SH address locked by `redeem_script` on the builder's network.
+    pub fn p2sh_address(&self, redeem_script: &[u8]) -> Result<Address> {
+        p2sh_address(redeem_script, &self.network_id)
+    }
+
+    /// Builds the commit transactions funding the inscription P2SH output and
+    /// the reveal transaction spending it.
+    pub fn build(&self, redeem_script: &[u8], secret_key: &SecretKey) -> Result<Inscription> {
+        let InscriptionBatch { commit, reveals } =
+            self.build_batch(&[(redeem_script.to_vec(), *secret_key)])?;
+        let BatchReveal {
+            p2sh,
+            outpoint,
+            amount,
+            reveal,
+        } = reveals.into_iter().next().expect("batch reveal");
+
+        Ok(Inscription {
+            p2sh,
+            commit,
+            commit_outpoint: outpoint,
+            commit_amount: amount,
+            reveal,
+        })
+    }
+
+    /// Builds a single commit transaction funding one P2SH output per redeem
+    /// script along with the reveal transactions spending each of them.
+    pub fn build_batch(&self, inscriptions: &[(Vec<u8>, SecretKey)]) -> Result<InscriptionBatch> {
+        if inscriptions.is_empty() {
+            return Err(Error::custom("No inscriptions supplied"));
+        }
+
+        let mut outputs = Vec::with_capacity(inscriptions.len());
+        for (redeem_script, _) in inscriptions.iter() {
+            let p2sh = self.p2sh_address(redeem_script)?;
+            let (reveal_fee, _) = self.reveal_fee(redeem_script)?;
+            outputs.push(PaymentOutput::new(
+                p2sh,
+                self.fee_policy.payback + reveal_fee,
+            ));
+        }
+
+        let commit = self.commit(PaymentOutputs {
+            outputs: outputs.clone(),
+        })?;
+        let outpoints = find_outpoints(
+            &commit,
+            inscriptions
+                .iter()
+                .map(|(redeem_script, _)| redeem_script.as_slice()),
+        )?;
+
+        let reveals = inscriptions
+            .iter()
+            .zip(outputs)
+            .zip(outpoints)
+            .map(|(((redeem_script, secret_key), output), outpoint)| {
+                let reveal = self.reveal(outpoint, output.amount, 0, redeem_script, secret_key)?;
+                Ok(BatchReveal {
+                    p2sh: output.address,
+                    outpoint,
+                    amount: output.amount,
+                    reveal,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(InscriptionBatch { commit, reveals })
+    }
+
+    /// Estimates the mass and fees of the commit and reveal transactions of
+    /// the inscription locked by `redeem_script`.
+    pub fn estimate(&self, redeem_script: &[u8]) -> Result<FeeEstimate> {
+        self.estimate_batch([redeem_script])
+    }
+
+    /// Estimates the mass and fees of an inscription batch. Reveal mass and
+    /// fees are summed up over all inscriptions.
+    pub fn estimate_batch<'a, I>(&self, redeem_scripts: I) -> Result<FeeEstimate>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut estimate = FeeEstimate {
+            protocol_fee: 0,
+            commit_mass: 0,
+            commit_fee: 0,
+            reveal_mass: 0,
+            reveal_fee: 0,
+        };
+
+        let mut outputs = vec![];
+        for redeem_script in redeem_scripts {
+            let (fee, mass) = self.reveal_fee(redeem_script)?;
+            estimate.protocol_fee += self.fee_policy.protocol_fee;
+            estimate.reveal_mass += mass;
+            estimate.reveal_fee += fee - self.fee_policy.protocol_fee;
+            outputs.push(PaymentOutput::new(
+                self.p2sh_address(redeem_script)?,
+                self.fee_policy.payback + fee,
+            ));
+        }
+
+        for transaction in self.commit(PaymentOutputs { outputs })?.iter() {
+            estimate.commit_mass += transaction.mass();
+            estimate.commit_fee += transaction.fees();
+        }
+
+        Ok(estimate)
+    }
+
+    /// Runs the generator over the builder's UTXO set paying to `outputs`.
+    /// If a fee rate is set, the final transaction is regenerated with the
+    /// priority fee required to meet it.
+    pub(crate) fn commit(&self, outputs: PaymentOutputs) -> Result<Vec<PendingTransaction>> {
+        let priority_fee = self.fee_policy.priority_fee;
+        let commit = self.generate(outputs.clone(), priority_fee)?;
+        let last = commit.last().expect("final commit transaction");
+        let fee = self.fee_policy.feerate_fee(last.mass());
+        if fee > last.fees() {
+            self.generate(outputs, priority_fee + fee - last.fees())
+        } else {
+            Ok(commit)
+        }
+    }
+
+    fn generate(
+        &self,
+        outputs: PaymentOutputs,
+        priority_fee: u64,
+    ) -> Result<Vec<PendingTransaction>> {
+        let utxo_iterator: Box<dyn Iterator<Item = UtxoEntryReference> + Send + Sync + 'static> =
+            Box::new(self.utxo_entries.clone().into_iter());
+
+        let settings = GeneratorSettings {
+            network_id: self.network_id,
+            multiplexer: None,
+            sig_op_count: 1,
+            minimum_signatures: 1,
+            change_address: self.change_address.clone(),
+            utxo_iterator,
+            priority_utxo_entries: None,
+            source_utxo_context: None,
+            destination_utxo_context: None,
+            final_transaction_priority_fee: priority_fee.into(),
+            final_transaction_destination: PaymentDestination::PaymentOutputs(outputs),
+            final_transaction_payload: None,
+        };
+
+        let generator = Generator::try_new(settings, self.signer.clone(), None)?;
+        let commit = generator
+            .iter()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        match commit.last() {
+            Some(transaction) if transaction.is_final() => Ok(commit),
+            _ => Err(Error::custom(
+                "Generator did not produce a final commit transaction",
+            )),
+        }
+    }
+
+    /// Computes the fee of a reveal transaction spending the P2SH output locked
+    /// by `redeem_script` and returns it along with the transaction mass.
+    pub fn reveal_fee(&self, redeem_script: &[u8]) -> Result<(u64, u64)> {
+        let payback = self.fee_policy.payback;
+        self.settle_fee(redeem_script, self.reveal_address(), |fee| {
+            Ok((payback + fee, payback))
+        })
+    }
+
+    /// Computes the fee of a transaction spending the P2SH output holding
+    /// `amount` and returning the remainder to the change address.
+    pub fn recover_fee(&self, redeem_script: &[u8], amount: u64) -> Result<(u64, u64)> {
+        self.settle_fee(redeem_script, &self.change_address, |fee| {
+            let payback = amount
+                .checked_sub(fee)
+                .ok_or_else(|| Error::custom(format!("Insufficient P2SH amount: {amount}")))?;
+            Ok((amount, payback))
+        })
+    }
+
+    /// The storage mass depends on the input and output values which in turn
+    /// depend on the fee, iterate until the fee settles (converges within a
+    /// couple of rounds). `amounts` maps the fee to the input and payback values.
+    fn settle_fee<F>(
+        &self,
+        redeem_script: &[u8],
+        recipient: &Address,
+        amounts: F,
+    ) -> Result<(u64, u64)>
+    where
+        F: Fn(u64) -> Result<(u64, u64)>,
+    {
+        let calc = self.mass_calculator();
+        let FeePolicy {
+            protocol_fee,
+            priority_fee,
+            ..
+        } = self.fee_policy;
+
+        let mut network_fee = 0;
+        let mut mass = 0;
+        for _ in 0..4 {
+            let (input_amount, payback) = amounts(protocol_fee + network_fee + priority_fee)?;
+            mass = reveal_mass(&calc, redeem_script, input_amount, payback, recipient)?;
+            let fee = calc
+                .calc_minimum_transaction_fee_from_mass(mass)
+                .max(self.fee_policy.feerate_fee(mass));
+            if fee == network_fee {
+                break;
+            }
+            network_fee = fee;
+        }
+
+        Ok((protocol_fee + network_fee + priority_fee, mass))
+    }
+
+    /// Creates a signed reveal transaction spending `outpoint` holding `amount`.
+    pub fn reveal(
+        &self,
+        outpoint: TransactionOutpoint,
+        amount: u64,
+        block_daa_score: u64,
+        redeem_script: &[u8],
+        secret_key: &SecretKey,
+    ) -> Result<Reveal> {
+        let fee = self.reveal_fee(redeem_script)?;
+        self.sign_reveal(
+            outpoint,
+            amount,
+            block_daa_score,
+            redeem_script,
+            secret_key,
+            self.reveal_address(),
+            fee,
+        )
+    }
+
+    /// Creates a signed transaction spending a P2SH output of an arbitrary
+    /// `amount` (e.g. a stranded commit), returning everything but the fee
+    /// to the change address.
+    pub fn recover(
+        &self,
+        outpoint: TransactionOutpoint,
+        amount: u64,
+        block_daa_score: u64,
+        redeem_script: &[u8],
+        secret_key: &SecretKey,
+    ) -> Result<Reveal> {
+        let fee = self.recover_fee(redeem_script, amount)?;
+        self.sign_reveal(
+            outpoint,
+            amount,
+            block_daa_score,
+            redeem_script,
+            secret_key,
+            &self.change_address,
+            fee,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sign_reveal(
+        &self,
+        outpoint: TransactionOutpoint,
+        amount: u64,
+        block_daa_score: u64,
+        redeem_script: &[u8],
+        secret_key: &SecretKey,
+        recipient: &Address,
+        (fee, mass): (u64, u64),
+    ) -> Result<Reveal> {
+        let payback = amount
+            .checked_sub(fee)
+            .ok_or_else(|| Error::custom(format!("Insufficient commit amount: {amount}")))?;
+
+        let mut transaction = reveal_template(outpoint, payback, recipient);
+        let entries = vec![UtxoEntry {
+            amount,
+            script_public_key: pay_to_script_hash_script(redeem_script),
+            block_daa_score,
+            is_coinbase: false,
+        }];
+
+        sign_redeem_input(&mut transaction, &entries, 0, redeem_script, secret_key)?;
+
+        Ok(Reveal {
+            transaction,
+            entries,
+            mass,
+            fee,
+        })
+    }
+}
+
+/// Returns the P2SH address locked by `redeem_script` on `network_id`.
+pub fn p2sh_address(redeem_script: &[u8], network_id: &NetworkId) -> Result<Address> {
+    script_address(&pay_to_script_hash_script(redeem_script), network_id)
+}
+
+/// Returns the address of `script_public_key` on `network_id`, failing with
+/// [`Error::NonStandardScript`] for scripts that do not map to an address.
+pub fn script_address(
+    script_public_key: &ScriptPublicKey,
+    network_id: &NetworkId,
+) -> Result<Address> {
+    Ok(extract_script_pub_key_address(
+        script_public_key,
+        (*network_id).into(),
+    )?)
+}
+
+/// Locates the outputs paying to each of the `redeem_scripts` in the final
+/// commit transaction. Identical redeem scripts are matched to distinct outputs.
+pub(crate) fn find_outpoints<'a, I>(
+    commit: &[PendingTransaction],
+    redeem_scripts: I,
+) -> Result<Vec<TransactionOutpoint>>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let transaction = commit
+        .last()
+        .ok_or_else(|| Error::custom("Missing commit transaction"))?
+        .transaction();
+    let transaction_id = transaction.id();
+
+    let script_public_keys = redeem_scripts
+        .into_iter()
+        .map(pay_to_script_hash_script)
+        .collect::<Vec<_>>();
+    output_indices(&transaction.outputs, &script_public_keys)
+        .ok_or_else(|| Error::custom("Commit transaction does not fund the P2SH outputs"))
+        .map(|indices| {
+            indices
+                .into_iter()
+                .map(|index| TransactionOutpoint::new(transaction_id, index))
+                .collect()
+        })
+}
+
+pub(crate) fn output_indices(
+    outputs: &[TransactionOutput],
+    script_public_keys: &[ScriptPublicKey],
+) -> Option<Vec<u32>> {
+    let mut claimed = vec![false; outputs.len()];
+    script_public_keys
+        .iter()
+        .map(|script_public_key| {
+            let index = (0..outputs.len()).find(|&index| {
+                !claimed[index] && &outputs[index].script_public_key == script_public_key
+            })?;
+            claimed[index] = true;
+            Some(index as u32)
+        })
+        .collect()
+}
+
+fn reveal_template(
+    outpoint: TransactionOutpoint,
+    payback: u64,
+    recipient: &Address,
+) -> Transaction {
+    Transaction::new(
+        0,
+        vec![TransactionInput {
+            previous_outpoint: outpoint,
+            signature_script: vec![],
+            sequence: 0,
+            sig_op_count: 1,
+        }],
+        vec![TransactionOutput {
+            value: payback,
+            script_public_key: pay_to_address_script(recipient),
+        }],
+        0,
+        SubnetworkId::from_byte(0),
+        0,
+        vec![],
+    )
+}
+
+/// Mass of a signed reveal transaction (compute and storage mass combined).
+fn reveal_mass(
+    calc: &MassCalculator,
+    redeem_script: &[u8],
+    input_amount: u64,
+    payback: u64,
+    recipient: &Address,
+) -> Result<u64> {
+    let mut transaction = reveal_template(
+        TransactionOutpoint::new(TransactionId::default(), 0),
+        payback,
+        recipient,
+    );
+    // placeholder signature of the same size as the real one
+    transaction.inputs[0].signature_script = kaspa_txscript::pay_to_script_hash_signature_script(
+        redeem_script.to_vec(),
+        vec![0; SIGNATURE_PUSH_SIZE],
+    )?;
+
+    let compute_mass = calc.calc_mass_for_transaction(&transaction);
+
+    let input = UtxoEntryReference {
+        utxo: Arc::new(ClientUTXO {
+            address: None,
+            outpoint: transaction.inputs[0].previous_outpoint.into(),
+            amount: input_amount,
+            script_public_key: pay_to_script_hash_script(redeem_script),
+            block_daa_score: 0,
+            is_coinbase: false,
+        }),
+    };
+    let storage_mass = calc
+        .calc_storage_mass_for_transaction(false, &[input], &transaction.outputs)
+        .ok_or_else(|| Error::custom("Reveal storage mass is incomputable"))?;
+
+    Ok(calc.combine_mass(compute_mass, storage_mass))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_indices() {
+        let a = pay_to_script_hash_script(b"a");
+        let b = pay_to_script_hash_script(b"b");
+        let outputs = [&a, &b, &a]
+            .into_iter()
+            .map(|script_public_key| TransactionOutput::new(1, script_public_key.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            output_indices(&outputs, &[a.clone(), b.clone(), a.clone()]),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(output_indices(&outputs, &[b.clone(), b]), None);
+        assert_eq!(output_indices(&outputs, &[]), Some(vec![]));
+    }
+
+    #[test]
+    fn test_fee_estimate() {
+        let policy = FeePolicy::new(SOMPI_PER_KASPA);
+        assert_eq!(policy.feerate_fee(2036), 0);
+        assert_eq!(policy.with_feerate(1.5).feerate_fee(2035), 3053);
+
+        let estimate = FeeEstimate {
+            protocol_fee: SOMPI_PER_KASPA,
+            commit_mass: 2000,
+            commit_fee: 2000,
+            reveal_mass: 3000,
+            reveal_fee: 3000,
+        };
+        assert_eq!(estimate.network_fee(), 5000);
+        assert_eq!(estimate.total(), SOMPI_PER_KASPA + 5000);
+    }
+}
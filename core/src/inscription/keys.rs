@@ -0,0 +1,108 @@
+//!
+//! Deterministic inscription keys.
+//!
+//! Reveal keys are derived from the wallet master key using a dedicated
+//! BIP32 path that does not overlap with the receive and change chains of
+//! the account:
+//!
+//! ```text
+//! m/44'/111111'/<account index>'/<INSCRIPTION_KEY_CHAIN>'/<inscription index>'
+//! ```
+//!
+//! The key locking a pending commit output can therefore always be
+//! recomputed from the wallet and the inscription index. Keys funding
+//! marketplace purchases are derived from a separate chain
+//! ([`FUNDING_KEY_CHAIN`]) so that they never collide with an inscription.
+//!
+
+use crate::result::Result;
+use kaspa_bip32::{ChildNumber, DerivationPath, ExtendedPrivateKey};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use std::str::FromStr;
+
+/// Hardened chain index reserved for inscription keys ("KSPL").
+pub const INSCRIPTION_KEY_CHAIN: u32 = 0x4b53504c;
+/// Hardened chain index reserved for purchase funding keys ("KSPF").
+pub const FUNDING_KEY_CHAIN: u32 = 0x4b535046;
+
+/// Returns the derivation path of the inscription key `index` of `account_index`.
+pub fn derivation_path(account_index: u64, index: u32) -> Result<DerivationPath> {
+    Ok(DerivationPath::from_str(&format!(
+        "m/44'/111111'/{account_index}'/{INSCRIPTION_KEY_CHAIN}'/{index}'"
+    ))?)
+}
+
+/// Inscription key chain of a wallet account.
+#[derive(Clone)]
+pub struct InscriptionKeys {
+    xprv: ExtendedPrivateKey<SecretKey>,
+    funding: ExtendedPrivateKey<SecretKey>,
+}
+
+impl InscriptionKeys {
+    /// Creates the key chain of `account_index` from the wallet master key.
+    pub fn try_new(master: &ExtendedPrivateKey<SecretKey>, account_index: u64) -> Result<Self> {
+        let chain = |chain: u32| -> Result<ExtendedPrivateKey<SecretKey>> {
+            let path =
+                DerivationPath::from_str(&format!("m/44'/111111'/{account_index}'/{chain}'"))?;
+            Ok(master.clone().derive_path(path)?)
+        };
+        Ok(Self {
+            xprv: chain(INSCRIPTION_KEY_CHAIN)?,
+            funding: chain(FUNDING_KEY_CHAIN)?,
+        })
+    }
+
+    /// Derives the keypair locking the inscription `index`.
+    pub fn keypair(&self, index: u32) -> Result<(SecretKey, PublicKey)> {
+        derive_keypair(&self.xprv, index)
+    }
+
+    /// Derives the keypair of the purchase funding key `index`.
+    pub fn funding_keypair(&self, index: u32) -> Result<(SecretKey, PublicKey)> {
+        derive_keypair(&self.funding, index)
+    }
+}
+
+fn derive_keypair(
+    xprv: &ExtendedPrivateKey<SecretKey>,
+    index: u32,
+) -> Result<(SecretKey, PublicKey)> {
+    let child = ChildNumber::new(index, true)?;
+    let secret_key = *xprv.derive_child(child)?.private_key();
+    let public_key = secret_key.public_key(&Secp256k1::new());
+    Ok((secret_key, public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn master() -> ExtendedPrivateKey<SecretKey> {
+        ExtendedPrivateKey::new([42u8; 64]).unwrap()
+    }
+
+    #[test]
+    fn test_inscription_keys() {
+        let keys = InscriptionKeys::try_new(&master(), 0).unwrap();
+        let (secret_key, public_key) = keys.keypair(7).unwrap();
+
+        // same path derived in a single step
+        let xprv = master()
+            .derive_path(derivation_path(0, 7).unwrap())
+            .unwrap();
+        assert_eq!(&secret_key, xprv.private_key());
+        assert_eq!(keys.keypair(7).unwrap().1, public_key);
+
+        assert_ne!(keys.keypair(8).unwrap().0, secret_key);
+        let other = InscriptionKeys::try_new(&master(), 1).unwrap();
+        assert_ne!(other.keypair(7).unwrap().0, secret_key);
+
+        // funding keys do not collide with inscription keys
+        assert_ne!(keys.funding_keypair(7).unwrap().0, secret_key);
+        assert_eq!(
+            keys.funding_keypair(7).unwrap(),
+            keys.funding_keypair(7).unwrap()
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//!
+//! KRC-20 marketplace operations.
+//!
+//! A seller lists a balance by inscribing a `list` operation whose reveal
+//! transaction pays to a P2SH address locked by a `send` inscription of
+//! the seller's key. The balance stays locked until that output is spent:
+//!
+//! - the seller signs the `send` input with `SIG_HASH_SINGLE |
+//!   SIG_HASH_ANY_ONE_CAN_PAY`, committing only to the payment output of
+//!   the same index, which returns the value locked in the listed output
+//!   along with the price, and hands the resulting [`Pskt`] to the buyer;
+//! - the buyer appends a funding input along with the output receiving the
+//!   tokens, signs the funding input and submits the transaction;
+//! - the seller may instead cancel the listing by spending the output back
+//!   to its own address ([`InscriptionBuilder::recover`]).
+//!
+//! ```text
+//! inputs:  [0] send P2SH (seller)   [1] funding (buyer)
+//! outputs: [0] price + listed value -> seller
+//!          [1] remainder -> buyer (token recipient)
+//! ```
+//!
+
+use super::builder::{output_indices, InscriptionBuilder, Reveal};
+use super::{redeem_script, sign_input, sign_redeem_input_with};
+use crate::constants::PROTOCOL_NAMESPACE;
+use crate::envelope::{self, Envelope};
+use crate::error::Error;
+use crate::model::kasplex::v1::krc20::{Op, TokenTransaction, TokenTransactionBuilder};
+use crate::model::kasplex::v1::{Protocol, Tick};
+use crate::result::Result;
+use kaspa_addresses::{Address, Version};
+use kaspa_consensus_client::UtxoEntry as ClientUTXO;
+use kaspa_consensus_core::hashing::sighash_type::{
+    SigHashType, SIG_HASH_ANY_ONE_CAN_PAY, SIG_HASH_SINGLE,
+};
+use kaspa_consensus_core::network::NetworkId;
+use kaspa_consensus_core::subnets::SubnetworkId;
+use kaspa_consensus_core::tx::{
+    Transaction, TransactionId, TransactionInput, TransactionOutpoint, TransactionOutput, UtxoEntry,
+};
+use kaspa_txscript::{pay_to_address_script, pay_to_script_hash_script};
+use kaspa_wallet_core::tx::{PaymentOutput, PaymentOutputs, PendingTransaction};
+use kaspa_wallet_core::utxo::UtxoEntryReference;
+use secp256k1::{PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Current [`Pskt`] format version.
+pub const PSKT_VERSION: u16 = 1;
+/// Prefix of the string representation of a [`Pskt`].
+pub const PSKT_PREFIX: &str = "pskt";
+
+/// Size of the `OpData65 <signature + sighash type>` push of the funding input.
+const SIGNATURE_PUSH_SIZE: usize = 66;
+
+/// `list` operation locking `amount` of `tick`.
+pub fn list_transaction(tick: &Tick, amount: u128) -> TokenTransaction {
+    TokenTransactionBuilder::new(Protocol::Krc20, Op::List, tick)
+        .amount(amount)
+        .build()
+}
+
+/// `send` operation releasing the balance listed for `tick`.
+pub fn send_transaction(tick: &Tick) -> TokenTransaction {
+    TokenTransactionBuilder::new(Protocol::Krc20, Op::Send, tick).build()
+}
+
+/// Redeem script inscribing the kasplex `transaction` under `public_key`.
+pub fn token_redeem_script(
+    public_key: &PublicKey,
+    transaction: &TokenTransaction,
+) -> Result<Vec<u8>> {
+    let payload = serde_json::to_vec(transaction)?;
+    let envelope = Envelope::new(PROTOCOL_NAMESPACE.as_bytes(), &[], &payload);
+    Ok(redeem_script(public_key, &envelope)?)
+}
+
+/// P2PK address of `public_key`, used by the buyer to fund a purchase
+/// with a key it can sign for outside of the wallet.
+pub fn funding_address(public_key: &PublicKey, network_id: &NetworkId) -> Address {
+    Address::new(
+        (*network_id).into(),
+        Version::PubKey,
+        &public_key.x_only_public_key().0.serialize(),
+    )
+}
+
+fn listing_hash_type() -> Result<SigHashType> {
+    SigHashType::from_u8(SIG_HASH_SINGLE.to_u8() | SIG_HASH_ANY_ONE_CAN_PAY.to_u8())
+        .map_err(Error::custom)
+}
+
+/// Partially signed `send` transaction of a listing, exchanged between the
+/// seller and the buyer as a `pskt`-prefixed hex string.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pskt {
+    pub version: u16,
+    pub network_id: NetworkId,
+    pub tick: Tick,
+    /// Listed token amount.
+    #[serde_as(as = "DisplayFromStr")]
+    pub amount: u128,
+    /// Price in sompi paid to the seller.
+    pub price: u64,
+    pub seller: Address,
+    /// Outpoint of the listed (`send` P2SH) output.
+    pub outpoint: TransactionOutpoint,
+    pub entry: UtxoEntry,
+    #[serde(with = "hex::serde")]
+    pub redeem_script: Vec<u8>,
+    /// `<signature> <redeem script>` of the seller.
+    #[serde(with = "hex::serde")]
+    pub signature_script: Vec<u8>,
+}
+
+impl Pskt {
+    /// Checks that the signed input spends the listed output and reveals a
+    /// `send` inscription of the listed token.
+    pub fn verify(&self) -> Result<()> {
+        if self.version != PSKT_VERSION {
+            return Err(Error::custom(format!(
+                "Unsupported PSKT version {}",
+                self.version
+            )));
+        }
+        if pay_to_script_hash_script(&self.redeem_script) != self.entry.script_public_key {
+            return Err(Error::custom(
+                "PSKT redeem script does not match the listed output",
+            ));
+        }
+
+        let redeem_script = envelope::decode_signature_script(&self.signature_script)?;
+        if envelope::encode_redeem_script(&redeem_script.pubkey, &redeem_script.envelope)?
+            != self.redeem_script
+        {
+            return Err(Error::custom(
+                "PSKT signature script does not match the redeem script",
+            ));
+        }
+        let transaction: TokenTransaction =
+            serde_json::from_slice(&redeem_script.envelope.payload)?;
+        if transaction.op != Op::Send || !transaction.has_tick(&self.tick) {
+            return Err(Error::custom(format!(
+                "PSKT does not send `{}` (found `{}` of `{}`)",
+                self.tick,
+                transaction.op,
+                transaction.token_id()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Value paid to the seller: the price and the value of the listed output.
+    pub fn payment(&self) -> u64 {
+        self.price + self.entry.amount
+    }
+
+    /// Assembles the purchase transaction paying `remainder` to `recipient`.
+    fn transaction(
+        &self,
+        funding: TransactionOutpoint,
+        remainder: u64,
+        recipient: &Address,
+    ) -> Transaction {
+        Transaction::new(
+            0,
+            vec![
+                TransactionInput {
+                    previous_outpoint: self.outpoint,
+                    signature_script: self.signature_script.clone(),
+                    sequence: 0,
+                    sig_op_count: 1,
+                },
+                TransactionInput {
+                    previous_outpoint: funding,
+                    signature_script: vec![],
+                    sequence: 0,
+                    sig_op_count: 1,
+                },
+            ],
+            vec![
+                TransactionOutput {
+                    value: self.payment(),
+                    script_public_key: pay_to_address_script(&self.seller),
+                },
+                TransactionOutput {
+                    value: remainder,
+                    script_public_key: pay_to_address_script(recipient),
+                },
+            ],
+            0,
+            SubnetworkId::from_byte(0),
+            0,
+            vec![],
+        )
+    }
+}
+
+impl fmt::Display for Pskt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_vec(self).map_err(|_| fmt::Error)?;
+        write!(f, "{PSKT_PREFIX}{}", hex::encode(json))
+    }
+}
+
+impl FromStr for Pskt {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let data = s
+            .trim()
+            .strip_prefix(PSKT_PREFIX)
+            .ok_or_else(|| Error::custom("Missing PSKT prefix"))?;
+        let json =
+            hex::decode(data).map_err(|err| Error::custom(format!("Invalid PSKT: {err}")))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+impl InscriptionBuilder {
+    /// Signs the `send` input spending the listed `outpoint` and pays `price`
+    /// along with the value of the listed `entry` back to the change address
+    /// of the builder (the seller).
+    pub fn sign_listing(
+        &self,
+        listing: &TokenTransaction,
+        price: u64,
+        outpoint: TransactionOutpoint,
+        entry: UtxoEntry,
+        redeem_script: &[u8],
+        secret_key: &SecretKey,
+    ) -> Result<Pskt> {
+        let amount = listing
+            .amount
+            .ok_or_else(|| Error::custom("Listing without an amount"))?;
+        let seller = self.change_address().clone();
+
+        let mut transaction = Transaction::new(
+            0,
+            vec![TransactionInput {
+                previous_outpoint: outpoint,
+                signature_script: vec![],
+                sequence: 0,
+                sig_op_count: 1,
+            }],
+            vec![TransactionOutput {
+                value: price + entry.amount,
+                script_public_key: pay_to_address_script(&seller),
+            }],
+            0,
+            SubnetworkId::from_byte(0),
+            0,
+            vec![],
+        );
+        sign_redeem_input_with(
+            &mut transaction,
+            &[entry.clone()],
+            0,
+            redeem_script,
+            secret_key,
+            listing_hash_type()?,
+        )?;
+
+        Ok(Pskt {
+            version: PSKT_VERSION,
+            network_id: self.network_id(),
+            tick: listing.tick()?,
+            amount,
+            price,
+            seller,
+            outpoint,
+            entry,
+            redeem_script: redeem_script.to_vec(),
+            signature_script: transaction.inputs[0].signature_script.clone(),
+        })
+    }
+
+    /// Computes the network fee of the purchase of `pskt` funded with an
+    /// output of `price + payback + fee`, the payback being paid to the token
+    /// recipient, and returns it along with the transaction mass.
+    pub fn buy_fee(&self, pskt: &Pskt) -> Result<(u64, u64)> {
+        let calc = self.mass_calculator();
+        let funding_script = pay_to_address_script(self.change_address());
+        let payback = self.fee_policy().payback;
+        if payback == 0 {
+            return Err(Error::custom(
+                "Purchase requires a payback amount for the token recipient",
+            ));
+        }
+
+        let mut fee = 0;
+        let mut mass = 0;
+        for _ in 0..4 {
+            let funding = pskt.price + payback + fee;
+            let mut transaction = pskt.transaction(
+                TransactionOutpoint::new(TransactionId::default(), 0),
+                payback,
+                self.change_address(),
+            );
+            // placeholder signature of the same size as the real one
+            transaction.inputs[1].signature_script = vec![0; SIGNATURE_PUSH_SIZE];
+
+            let compute_mass = calc.calc_mass_for_transaction(&transaction);
+            let inputs = [
+                (pskt.entry.amount, pskt.entry.script_public_key.clone()),
+                (funding, funding_script.clone()),
+            ]
+            .into_iter()
+            .zip(transaction.inputs.iter())
+            .map(|((amount, script_public_key), input)| UtxoEntryReference {
+                utxo: Arc::new(ClientUTXO {
+                    address: None,
+                    outpoint: input.previous_outpoint.into(),
+                    amount,
+                    script_public_key,
+                    block_daa_score: 0,
+                    is_coinbase: false,
+                }),
+            })
+            .collect::<Vec<_>>();
+            let storage_mass = calc
+                .calc_storage_mass_for_transaction(false, &inputs, &transaction.outputs)
+                .ok_or_else(|| Error::custom("Purchase storage mass is incomputable"))?;
+            mass = calc.combine_mass(compute_mass, storage_mass);
+
+            let next = calc
+                .calc_minimum_transaction_fee_from_mass(mass)
+                .max(self.fee_policy().feerate_fee(mass))
+                + self.fee_policy().priority_fee;
+            if next == fee {
+                break;
+            }
+            fee = next;
+        }
+
+        Ok((fee, mass))
+    }
+
+    /// Creates the transactions funding `address` with `amount`, returning
+    /// them along with the funded outpoint.
+    pub fn fund(
+        &self,
+        address: &Address,
+        amount: u64,
+    ) -> Result<(Vec<PendingTransaction>, TransactionOutpoint)> {
+        let commit = self.commit(PaymentOutputs {
+            outputs: vec![PaymentOutput::new(address.clone(), amount)],
+        })?;
+        let transaction = commit
+            .last()
+            .ok_or_else(|| Error::custom("Missing funding transaction"))?
+            .transaction();
+        let index = output_indices(&transaction.outputs, &[pay_to_address_script(address)])
+            .and_then(|indices| indices.first().copied())
+            .ok_or_else(|| Error::custom("Funding transaction does not pay the funding address"))?;
+        let outpoint = TransactionOutpoint::new(transaction.id(), index);
+        Ok((commit, outpoint))
+    }
+
+    /// Completes the purchase of `pskt` with the P2PK `funding` output of
+    /// `secret_key` holding `amount`. The listed tokens and the funding in
+    /// excess of the price and fee are paid to the change address; the
+    /// value of the listed output goes back to the seller.
+    pub fn buy(
+        &self,
+        pskt: &Pskt,
+        funding: TransactionOutpoint,
+        amount: u64,
+        secret_key: &SecretKey,
+    ) -> Result<Reveal> {
+        if pskt.network_id != self.network_id() {
+            return Err(Error::NetworkId(pskt.network_id.to_string()));
+        }
+        pskt.verify()?;
+
+        let (fee, mass) = self.buy_fee(pskt)?;
+        let remainder = amount
+            .checked_sub(pskt.price + fee)
+            .ok_or_else(|| Error::custom(format!("Insufficient funding amount: {amount}")))?;
+
+        let public_key = secret_key.public_key(secp256k1::SECP256K1);
+        let entries = vec![
+            pskt.entry.clone(),
+            UtxoEntry {
+                amount,
+                script_public_key: pay_to_address_script(&funding_address(
+                    &public_key,
+                    &self.network_id(),
+                )),
+                block_daa_score: 0,
+                is_coinbase: false,
+            },
+        ];
+
+        let mut transaction = pskt.transaction(funding, remainder, self.change_address());
+        sign_input(&mut transaction, &entries, 1, secret_key)?;
+
+        Ok(Reveal {
+            transaction,
+            entries,
+            mass,
+            fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inscription::{demo_keypair, FeePolicy};
+    use kaspa_consensus_core::constants::SOMPI_PER_KASPA;
+    use kaspa_consensus_core::hashing::sighash::SigHashReusedValues;
+    use kaspa_consensus_core::tx::{MutableTransaction, VerifiableTransaction};
+    use kaspa_txscript::caches::Cache;
+    use kaspa_txscript::{SigCacheKey, TxScriptEngine};
+
+    #[test]
+    fn test_listing_purchase() {
+        let network_id = NetworkId::from_str("testnet-11").unwrap();
+        let (seller_key, seller_public_key) = demo_keypair();
+        let (buyer_key, buyer_public_key) = demo_keypair();
+        let seller = funding_address(&seller_public_key, &network_id);
+        let buyer = funding_address(&buyer_public_key, &network_id);
+
+        let tick = Tick::from_str("SPARKL").unwrap();
+        let listing = list_transaction(&tick, 1000);
+        let send_script =
+            token_redeem_script(&seller_public_key, &send_transaction(&tick)).unwrap();
+        let outpoint = TransactionOutpoint::new(
+            TransactionId::from_str(
+                "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+            )
+            .unwrap(),
+            0,
+        );
+        let entry = UtxoEntry {
+            amount: SOMPI_PER_KASPA,
+            script_public_key: pay_to_script_hash_script(&send_script),
+            block_daa_score: 0,
+            is_coinbase: false,
+        };
+
+        let pskt = InscriptionBuilder::new(network_id, seller.clone())
+            .sign_listing(
+                &listing,
+                50 * SOMPI_PER_KASPA,
+                outpoint,
+                entry,
+                &send_script,
+                &seller_key,
+            )
+            .unwrap();
+        let pskt = Pskt::from_str(&pskt.to_string()).unwrap();
+        pskt.verify().unwrap();
+        assert_eq!(pskt.amount, 1000);
+
+        let builder = InscriptionBuilder::new(network_id, buyer)
+            .with_fee_policy(FeePolicy::default().with_payback(2 * SOMPI_PER_KASPA));
+        let (fee, _) = builder.buy_fee(&pskt).unwrap();
+        let funding = TransactionOutpoint::new(outpoint.transaction_id, 1);
+        let purchase = builder
+            .buy(
+                &pskt,
+                funding,
+                pskt.price + 2 * SOMPI_PER_KASPA + fee,
+                &buyer_key,
+            )
+            .unwrap();
+        // the seller gets the price and the locked value back
+        assert_eq!(
+            purchase.transaction.outputs[0].value,
+            pskt.price + SOMPI_PER_KASPA
+        );
+        assert_eq!(purchase.transaction.outputs[1].value, 2 * SOMPI_PER_KASPA);
+        let inputs = purchase
+            .entries
+            .iter()
+            .map(|entry| entry.amount)
+            .sum::<u64>();
+        let outputs = purchase
+            .transaction
+            .outputs
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        assert_eq!(inputs - outputs, fee);
+
+        let tx = MutableTransaction::with_entries(purchase.transaction, purchase.entries);
+        let tx = tx.as_verifiable();
+        let cache: Cache<SigCacheKey, bool> = Cache::new(10_000);
+        let mut reused_values = SigHashReusedValues::new();
+        for (idx, (input, entry)) in tx.populated_inputs().enumerate() {
+            TxScriptEngine::from_transaction_input(
+                &tx,
+                input,
+                idx,
+                entry,
+                &mut reused_values,
+                &cache,
+            )
+            .unwrap()
+            .execute()
+            .unwrap();
+        }
+
+        // a listing of another token is rejected
+        let mut other = pskt.clone();
+        other.tick = Tick::from_str("OTHER").unwrap();
+        assert!(other.verify().is_err());
+    }
+}
@@ -0,0 +1,648 @@
+use crate::constants::*;
+use crate::envelope::{self, Envelope};
+use crate::model::kasplex::v1::krc20::{Op, TokenTransaction};
+use crate::model::kasplex::v1::Protocol;
+use crate::model::sparkle::SparkleTransaction;
+
+use kaspa_addresses::Address;
+use kaspa_consensus_client::UtxoEntry as ClientUTXO;
+use kaspa_consensus_core::hashing::sighash::{calc_schnorr_signature_hash, SigHashReusedValues};
+use kaspa_consensus_core::hashing::sighash_type::{SigHashType, SIG_HASH_ALL};
+use kaspa_consensus_core::sign::sign;
+use kaspa_consensus_core::subnets::SubnetworkId;
+use kaspa_consensus_core::tx::{
+    MutableTransaction, Transaction, TransactionInput, TransactionOutpoint, TransactionOutput,
+    UtxoEntry,
+};
+use kaspa_hashes::Hash;
+use kaspa_txscript::{
+    pay_to_address_script, pay_to_script_hash_script, pay_to_script_hash_signature_script,
+};
+use kaspa_wallet_core::tx::{
+    Generator, GeneratorSettings, PaymentDestination, PaymentOutputs, PendingTransaction,
+};
+use kaspa_wallet_core::utxo::UtxoEntryReference;
+use kaspa_wrpc_client::prelude::*;
+use secp256k1::{rand, Message, Secp256k1, SecretKey};
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub mod builder;
+pub use builder::{
+    BatchReveal, FeeEstimate, FeePolicy, Inscription, InscriptionBatch, InscriptionBuilder, Reveal,
+};
+pub mod keys;
+pub use keys::InscriptionKeys;
+pub mod market;
+pub use market::Pskt;
+
+#[derive(Debug, Clone)]
+pub struct TransactionDetails {
+    pub script_sig: Vec<u8>,
+    pub recipient: Address,
+    pub secret_key: SecretKey,
+    pub prev_tx_tid: Hash,
+    pub prev_tx_score: u64,
+}
+
+pub fn demo_keypair() -> (secp256k1::SecretKey, secp256k1::PublicKey) {
+    let secp = Secp256k1::new();
+    let (secret_key, public_key) = secp.generate_keypair(&mut rand::thread_rng());
+    (secret_key, public_key)
+}
+
+fn redeem_pubkey(redeem_script: &[u8], pubkey: &[u8]) -> envelope::Result<Vec<u8>> {
+    // TODO - force OpPushData1 for metadata to be kasplex compliant?
+    let envelope = Envelope::new(PROTOCOL_NAMESPACE.as_bytes(), &[], redeem_script);
+    envelope::encode_redeem_script(pubkey, &envelope)
+}
+
+/// Creates the redeem script inscribing `envelope` (of any content type)
+/// under `public_key`. The redeem script is limited to the 520 byte script
+/// element size (see [`envelope::encode_redeem_script`]).
+pub fn redeem_script(
+    public_key: &secp256k1::PublicKey,
+    envelope: &Envelope,
+) -> envelope::Result<Vec<u8>> {
+    envelope::encode_redeem_script(&public_key.x_only_public_key().0.serialize(), envelope)
+}
+
+/// Creates the redeem script inscribing the borsh-encoded sparkle
+/// `transaction` under `public_key`.
+pub fn sparkle_redeem_script(
+    public_key: &secp256k1::PublicKey,
+    transaction: &SparkleTransaction,
+) -> crate::result::Result<Vec<u8>> {
+    Ok(redeem_script(public_key, &transaction.to_envelope()?)?)
+}
+
+/// Signs input `index` of `transaction` spending a P2SH output locked by
+/// `redeem_script`, setting its signature script to `<signature> <redeem script>`.
+pub fn sign_redeem_input(
+    transaction: &mut Transaction,
+    entries: &[UtxoEntry],
+    index: usize,
+    redeem_script: &[u8],
+    secret_key: &SecretKey,
+) -> crate::result::Result<()> {
+    sign_redeem_input_with(
+        transaction,
+        entries,
+        index,
+        redeem_script,
+        secret_key,
+        SIG_HASH_ALL,
+    )
+}
+
+/// Same as [`sign_redeem_input`] under an arbitrary signature hash type
+/// (e.g. `SIG_HASH_SINGLE | SIG_HASH_ANY_ONE_CAN_PAY` for partially signed
+/// transactions).
+pub fn sign_redeem_input_with(
+    transaction: &mut Transaction,
+    entries: &[UtxoEntry],
+    index: usize,
+    redeem_script: &[u8],
+    secret_key: &SecretKey,
+    hash_type: SigHashType,
+) -> crate::result::Result<()> {
+    transaction.inputs[index].sig_op_count = 1;
+    let signature = schnorr_signature(transaction, entries, index, secret_key, hash_type)?;
+    transaction.inputs[index].signature_script =
+        pay_to_script_hash_signature_script(redeem_script.to_vec(), signature)?;
+
+    Ok(())
+}
+
+/// Signs input `index` of `transaction` spending a P2PK output of `secret_key`.
+pub fn sign_input(
+    transaction: &mut Transaction,
+    entries: &[UtxoEntry],
+    index: usize,
+    secret_key: &SecretKey,
+) -> crate::result::Result<()> {
+    transaction.inputs[index].sig_op_count = 1;
+    transaction.inputs[index].signature_script =
+        schnorr_signature(transaction, entries, index, secret_key, SIG_HASH_ALL)?;
+    Ok(())
+}
+
+/// Returns the `OpData65 <signature + sighash type>` push signing input `index`.
+fn schnorr_signature(
+    transaction: &Transaction,
+    entries: &[UtxoEntry],
+    index: usize,
+    secret_key: &SecretKey,
+    hash_type: SigHashType,
+) -> crate::result::Result<Vec<u8>> {
+    let sig_hash = {
+        let signable = MutableTransaction::with_entries(transaction, entries.to_vec());
+        let mut reused_values = SigHashReusedValues::new();
+        calc_schnorr_signature_hash(
+            &signable.as_verifiable(),
+            index,
+            hash_type,
+            &mut reused_values,
+        )
+    };
+
+    let keypair = secp256k1::Keypair::from_secret_key(secp256k1::SECP256K1, secret_key);
+    let message = Message::from_digest_slice(sig_hash.as_bytes().as_slice())
+        .map_err(|err| crate::error::Error::custom(format!("Invalid signature hash: {err}")))?;
+    let signature: [u8; 64] = *keypair.sign_schnorr(message).as_ref();
+
+    Ok(std::iter::once(65u8)
+        .chain(signature)
+        .chain([hash_type.to_u8()])
+        .collect())
+}
+
+/// Demo `deploy` operation of the `TOITOI` token.
+pub fn deploy_transaction_demo() -> TokenTransaction {
+    TokenTransaction {
+        protocol: Protocol::from_str("krc-20").unwrap(),
+        op: Op::Deploy,
+        tick: "TOITOI".to_string(),
+        mode: None,
+        name: None,
+        ca: None,
+        max: Some(100000000000000000),
+        limit: Some(100000000000),
+        pre: Some(100000000000),
+        dec: Some(8),
+        amount: None,
+        from: None,
+        to: None,
+        op_score: None,
+        hash_rev: None,
+        fee_rev: None,
+        tx_accept: None,
+        op_accept: None,
+        op_error: None,
+        mts_add: None,
+        mts_mod: None,
+    }
+}
+
+pub fn deploy_token_demo(
+    pubkey: &secp256k1::PublicKey,
+    network_id: &NetworkId,
+) -> crate::result::Result<(Address, Vec<u8>)> {
+    let transaction = deploy_transaction_demo();
+
+    let json = serde_json::to_string(&transaction)?;
+    println!("{json}");
+    let script_sig: Vec<u8> = redeem_pubkey(json.as_bytes(), &pubkey.serialize()[1..33])?;
+
+    if crate::debug::debug() {
+        println!("{}", crate::script::inspect(&script_sig));
+    }
+
+    let p2sh = builder::p2sh_address(&script_sig, network_id)?;
+    Ok((p2sh, script_sig))
+}
+
+/// Demo `mint` operation of the `TOITOI` token.
+pub fn mint_transaction_demo() -> TokenTransaction {
+    TokenTransaction {
+        protocol: Protocol::from_str("krc-20").unwrap(),
+        op: Op::Mint,
+        tick: "TOITOI".to_string(),
+        mode: None,
+        name: None,
+        ca: None,
+        max: None,
+        limit: None,
+        pre: None,
+        dec: None,
+        amount: None,
+        from: None,
+        to: None,
+        op_score: None,
+        hash_rev: None,
+        fee_rev: None,
+        tx_accept: None,
+        op_accept: None,
+        op_error: None,
+        mts_add: None,
+        mts_mod: None,
+    }
+}
+
+pub fn mint_token_demo(
+    pubkey: &secp256k1::PublicKey,
+    network_id: &NetworkId,
+) -> crate::result::Result<(Address, Vec<u8>)> {
+    let transaction = mint_transaction_demo();
+
+    let json = serde_json::to_string(&transaction)?;
+    println!("{json}");
+    let script_sig: Vec<u8> = redeem_pubkey(json.as_bytes(), &pubkey.serialize()[1..33])?;
+
+    if crate::debug::debug() {
+        println!("{}", crate::script::inspect(&script_sig));
+    }
+
+    let p2sh = builder::p2sh_address(&script_sig, network_id)?;
+    Ok((p2sh, script_sig))
+}
+
+pub fn reveal_transaction(
+    TransactionDetails {
+        script_sig,
+        recipient,
+        secret_key,
+        prev_tx_tid,
+        prev_tx_score,
+    }: TransactionDetails,
+    payback_amount: u64,
+    reveal_fee: u64,
+    network_id: NetworkId,
+) -> (PendingTransaction, Vec<UtxoEntry>, Transaction) {
+    let entry_total_amount = payback_amount + reveal_fee;
+    let redeem_lock_p2sh = pay_to_script_hash_script(&script_sig);
+
+    let mut unsigned_tx = Transaction::new(
+        0,
+        vec![TransactionInput {
+            previous_outpoint: TransactionOutpoint {
+                transaction_id: prev_tx_tid,
+                index: 0,
+            },
+            signature_script: vec![],
+            sequence: 0,
+            sig_op_count: 1, // when signed it turns into 1
+        }],
+        vec![TransactionOutput {
+            value: payback_amount,
+            script_public_key: pay_to_address_script(&recipient),
+        }],
+        0,
+        SubnetworkId::from_byte(0),
+        0,
+        vec![],
+    );
+
+    let entries = vec![UtxoEntry {
+        amount: entry_total_amount,
+        script_public_key: redeem_lock_p2sh.clone(),
+        block_daa_score: prev_tx_score,
+        is_coinbase: false,
+    }];
+
+    // Signing the transaction with keypair.
+    let tx_clone = unsigned_tx.clone();
+    let entries_clone = entries.clone();
+    let schnorr_key =
+        secp256k1::Keypair::from_seckey_slice(secp256k1::SECP256K1, &secret_key.secret_bytes())
+            .unwrap();
+    let mut signed_tx = sign(
+        MutableTransaction::with_entries(tx_clone, entries_clone),
+        schnorr_key,
+    );
+    let signature = signed_tx.tx.inputs[0].signature_script.clone();
+
+    // Prepend the signature to the unlock script.
+    let script_sig = pay_to_script_hash_signature_script(script_sig.clone(), signature).unwrap();
+    unsigned_tx.inputs[0]
+        .signature_script
+        .clone_from(&script_sig);
+    signed_tx.tx.inputs[0].signature_script = script_sig;
+
+    let utxo_entry = ClientUTXO {
+        address: None,
+        outpoint: TransactionOutpoint {
+            transaction_id: prev_tx_tid,
+            index: 0,
+        }
+        .into(),
+        amount: entry_total_amount,
+        script_public_key: redeem_lock_p2sh.clone(),
+        block_daa_score: prev_tx_score,
+        is_coinbase: false,
+    };
+
+    // Transaction generator
+    let utxo_entries: Vec<UtxoEntryReference> = vec![];
+    let priority_utxo_entries = None;
+    let multiplexer = None;
+    let sig_op_count = 1;
+    let minimum_signatures = 1;
+    let utxo_iterator: Box<dyn Iterator<Item = UtxoEntryReference> + Send + Sync + 'static> =
+        Box::new(utxo_entries.into_iter());
+    let source_utxo_context = None;
+    let destination_utxo_context = None;
+    let final_priority_fee = reveal_fee.into();
+    let final_transaction_payload = None;
+    let change_address: Address = recipient.clone();
+
+    let final_transaction_destination = PaymentDestination::PaymentOutputs(PaymentOutputs::from((
+        recipient.clone(),
+        payback_amount,
+    )));
+
+    let settings = GeneratorSettings {
+        network_id,
+        multiplexer,
+        sig_op_count,
+        minimum_signatures,
+        change_address,
+        utxo_iterator,
+        priority_utxo_entries,
+        source_utxo_context,
+        destination_utxo_context,
+        final_transaction_priority_fee: final_priority_fee,
+        final_transaction_destination,
+        final_transaction_payload,
+    };
+    let generator = Generator::try_new(settings, None, None).unwrap();
+
+    let utxo_entry_ref_from_ref: Vec<UtxoEntryReference> = vec![UtxoEntryReference {
+        utxo: Arc::new(utxo_entry.to_owned()),
+    }];
+
+    (
+        PendingTransaction::try_new(
+            &generator,
+            signed_tx.tx,
+            utxo_entry_ref_from_ref,
+            vec![recipient].into_iter().collect(),
+            Some(payback_amount),
+            0,
+            0,
+            0,
+            reveal_fee,
+            reveal_fee,
+            kaspa_wallet_core::tx::DataKind::Final,
+        )
+        .unwrap(),
+        entries,
+        unsigned_tx,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::kasplex::v1::Tick;
+    use kaspa_addresses::{Address, Prefix};
+    use kaspa_consensus_core::constants::SOMPI_PER_KASPA;
+    use kaspa_consensus_core::hashing::sighash::SigHashReusedValues;
+    use kaspa_consensus_core::tx::TransactionId;
+    use kaspa_consensus_core::tx::VerifiableTransaction;
+    use kaspa_txscript::caches::Cache;
+    use kaspa_txscript::SigCacheKey;
+    use kaspa_txscript::TxScriptEngine;
+    use kaspa_txscript_errors::TxScriptError;
+
+    #[test]
+    pub fn test_and_verify_sign() {
+        let (secret_key, public_key) = demo_keypair();
+        // let pubkey = ScriptVec::from_slice(&public_key.serialize());
+        let test_address = Address::new(
+            Prefix::Testnet,
+            kaspa_addresses::Version::PubKey,
+            &public_key.x_only_public_key().0.serialize(),
+        );
+
+        let (_, script_sig) =
+            deploy_token_demo(&public_key, &NetworkId::from_str("testnet-11").unwrap()).unwrap();
+        let priority_fee_sompi = SOMPI_PER_KASPA;
+
+        let prev_tx_id = TransactionId::from_str(
+            "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+        )
+        .unwrap();
+
+        let test_daa_score = 30310;
+        let (_, entries, unsigned_tx) = reveal_transaction(
+            TransactionDetails {
+                script_sig,
+                recipient: test_address,
+                secret_key,
+                prev_tx_tid: prev_tx_id,
+                prev_tx_score: test_daa_score,
+            },
+            priority_fee_sompi,
+            priority_fee_sompi,
+            NetworkId::from_str("testnet-11").unwrap(),
+        );
+
+        println!(
+            "{}",
+            crate::script::inspect(&unsigned_tx.inputs[0].signature_script)
+        );
+
+        let tx = MutableTransaction::with_entries(unsigned_tx, entries);
+
+        let tx = tx.as_verifiable();
+        let cache: Cache<SigCacheKey, bool> = Cache::new(10_000);
+        let mut reused_values = SigHashReusedValues::new();
+
+        let script_run: Result<(), TxScriptError> =
+            tx.populated_inputs()
+                .enumerate()
+                .try_for_each(|(idx, (input, entry))| {
+                    TxScriptEngine::from_transaction_input(
+                        &tx,
+                        input,
+                        idx,
+                        entry,
+                        &mut reused_values,
+                        &cache,
+                    )?
+                    .execute()
+                });
+
+        eprintln!("{:?}", script_run.clone().err());
+        assert!(script_run.is_ok());
+    }
+
+    #[test]
+    pub fn test_envelope_round_trip() {
+        let (secret_key, public_key) = demo_keypair();
+        let test_address = Address::new(
+            Prefix::Testnet,
+            kaspa_addresses::Version::PubKey,
+            &public_key.x_only_public_key().0.serialize(),
+        );
+
+        let (_, script_sig) =
+            deploy_token_demo(&public_key, &NetworkId::from_str("testnet-11").unwrap()).unwrap();
+
+        let redeem_script = envelope::decode_redeem_script(&script_sig).unwrap();
+        assert_eq!(redeem_script.pubkey, public_key.serialize()[1..33]);
+        assert!(redeem_script.envelope.has_namespace(PROTOCOL_NAMESPACE));
+        let transaction: TokenTransaction =
+            serde_json::from_slice(&redeem_script.envelope.payload).unwrap();
+        assert_eq!(transaction.op, Op::Deploy);
+        assert!(transaction.has_tick(&Tick::from_str("toitoi").unwrap()));
+
+        let prev_tx_id = TransactionId::from_str(
+            "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+        )
+        .unwrap();
+
+        let (_, _, unsigned_tx) = reveal_transaction(
+            TransactionDetails {
+                script_sig: script_sig.clone(),
+                recipient: test_address,
+                secret_key,
+                prev_tx_tid: prev_tx_id,
+                prev_tx_score: 30310,
+            },
+            SOMPI_PER_KASPA,
+            SOMPI_PER_KASPA,
+            NetworkId::from_str("testnet-11").unwrap(),
+        );
+
+        let revealed =
+            envelope::decode_signature_script(&unsigned_tx.inputs[0].signature_script).unwrap();
+        assert_eq!(revealed, redeem_script);
+        assert_eq!(
+            envelope::encode_redeem_script(&revealed.pubkey, &revealed.envelope).unwrap(),
+            script_sig
+        );
+    }
+
+    #[test]
+    pub fn test_sparkle_redeem_script() {
+        let (_, public_key) = demo_keypair();
+        let transaction = SparkleTransaction::mint(&Tick::from_str("SPARKL").unwrap(), 1_000);
+
+        let script_sig = sparkle_redeem_script(&public_key, &transaction).unwrap();
+        let redeem_script = envelope::decode_redeem_script(&script_sig).unwrap();
+        assert_eq!(redeem_script.pubkey, public_key.serialize()[1..33]);
+        assert_eq!(
+            SparkleTransaction::try_from_envelope(&redeem_script.envelope).unwrap(),
+            transaction
+        );
+    }
+
+    #[test]
+    pub fn test_builder_reveal() {
+        let (secret_key, public_key) = demo_keypair();
+        let change_address = Address::new(
+            Prefix::Testnet,
+            kaspa_addresses::Version::PubKey,
+            &public_key.x_only_public_key().0.serialize(),
+        );
+        let (_, script_sig) =
+            mint_token_demo(&public_key, &NetworkId::from_str("testnet-11").unwrap()).unwrap();
+
+        let builder = InscriptionBuilder::new(
+            NetworkId::from_str("testnet-11").unwrap(),
+            change_address.clone(),
+        )
+        .with_fee_policy(FeePolicy::new(FEE_MINT).with_payback(10 * SOMPI_PER_KASPA));
+
+        let (fee, mass) = builder.reveal_fee(&script_sig).unwrap();
+        assert!(fee > FEE_MINT);
+        assert!(mass > 0);
+
+        let outpoint = TransactionOutpoint::new(
+            TransactionId::from_str(
+                "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+            )
+            .unwrap(),
+            3,
+        );
+        let amount = 10 * SOMPI_PER_KASPA + fee;
+        let reveal = builder
+            .reveal(outpoint, amount, 30310, &script_sig, &secret_key)
+            .unwrap();
+
+        assert_eq!(reveal.transaction.inputs[0].previous_outpoint.index, 3);
+        assert_eq!(reveal.transaction.outputs[0].value, 10 * SOMPI_PER_KASPA);
+        assert_eq!(
+            reveal.transaction.outputs[0].script_public_key,
+            pay_to_address_script(&change_address)
+        );
+
+        let tx = MutableTransaction::with_entries(reveal.transaction, reveal.entries);
+        let tx = tx.as_verifiable();
+        let cache: Cache<SigCacheKey, bool> = Cache::new(10_000);
+        let mut reused_values = SigHashReusedValues::new();
+
+        let script_run: Result<(), TxScriptError> =
+            tx.populated_inputs()
+                .enumerate()
+                .try_for_each(|(idx, (input, entry))| {
+                    TxScriptEngine::from_transaction_input(
+                        &tx,
+                        input,
+                        idx,
+                        entry,
+                        &mut reused_values,
+                        &cache,
+                    )?
+                    .execute()
+                });
+
+        assert!(script_run.is_ok());
+    }
+
+    #[test]
+    pub fn test_builder_reveal_size_limit() {
+        let (secret_key, public_key) = demo_keypair();
+        let network_id = NetworkId::from_str("testnet-11").unwrap();
+        let change_address = Address::new(
+            Prefix::Testnet,
+            kaspa_addresses::Version::PubKey,
+            &public_key.x_only_public_key().0.serialize(),
+        );
+        let envelope = |len: usize| Envelope::new(b"sparkle", &[], &vec![0x2a; len]);
+
+        // the largest payload, one more byte exceeds the 520 byte limit
+        let script_sig = redeem_script(&public_key, &envelope(471)).unwrap();
+        assert_eq!(script_sig.len(), kaspa_txscript::MAX_SCRIPT_ELEMENT_SIZE);
+        assert!(redeem_script(&public_key, &envelope(472)).is_err());
+
+        let builder = InscriptionBuilder::new(network_id, change_address)
+            .with_fee_policy(FeePolicy::default().with_payback(SOMPI_PER_KASPA));
+        let (fee, _) = builder.reveal_fee(&script_sig).unwrap();
+        let outpoint = TransactionOutpoint::new(
+            TransactionId::from_str(
+                "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+            )
+            .unwrap(),
+            0,
+        );
+        let reveal = builder
+            .reveal(
+                outpoint,
+                SOMPI_PER_KASPA + fee,
+                30310,
+                &script_sig,
+                &secret_key,
+            )
+            .unwrap();
+
+        let revealed =
+            envelope::decode_signature_script(&reveal.transaction.inputs[0].signature_script)
+                .unwrap();
+        assert_eq!(revealed.envelope, envelope(471));
+
+        let tx = MutableTransaction::with_entries(reveal.transaction, reveal.entries);
+        let tx = tx.as_verifiable();
+        let cache: Cache<SigCacheKey, bool> = Cache::new(10_000);
+        let mut reused_values = SigHashReusedValues::new();
+
+        let script_run: Result<(), TxScriptError> =
+            tx.populated_inputs()
+                .enumerate()
+                .try_for_each(|(idx, (input, entry))| {
+                    TxScriptEngine::from_transaction_input(
+                        &tx,
+                        input,
+                        idx,
+                        entry,
+                        &mut reused_values,
+                        &cache,
+                    )?
+                    .execute()
+                });
+
+        assert!(script_run.is_ok());
+    }
+}
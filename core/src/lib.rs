@@ -1,5 +1,6 @@
 pub mod constants;
 pub mod debug;
+pub mod envelope;
 pub mod error;
 pub mod hash;
 pub mod id;
@@ -8,6 +9,7 @@ pub mod inscription;
 pub mod model;
 pub mod prelude;
 pub mod result;
+pub mod script;
 pub mod url;
 pub mod version;
 
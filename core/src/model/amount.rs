@@ -0,0 +1,281 @@
+//!
+//! Fixed-point token amounts.
+//!
+//! Token quantities are carried on-chain and by the kasplex indexer as raw
+//! integer strings in the smallest unit of the token; `dec` gives the number
+//! of decimal places of the human-readable amount (`1,000.5` with `dec = 8`
+//! is `100050000000`).
+//!
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+use thiserror::Error;
+
+/// Decimals of a KRC-20 token deployed without an explicit `dec`.
+pub const DEFAULT_DECIMALS: u8 = 8;
+/// Maximum number of token decimals, shared by the KRC-20 validator and
+/// sparkle tokens.
+pub const MAX_DECIMALS: u8 = 18;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    #[error("Empty amount")]
+    Empty,
+
+    #[error("Invalid amount `{0}`")]
+    Invalid(String),
+
+    #[error("Amount `{amount}` exceeds {dec} decimal places")]
+    Precision { amount: String, dec: u8 },
+
+    #[error("Invalid decimals {0}, expecting at most {MAX_DECIMALS}")]
+    Decimals(u8),
+
+    #[error("Amount overflow")]
+    Overflow,
+}
+
+pub type AmountResult<T> = std::result::Result<T, AmountError>;
+
+/// Token amount of `raw` smallest units with `dec` decimal places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TokenAmount {
+    pub raw: u128,
+    pub dec: u8,
+}
+
+impl TokenAmount {
+    pub fn new(raw: u128, dec: u8) -> Self {
+        Self { raw, dec }
+    }
+
+    pub fn zero(dec: u8) -> Self {
+        Self { raw: 0, dec }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.raw == 0
+    }
+
+    /// Parses a raw integer string in smallest units, as used on-chain and
+    /// by kasplex. The encoding does not carry the decimals, the caller
+    /// must supply them from the token record.
+    pub fn from_raw_str(raw: &str, dec: u8) -> AmountResult<Self> {
+        unit(dec)?;
+        if raw.is_empty() {
+            return Err(AmountError::Empty);
+        }
+        if !raw.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(AmountError::Invalid(raw.to_string()));
+        }
+        let raw = raw.parse::<u128>().map_err(|_| AmountError::Overflow)?;
+        Ok(Self { raw, dec })
+    }
+
+    /// Reinterprets the raw amount with `dec` decimal places.
+    pub fn with_dec(self, dec: u8) -> Self {
+        Self { dec, ..self }
+    }
+
+    /// Parses a human-readable amount such as `1,000.5`. Thousands separators
+    /// (`,`, `_` or spaces) are ignored; the fractional part may not exceed
+    /// `dec` digits.
+    pub fn parse(amount: &str, dec: u8) -> AmountResult<Self> {
+        let unit = unit(dec)?;
+        let digits = amount
+            .trim()
+            .chars()
+            .filter(|c| !matches!(c, ',' | '_' | ' '))
+            .collect::<String>();
+        if digits.is_empty() {
+            return Err(AmountError::Empty);
+        }
+
+        let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+        let invalid = || AmountError::Invalid(amount.to_string());
+        if (integer.is_empty() && fraction.is_empty())
+            || !integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > dec as usize {
+            return Err(AmountError::Precision {
+                amount: amount.to_string(),
+                dec,
+            });
+        }
+
+        let integer = if integer.is_empty() {
+            0
+        } else {
+            integer.parse::<u128>().map_err(|_| AmountError::Overflow)?
+        };
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            let scale = unit / 10u128.pow(fraction.len() as u32);
+            fraction.parse::<u128>().map_err(|_| invalid())? * scale
+        };
+
+        let raw = integer
+            .checked_mul(unit)
+            .and_then(|raw| raw.checked_add(fraction))
+            .ok_or(AmountError::Overflow)?;
+        Ok(Self { raw, dec })
+    }
+
+    /// Returns `None` on overflow or if the decimals differ.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        (self.dec == other.dec).then_some(())?;
+        Some(Self::new(self.raw.checked_add(other.raw)?, self.dec))
+    }
+
+    /// Returns `None` on underflow or if the decimals differ.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        (self.dec == other.dec).then_some(())?;
+        Some(Self::new(self.raw.checked_sub(other.raw)?, self.dec))
+    }
+
+    pub fn checked_mul(self, factor: u128) -> Option<Self> {
+        Some(Self::new(self.raw.checked_mul(factor)?, self.dec))
+    }
+
+    pub fn checked_div(self, divisor: u128) -> Option<Self> {
+        Some(Self::new(self.raw.checked_div(divisor)?, self.dec))
+    }
+
+    /// Formats the amount followed by `tick` (e.g. `1,000.5 SPARKL`).
+    pub fn format_with_suffix<S: fmt::Display>(&self, tick: S) -> String {
+        format!("{self} {}", tick.to_string().to_uppercase())
+    }
+}
+
+/// Formats the amount with thousands separators and without trailing
+/// fractional zeros (e.g. `1,000.5`).
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (integer, fraction) = match unit(self.dec) {
+            Ok(unit) => (self.raw / unit, self.raw % unit),
+            Err(_) => return write!(f, "{}", self.raw),
+        };
+
+        let integer = integer.to_string();
+        let len = integer.len();
+        for (i, c) in integer.chars().enumerate() {
+            if i > 0 && (len - i) % 3 == 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{c}")?;
+        }
+
+        if fraction > 0 {
+            let fraction = format!("{fraction:0width$}", width = self.dec as usize);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized as the raw integer string used by kasplex.
+impl Serialize for TokenAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw.to_string())
+    }
+}
+
+fn unit(dec: u8) -> AmountResult<u128> {
+    if dec > MAX_DECIMALS {
+        Err(AmountError::Decimals(dec))
+    } else {
+        Ok(10u128.pow(dec as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            TokenAmount::parse("1,000.5", 8),
+            Ok(TokenAmount::new(100_050_000_000, 8))
+        );
+        assert_eq!(TokenAmount::parse("42", 0), Ok(TokenAmount::new(42, 0)));
+        assert_eq!(TokenAmount::parse(".25", 2), Ok(TokenAmount::new(25, 2)));
+        assert_eq!(TokenAmount::parse("7.", 2), Ok(TokenAmount::new(700, 2)));
+        assert_eq!(TokenAmount::parse(" ", 8), Err(AmountError::Empty));
+        assert_eq!(
+            TokenAmount::parse("1.5.1", 8),
+            Err(AmountError::Invalid("1.5.1".to_string()))
+        );
+        assert_eq!(
+            TokenAmount::parse("-1", 8),
+            Err(AmountError::Invalid("-1".to_string()))
+        );
+        assert_eq!(
+            TokenAmount::parse("0.001", 2),
+            Err(AmountError::Precision {
+                amount: "0.001".to_string(),
+                dec: 2
+            })
+        );
+        assert_eq!(
+            TokenAmount::parse(&u128::MAX.to_string(), 1),
+            Err(AmountError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(TokenAmount::new(100_050_000_000, 8).to_string(), "1,000.5");
+        assert_eq!(TokenAmount::new(123_456_789, 0).to_string(), "123,456,789");
+        assert_eq!(TokenAmount::new(1, 8).to_string(), "0.00000001");
+        assert_eq!(TokenAmount::zero(8).to_string(), "0");
+        assert_eq!(
+            TokenAmount::new(250, 2).format_with_suffix("sparkl"),
+            "2.5 SPARKL"
+        );
+
+        for text in ["1,000.5", "0.00000001", "21,000,000"] {
+            assert_eq!(TokenAmount::parse(text, 8).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_checked_math() {
+        let a = TokenAmount::new(10, 8);
+        let b = TokenAmount::new(3, 8);
+        assert_eq!(a.checked_add(b), Some(TokenAmount::new(13, 8)));
+        assert_eq!(a.checked_sub(b), Some(TokenAmount::new(7, 8)));
+        assert_eq!(b.checked_sub(a), None);
+        assert_eq!(a.checked_add(TokenAmount::new(1, 2)), None);
+        assert_eq!(TokenAmount::new(u128::MAX, 8).checked_add(b), None);
+        assert_eq!(a.checked_mul(3), Some(TokenAmount::new(30, 8)));
+        assert_eq!(a.checked_div(0), None);
+    }
+
+    #[test]
+    fn test_serde() {
+        let amount = TokenAmount::new(100_050_000_000, 8);
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, r#""100050000000""#);
+        assert_eq!(TokenAmount::from_raw_str("100050000000", 8), Ok(amount));
+        assert_eq!(
+            TokenAmount::from_raw_str("250", 2).unwrap().to_string(),
+            "2.5"
+        );
+        assert_eq!(
+            TokenAmount::from_raw_str("1.5", 8),
+            Err(AmountError::Invalid("1.5".to_string()))
+        );
+        assert_eq!(TokenAmount::from_raw_str("", 8), Err(AmountError::Empty));
+        assert_eq!(
+            TokenAmount::from_raw_str("1", MAX_DECIMALS + 1),
+            Err(AmountError::Decimals(MAX_DECIMALS + 1))
+        );
+    }
+}
@@ -0,0 +1,506 @@
+//!
+//! Local KRC-20 ledger.
+//!
+//! Replays the KRC-20 operations inscribed on-chain into token supplies and
+//! holder balances, and answers the kasplex v1 API queries from that state
+//! so that a node can be audited against the kasplex indexer.
+//!
+//! Operations are applied in the order they are received, which is the
+//! acceptance order of the historical sync. Mint-mode tokens are tracked
+//! (`deploy`, `mint`, `transfer` and `burn`); issue-mode and marketplace
+//! operations are recorded as rejected. Protocol fees are not checked and
+//! operations reorged out of the selected chain are not rolled back.
+//!
+
+use super::krc20::{
+    Op, OpListFilter, Token, TokenBalance, TokenHolder, TokenHolderResult, TokenTransaction,
+};
+use super::{IndexerStatus, OpScore, State, Tick, Timestamp, TokenState};
+use crate::imports::*;
+use crate::model::amount::DEFAULT_DECIMALS;
+use kaspa_addresses::Address;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Reason of a rejected operation, reported as the kasplex `opError`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OpError {
+    #[error("tick existed")]
+    TickExisted,
+
+    #[error("tick not found")]
+    TickNotFound,
+
+    #[error("mint finished")]
+    MintFinished,
+
+    #[error("balance insufficient")]
+    BalanceInsufficient,
+
+    #[error("operation unsupported")]
+    Unsupported,
+}
+
+pub type OpResult<T> = std::result::Result<T, OpError>;
+
+/// On-chain context of an inscribed operation.
+#[derive(Debug, Clone)]
+pub struct OpContext {
+    /// Reveal transaction id.
+    pub hash_rev: Hash,
+    /// P2PK address of the key revealing the inscription.
+    pub from: Address,
+    /// DAA score the operation is scored at.
+    pub daa_score: u64,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Balance {
+    amount: u128,
+    op_score_mod: OpScore,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    token: Token,
+    mint_start_daa_score: u64,
+    holders: BTreeMap<String, Balance>,
+}
+
+impl Entry {
+    fn credit(&mut self, address: &str, amount: u128, op_score: OpScore) {
+        let balance = self.holders.entry(address.to_string()).or_insert(Balance {
+            amount: 0,
+            op_score_mod: op_score,
+        });
+        balance.amount += amount;
+        balance.op_score_mod = op_score;
+    }
+
+    fn debit(&mut self, address: &str, amount: u128, op_score: OpScore) -> OpResult<()> {
+        match self.holders.get_mut(address) {
+            Some(balance) if balance.amount >= amount => {
+                balance.amount -= amount;
+                balance.op_score_mod = op_score;
+                Ok(())
+            }
+            _ => Err(OpError::BalanceInsufficient),
+        }
+    }
+
+    fn mint(&mut self, address: &str, amount: u128, op_score: OpScore) {
+        self.token.minted += amount;
+        if self.token.minted >= self.token.max {
+            self.token.state = State::Finished;
+        }
+        self.credit(address, amount, op_score);
+    }
+
+    fn balance(&self, address: &str) -> Option<TokenBalance> {
+        self.holders.get(address).map(|balance| TokenBalance {
+            tick: self.token.tick.clone(),
+            balance: balance.amount,
+            locked: 0,
+            dec: self.token.dec,
+            op_score_mod: balance.op_score_mod,
+        })
+    }
+}
+
+/// KRC-20 token state built from the inscribed operations.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    tokens: BTreeMap<Tick, Entry>,
+    /// Recorded operations in acceptance order.
+    ops: Vec<TokenTransaction>,
+    daa_score: u64,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `transaction` and records it with its outcome. Returns `None`
+    /// for operations violating the protocol rules, which kasplex ignores.
+    pub fn apply(
+        &mut self,
+        transaction: &TokenTransaction,
+        context: &OpContext,
+    ) -> Option<&TokenTransaction> {
+        transaction.validate().ok()?;
+
+        let op_score = self.next_op_score(context.daa_score);
+        let from = context.from.to_string();
+        let mut op = transaction.clone();
+        // deploy and mint credit the sender unless a recipient is set
+        if matches!(op.op, Op::Deploy | Op::Mint) && op.to.is_none() {
+            op.to = Some(from.clone());
+        }
+        op.from = Some(from);
+        op.op_score = Some(op_score);
+        op.hash_rev = Some(context.hash_rev);
+        op.fee_rev = None;
+        op.tx_accept = Some("1".to_string());
+        op.mts_add = Some(context.timestamp);
+        op.mts_mod = Some(context.timestamp);
+
+        match self.execute(&op, context) {
+            Ok(amount) => {
+                op.amount = amount.or(op.amount);
+                op.op_accept = Some("1".to_string());
+                op.op_error = Some(String::new());
+            }
+            Err(err) => {
+                op.op_accept = Some("-1".to_string());
+                op.op_error = Some(err.to_string());
+            }
+        }
+
+        self.daa_score = self.daa_score.max(context.daa_score);
+        self.ops.push(op);
+        self.ops.last()
+    }
+
+    /// Updates the token state, returning the minted amount of a `mint`.
+    fn execute(&mut self, op: &TokenTransaction, context: &OpContext) -> OpResult<Option<u128>> {
+        if op.is_issue_mode() {
+            return Err(OpError::Unsupported);
+        }
+        let tick = op.tick().map_err(|_| OpError::Unsupported)?;
+        let op_score = op.op_score.unwrap_or_default();
+        let from = op.from.as_deref().unwrap_or_default();
+        let to = op.to.as_deref().unwrap_or_default();
+        let amount = op.amount.unwrap_or_default();
+
+        if op.op == Op::Deploy {
+            if self.tokens.contains_key(&tick) {
+                return Err(OpError::TickExisted);
+            }
+            let mut entry = Entry {
+                token: Token {
+                    tick: tick.to_string(),
+                    max: op.max.unwrap_or_default(),
+                    limit: op.limit.unwrap_or_default(),
+                    dec: op.dec.map_or(DEFAULT_DECIMALS, |dec| dec as u8),
+                    minted: 0,
+                    op_score_added: op_score,
+                    op_score_modified: op_score,
+                    state: State::Deployed,
+                    hash_rev: context.hash_rev,
+                    mts_add: context.timestamp,
+                },
+                mint_start_daa_score: context.daa_score,
+                holders: BTreeMap::new(),
+            };
+            if let Some(pre) = op.pre.filter(|pre| *pre > 0) {
+                entry.mint(to, pre, op_score);
+            }
+            self.tokens.insert(tick, entry);
+            return Ok(None);
+        }
+
+        let entry = self.tokens.get_mut(&tick).ok_or(OpError::TickNotFound)?;
+        let minted = match op.op {
+            Op::Mint => {
+                let remaining = entry.token.max.saturating_sub(entry.token.minted);
+                if remaining == 0 {
+                    return Err(OpError::MintFinished);
+                }
+                // the last mint receives the remaining supply
+                let amount = entry.token.limit.min(remaining);
+                entry.mint(to, amount, op_score);
+                Some(amount)
+            }
+            Op::Transfer => {
+                entry.debit(from, amount, op_score)?;
+                entry.credit(to, amount, op_score);
+                None
+            }
+            Op::Burn => {
+                entry.debit(from, amount, op_score)?;
+                None
+            }
+            _ => return Err(OpError::Unsupported),
+        };
+        entry.token.op_score_modified = op_score;
+        Ok(minted)
+    }
+
+    /// Scores an operation after those already recorded at `daa_score`.
+    fn next_op_score(&self, daa_score: u64) -> OpScore {
+        let index = self
+            .ops
+            .iter()
+            .rev()
+            .take_while(|op| {
+                op.op_score
+                    .is_some_and(|op_score| op_score.daa_score() == daa_score)
+            })
+            .count() as u64;
+        OpScore::try_new(daa_score, index.min(OpScore::INDEX_RANGE - 1)).unwrap_or_default()
+    }
+
+    /// Returns the state of the token `tick` or `None` if it is not deployed.
+    pub fn token_state(&self, tick: &Tick) -> Option<TokenState> {
+        self.tokens
+            .get(tick)
+            .map(|entry| TokenState::try_from(&entry.token))
+            .and_then(|state| state.ok())
+    }
+
+    /// Indexer status, as served by `//info`. Fees are not tracked.
+    pub fn status(&self) -> IndexerStatus {
+        IndexerStatus {
+            daa_score: self.daa_score,
+            op_score: self
+                .ops
+                .last()
+                .and_then(|op| op.op_score)
+                .unwrap_or_default(),
+            op_total: self.ops.len() as u64,
+            token_total: self.tokens.len() as u64,
+            fee_total: 0,
+        }
+    }
+
+    /// Deployed tokens, most recent first.
+    pub fn token_list(&self) -> Vec<Token> {
+        let mut tokens = self
+            .tokens
+            .values()
+            .map(|entry| entry.token.clone())
+            .collect::<Vec<_>>();
+        tokens.sort_by(|a, b| b.op_score_added.cmp(&a.op_score_added));
+        tokens
+    }
+
+    /// Details and holders of the token `tick`; only `tick` and `state` are
+    /// set if it is not deployed.
+    pub fn token(&self, tick: &Tick) -> TokenHolderResult {
+        let Some(entry) = self.tokens.get(tick) else {
+            return TokenHolderResult {
+                tick: tick.to_string(),
+                max: 0,
+                lim: 0,
+                dec: 0,
+                mint_start_daa_score: 0,
+                mint_end_daa_score: 0,
+                minted: 0,
+                op_score_add: OpScore::default(),
+                op_score_mod: OpScore::default(),
+                state: State::Unused,
+                holder: vec![],
+            };
+        };
+
+        let mut holder = entry
+            .holders
+            .iter()
+            .filter(|(_, balance)| balance.amount > 0)
+            .map(|(address, balance)| TokenHolder {
+                address: address.clone(),
+                amount: balance.amount,
+            })
+            .collect::<Vec<_>>();
+        holder.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+        let token = &entry.token;
+        TokenHolderResult {
+            tick: token.tick.clone(),
+            max: token.max,
+            lim: token.limit,
+            dec: token.dec,
+            mint_start_daa_score: entry.mint_start_daa_score,
+            mint_end_daa_score: 0,
+            minted: token.minted,
+            op_score_add: token.op_score_added,
+            op_score_mod: token.op_score_modified,
+            state: token.state,
+            holder,
+        }
+    }
+
+    /// Token balances of `address`.
+    pub fn balances(&self, address: &str) -> Vec<TokenBalance> {
+        self.tokens
+            .values()
+            .filter_map(|entry| entry.balance(address))
+            .collect()
+    }
+
+    /// Balance of `address` in the token `tick`, empty if it never held it.
+    pub fn balance(&self, address: &str, tick: &Tick) -> Vec<TokenBalance> {
+        self.tokens
+            .get(tick)
+            .and_then(|entry| entry.balance(address))
+            .into_iter()
+            .collect()
+    }
+
+    /// Operations matching `filter`, most recent first.
+    pub fn ops(&self, filter: &OpListFilter) -> Vec<TokenTransaction> {
+        self.ops
+            .iter()
+            .rev()
+            .filter(|op| {
+                filter.address.as_ref().map_or(true, |address| {
+                    op.from.as_ref() == Some(address) || op.to.as_ref() == Some(address)
+                }) && filter.tick.map_or(true, |tick| op.has_tick(&tick))
+                    && filter.op.map_or(true, |name| op.op == name)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Operation revealed by the transaction `id` or scored `id`.
+    pub fn op(&self, id: &str) -> Vec<TokenTransaction> {
+        self.ops
+            .iter()
+            .filter(|op| {
+                op.hash_rev.is_some_and(|hash| hash.to_string() == id)
+                    || op.op_score.is_some_and(|score| score.to_string() == id)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::krc20::TokenTransactionBuilder;
+    use super::super::Protocol;
+    use super::*;
+    use kaspa_addresses::{Prefix, Version};
+
+    fn address(byte: u8) -> Address {
+        Address::new(Prefix::Testnet, Version::PubKey, &[byte; 32])
+    }
+
+    fn context(word: u64, from: &Address, daa_score: u64) -> OpContext {
+        OpContext {
+            hash_rev: Hash::from_u64_word(word),
+            from: from.clone(),
+            daa_score,
+            timestamp: Timestamp::from_millis(1718000000000).unwrap(),
+        }
+    }
+
+    fn op(op: Op) -> TokenTransactionBuilder {
+        TokenTransactionBuilder::new(Protocol::Krc20, op, "SPARKL")
+    }
+
+    fn op_error(ledger: &mut Ledger, transaction: TokenTransaction, context: OpContext) -> String {
+        ledger
+            .apply(&transaction, &context)
+            .unwrap()
+            .op_error
+            .clone()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ledger() {
+        let (alice, bob) = (address(1), address(2));
+        let tick = Tick::from_str("SPARKL").unwrap();
+        let mut ledger = Ledger::new();
+
+        // invalid operations are ignored
+        assert!(ledger
+            .apply(&op(Op::Deploy).build(), &context(1, &alice, 100))
+            .is_none());
+
+        let deploy = op(Op::Deploy).max(250).limit(100).pre(20).build();
+        assert_eq!(
+            op_error(&mut ledger, deploy.clone(), context(2, &alice, 100)),
+            ""
+        );
+        assert_eq!(
+            op_error(&mut ledger, deploy, context(3, &bob, 100)),
+            "tick existed"
+        );
+
+        let mint = op(Op::Mint).build();
+        for word in 4..6 {
+            assert_eq!(
+                op_error(&mut ledger, mint.clone(), context(word, &bob, 101)),
+                ""
+            );
+        }
+        // the last mint is capped by the remaining supply
+        let last = ledger
+            .apply(&mint, &context(6, &alice, 102))
+            .unwrap()
+            .clone();
+        assert_eq!(last.amount, Some(30));
+        assert_eq!(last.to, Some(alice.to_string()));
+        assert_eq!(
+            op_error(&mut ledger, mint, context(7, &bob, 102)),
+            "mint finished"
+        );
+        assert_eq!(
+            op_error(
+                &mut ledger,
+                TokenTransactionBuilder::new(Protocol::Krc20, Op::Mint, "KEKE").build(),
+                context(8, &bob, 102)
+            ),
+            "tick not found"
+        );
+
+        let transfer = |amount| op(Op::Transfer).amount(amount).to(&alice).build();
+        assert_eq!(
+            op_error(&mut ledger, transfer(201), context(9, &bob, 103)),
+            "balance insufficient"
+        );
+        assert_eq!(
+            op_error(&mut ledger, transfer(150), context(10, &bob, 103)),
+            ""
+        );
+        assert_eq!(
+            op_error(
+                &mut ledger,
+                op(Op::Burn).amount(50).build(),
+                context(11, &bob, 103)
+            ),
+            ""
+        );
+        assert_eq!(
+            op_error(
+                &mut ledger,
+                op(Op::List).amount(10).build(),
+                context(12, &alice, 104)
+            ),
+            "operation unsupported"
+        );
+
+        let token = ledger.token(&tick);
+        assert_eq!((token.minted, token.state), (250, State::Finished));
+        let holders = token
+            .holder
+            .iter()
+            .map(|holder| (holder.address.clone(), holder.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(holders, vec![(alice.to_string(), 200)]);
+        assert_eq!(ledger.balance(&bob.to_string(), &tick)[0].balance, 0);
+        assert_eq!(ledger.token_state(&tick).unwrap().minted, 250);
+
+        let unused = ledger.token(&Tick::from_str("KEKE").unwrap());
+        assert_eq!(unused.state, State::Unused);
+
+        // operations are scored by DAA score and order of acceptance
+        let ops = ledger.ops(&OpListFilter::default().tick(tick).op(Op::Transfer));
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].op_score, OpScore::try_new(103, 1));
+        assert_eq!(
+            ledger.op("1030001")[0].hash_rev,
+            Some(Hash::from_u64_word(10))
+        );
+
+        let status = ledger.status();
+        assert_eq!(
+            (status.op_total, status.token_total, status.daa_score),
+            (11, 1, 104)
+        );
+    }
+}
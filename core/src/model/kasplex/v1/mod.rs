@@ -6,3 +6,13 @@ pub mod state;
 pub use state::*;
 pub mod network;
 pub use network::*;
+pub mod validator;
+pub use validator::*;
+pub mod ledger;
+pub use ledger::*;
+pub mod tick;
+pub use tick::*;
+pub mod op_score;
+pub use op_score::*;
+pub mod timestamp;
+pub use timestamp::*;
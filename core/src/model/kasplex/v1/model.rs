@@ -1,5 +1,7 @@
 use super::*;
 use crate::imports::*;
+use crate::model::TokenAmount;
+use kaspa_consensus_core::network::NetworkType;
 use kaspa_wallet_core::utils::sompi_to_kaspa_string_with_suffix;
 use std::fmt::Write;
 // use serde::de::{self, Deserializer};
@@ -22,22 +24,21 @@ use std::fmt::Write;
 /// ```
 ///
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct IndexerStatusResponse {
     pub message: String,
     pub result: IndexerStatus,
 }
 
 #[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct IndexerStatus {
     #[serde_as(as = "DisplayFromStr")]
     #[serde(rename = "daaScore")]
     pub daa_score: u64,
 
-    #[serde_as(as = "DisplayFromStr")]
     #[serde(rename = "opScore")]
-    pub op_score: u64,
+    pub op_score: OpScore,
 
     #[serde_as(as = "DisplayFromStr")]
     #[serde(rename = "opTotal")]
@@ -54,7 +55,7 @@ pub struct IndexerStatus {
 }
 
 impl IndexerStatus {
-    pub fn format(&self, network: Network) -> String {
+    pub fn format(&self, network: impl Into<NetworkType>) -> String {
         let IndexerStatus {
             daa_score,
             op_score,
@@ -65,7 +66,7 @@ impl IndexerStatus {
 
         [
             ("DAA Score", daa_score),
-            ("Op Score", op_score),
+            ("Op Score", &op_score.as_u64()),
             ("Op Total", op_total),
             ("Tokens", token_total),
         ]
@@ -90,7 +91,17 @@ pub mod krc20 {
         Deploy,
         Mint,
         Transfer,
-        // Burn,
+        Burn,
+        /// Issuance of an issue-mode token by its owner.
+        Issue,
+        /// Ownership change of an issue-mode token.
+        Chown,
+        /// Addition or removal (see [`Mode`]) of a blacklisted address.
+        Blacklist,
+        /// Lock of a balance for sale, see [`Op::Send`].
+        List,
+        /// Transfer of a listed balance to the buyer (or back to the seller).
+        Send,
     }
 
     impl std::fmt::Display for Op {
@@ -99,6 +110,12 @@ pub mod krc20 {
                 Op::Deploy => write!(f, "deploy"),
                 Op::Mint => write!(f, "mint"),
                 Op::Transfer => write!(f, "transfer"),
+                Op::Burn => write!(f, "burn"),
+                Op::Issue => write!(f, "issue"),
+                Op::Chown => write!(f, "chown"),
+                Op::Blacklist => write!(f, "blacklist"),
+                Op::List => write!(f, "list"),
+                Op::Send => write!(f, "send"),
             }
         }
     }
@@ -107,15 +124,58 @@ pub mod krc20 {
         type Err = Error;
 
         fn from_str(s: &str) -> Result<Self> {
-            match s {
+            match s.to_lowercase().as_str() {
                 "deploy" => Ok(Op::Deploy),
                 "mint" => Ok(Op::Mint),
                 "transfer" => Ok(Op::Transfer),
+                "burn" => Ok(Op::Burn),
+                "issue" => Ok(Op::Issue),
+                "chown" => Ok(Op::Chown),
+                "blacklist" => Ok(Op::Blacklist),
+                "list" => Ok(Op::List),
+                "send" => Ok(Op::Send),
                 _ => Err(Error::custom(format!("Invalid KRC20 operation: {}", s))),
             }
         }
     }
 
+    /// Operation mode (`mod` field). A `deploy` creates either a mintable
+    /// token (`mint`, the default) or a token issued by its owner (`issue`);
+    /// a `blacklist` either adds or removes an address.
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Mode {
+        Mint,
+        Issue,
+        Add,
+        Remove,
+    }
+
+    impl std::fmt::Display for Mode {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Mode::Mint => write!(f, "mint"),
+                Mode::Issue => write!(f, "issue"),
+                Mode::Add => write!(f, "add"),
+                Mode::Remove => write!(f, "remove"),
+            }
+        }
+    }
+
+    impl FromStr for Mode {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self> {
+            match s.to_lowercase().as_str() {
+                "mint" => Ok(Mode::Mint),
+                "issue" => Ok(Mode::Issue),
+                "add" => Ok(Mode::Add),
+                "remove" => Ok(Mode::Remove),
+                _ => Err(Error::custom(format!("Invalid KRC20 mode: {}", s))),
+            }
+        }
+    }
+
     ///
     /// URL path: `/krc20/tokenlist`
     ///
@@ -140,7 +200,7 @@ pub mod krc20 {
     /// ```
     ///
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenListResponse {
         pub message: String,
         pub next: String,
@@ -149,8 +209,9 @@ pub mod krc20 {
     }
 
     #[serde_as]
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct Token {
+        /// Token tick as reported by the indexer, see [`Token::tick`].
         pub tick: String,
         #[serde_as(as = "DisplayFromStr")]
         pub max: u128,
@@ -161,19 +222,17 @@ pub mod krc20 {
         pub limit: u128,
 
         #[serde_as(as = "DisplayFromStr")]
-        pub dec: u64,
+        pub dec: u8,
 
         // TODO - rename to total_minted ?
         #[serde_as(as = "DisplayFromStr")]
         pub minted: u128,
 
-        #[serde_as(as = "DisplayFromStr")]
         #[serde(rename = "opScoreAdd")]
-        pub op_score_added: u64,
+        pub op_score_added: OpScore,
 
-        #[serde_as(as = "DisplayFromStr")]
         #[serde(rename = "opScoreMod")]
-        pub op_score_modified: u64,
+        pub op_score_modified: OpScore,
 
         // TODO - what are the variants?
         pub state: State,
@@ -182,10 +241,27 @@ pub mod krc20 {
         // TODO - rename to hash_revision ?
         pub hash_rev: Hash,
 
+        /// Time the token was deployed.
         #[serde(rename = "mtsAdd")]
-        // TODO - what is mts ?  time stamp in milliseconds? should be `ts_msec_added`
-        #[serde_as(as = "DisplayFromStr")]
-        pub mts_add: u64,
+        pub mts_add: Timestamp,
+    }
+
+    impl Token {
+        pub fn tick(&self) -> ValidationResult<Tick> {
+            self.tick.parse()
+        }
+
+        pub fn max_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.max, self.dec)
+        }
+
+        pub fn limit_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.limit, self.dec)
+        }
+
+        pub fn minted_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.minted, self.dec)
+        }
     }
 
     ///
@@ -214,52 +290,76 @@ pub mod krc20 {
     /// ```
     ///   
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenHolderResponse {
         pub message: String,
         pub result: TokenHolderResult,
     }
 
+    /// Token details. For a tick that is not deployed the indexer only
+    /// returns `tick` and `state` (`unused`), the other fields default.
     #[serde_as]
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct TokenHolderResult {
         pub tick: String,
 
         #[serde_as(as = "DisplayFromStr")]
+        #[serde(default)]
         pub max: u128,
 
         #[serde_as(as = "DisplayFromStr")]
+        #[serde(default)]
         pub lim: u128,
 
         #[serde_as(as = "DisplayFromStr")]
-        pub dec: u64,
+        #[serde(default)]
+        pub dec: u8,
 
         #[serde_as(as = "DisplayFromStr")]
-        #[serde(rename = "daas")]
+        #[serde(rename = "daas", default)]
         pub mint_start_daa_score: u64,
 
         #[serde_as(as = "DisplayFromStr")]
-        #[serde(rename = "daae")]
+        #[serde(rename = "daae", default)]
         pub mint_end_daa_score: u64,
 
         #[serde_as(as = "DisplayFromStr")]
+        #[serde(default)]
         pub minted: u128,
 
-        #[serde_as(as = "DisplayFromStr")]
-        #[serde(rename = "opScoreAdd")]
-        pub op_score_add: u64,
+        #[serde(rename = "opScoreAdd", default)]
+        pub op_score_add: OpScore,
 
-        #[serde_as(as = "DisplayFromStr")]
-        #[serde(rename = "opScoreMod")]
-        pub op_score_mod: u64,
+        #[serde(rename = "opScoreMod", default)]
+        pub op_score_mod: OpScore,
 
         pub state: State,
 
+        #[serde(default)]
         pub holder: Vec<TokenHolder>,
     }
 
+    impl TokenHolderResult {
+        pub fn tick(&self) -> ValidationResult<Tick> {
+            self.tick.parse()
+        }
+
+        pub fn max_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.max, self.dec)
+        }
+
+        pub fn minted_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.minted, self.dec)
+        }
+
+        /// Balance of `holder` in the token decimals.
+        pub fn holder_amount(&self, holder: &TokenHolder) -> TokenAmount {
+            TokenAmount::new(holder.amount, self.dec)
+        }
+    }
+
     #[serde_as]
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct TokenHolder {
         pub address: String,
         #[serde_as(as = "DisplayFromStr")]
@@ -285,14 +385,14 @@ pub mod krc20 {
     /// ```
     ///
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenBalanceResponse {
         pub message: String,
         pub result: Vec<TokenBalance>,
     }
 
-    #[derive(Debug, Deserialize)]
     #[serde_as]
+    #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct TokenBalance {
         pub tick: String,
 
@@ -302,13 +402,26 @@ pub mod krc20 {
         #[serde_as(as = "DisplayFromStr")]
         pub locked: u64,
 
-        /// TODO - what is dec?
+        /// Token decimals, `balance` and `locked` are in the smallest token unit.
         #[serde_as(as = "DisplayFromStr")]
-        pub dec: u128,
+        pub dec: u8,
 
-        #[serde_as(as = "DisplayFromStr")]
         #[serde(rename = "opScoreMod")]
-        pub op_score_mod: u64,
+        pub op_score_mod: OpScore,
+    }
+
+    impl TokenBalance {
+        pub fn tick(&self) -> ValidationResult<Tick> {
+            self.tick.parse()
+        }
+
+        pub fn balance_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.balance, self.dec)
+        }
+
+        pub fn locked_amount(&self) -> TokenAmount {
+            TokenAmount::new(self.locked as u128, self.dec)
+        }
     }
 
     /// URLpath : `https://tn11api.kasplex.org/v1/krc20/address/{address}/tokenlist`
@@ -330,7 +443,7 @@ pub mod krc20 {
     ///   }
     /// ```
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenBalanceListByAddressResponse {
         pub message: String,
         pub next: String,
@@ -339,7 +452,7 @@ pub mod krc20 {
     }
 
     ///
-    /// URL path: `//krc20/oplist/{op}`
+    /// URL path: `//krc20/oplist?address={address}&tick={tick}&op={op}`
     ///
     /// ```json
     /// {
@@ -370,7 +483,7 @@ pub mod krc20 {
     /// }
     /// ```
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenTransactionResponse {
         pub message: String,
         pub next: String,
@@ -378,6 +491,50 @@ pub mod krc20 {
         pub result: Vec<TokenTransaction>,
     }
 
+    /// Filters of the `//krc20/oplist` endpoint; unset filters match any
+    /// operation.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct OpListFilter {
+        pub address: Option<String>,
+        pub tick: Option<Tick>,
+        pub op: Option<Op>,
+    }
+
+    impl OpListFilter {
+        pub fn address<S: std::fmt::Display>(self, address: S) -> Self {
+            Self {
+                address: Some(address.to_string()),
+                ..self
+            }
+        }
+
+        pub fn tick(self, tick: Tick) -> Self {
+            Self {
+                tick: Some(tick),
+                ..self
+            }
+        }
+
+        pub fn op(self, op: Op) -> Self {
+            Self {
+                op: Some(op),
+                ..self
+            }
+        }
+
+        /// Query parameters of the set filters.
+        pub fn params(&self) -> Vec<(&'static str, String)> {
+            [
+                ("address", self.address.clone()),
+                ("tick", self.tick.map(|tick| tick.to_string())),
+                ("op", self.op.map(|op| op.to_string())),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+        }
+    }
+
     #[serde_as]
     #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
     pub struct TokenTransaction {
@@ -385,10 +542,28 @@ pub mod krc20 {
         #[serde(rename = "p")]
         pub protocol: Protocol,
 
+        #[serde_as(as = "DisplayFromStr")]
         pub op: Op,
 
+        /// Token tick; empty for operations addressing an issue-mode
+        /// token by its contract address (`ca`).
+        #[serde(default)]
+        #[serde(skip_serializing_if = "String::is_empty")]
         pub tick: String,
 
+        #[serde_as(as = "Option<DisplayFromStr>")]
+        #[serde(rename = "mod")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub mode: Option<Mode>,
+
+        /// Token name of an issue-mode `deploy`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub name: Option<String>,
+
+        /// Contract address (deploy transaction id) of an issue-mode token.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ca: Option<String>,
+
         #[serde_as(as = "Option<DisplayFromStr>")]
         #[serde(skip_serializing_if = "Option::is_none")]
         pub max: Option<u128>,
@@ -418,10 +593,9 @@ pub mod krc20 {
         #[serde(skip_serializing_if = "Option::is_none")]
         pub to: Option<String>,
 
-        #[serde_as(as = "Option<DisplayFromStr>")]
         #[serde(rename = "opScore")]
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub op_score: Option<u64>,
+        pub op_score: Option<OpScore>,
 
         #[serde(rename = "hashRev")]
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -445,11 +619,11 @@ pub mod krc20 {
 
         #[serde(rename = "mtsAdd")]
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub mts_add: Option<String>,
+        pub mts_add: Option<Timestamp>,
 
         #[serde(rename = "mtsMod")]
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub mts_mod: Option<String>,
+        pub mts_mod: Option<Timestamp>,
     }
 
     impl AsRef<TokenTransaction> for TokenTransaction {
@@ -459,8 +633,23 @@ pub mod krc20 {
     }
 
     impl TokenTransaction {
-        pub fn has_tick<S: std::fmt::Display>(&self, tick: S) -> bool {
-            self.tick.to_lowercase() == tick.to_string().to_lowercase()
+        /// Returns the tick of the operation if valid.
+        pub fn tick(&self) -> ValidationResult<Tick> {
+            self.tick.parse()
+        }
+
+        pub fn has_tick(&self, tick: &Tick) -> bool {
+            self.tick().is_ok_and(|own| own == *tick)
+        }
+
+        /// Token identifier: the contract address if present, the tick otherwise.
+        pub fn token_id(&self) -> &str {
+            self.ca.as_deref().unwrap_or(&self.tick)
+        }
+
+        /// Returns `true` if the operation deploys or addresses an issue-mode token.
+        pub fn is_issue_mode(&self) -> bool {
+            self.ca.is_some() || (self.op == Op::Deploy && self.mode == Some(Mode::Issue))
         }
     }
 
@@ -495,16 +684,88 @@ pub mod krc20 {
     /// ```
     ///
 
-    #[derive(Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TokenTransactionIdResponse {
         pub message: String,
         pub result: Vec<TokenTransaction>,
     }
 
+    ///
+    /// URL path: `//krc20/market/{tick}?address={address}`
+    ///
+    /// ```json
+    /// {
+    ///     "message": "successful",
+    ///     "prev": "text",
+    ///     "next": "text",
+    ///     "result": [
+    ///         {
+    ///             "tick": "KEKE",
+    ///             "from": "kaspa:qra0p5ky...",
+    ///             "amount": "2300000000",
+    ///             "uTxid": "b2a7fc0a6d3e4b...",
+    ///             "uAddr": "kaspa:pzgm6d9n...",
+    ///             "uAmt": "100000000",
+    ///             "uScript": "2096d6b3...",
+    ///             "opScoreAdd": "779066550003"
+    ///         }
+    ///     ]
+    /// }
+    /// ```
+    ///
+
+    #[derive(Debug, Deserialize)]
+    pub struct MarketListingResponse {
+        pub message: String,
+        pub next: String,
+        pub prev: String,
+        pub result: Vec<MarketListing>,
+    }
+
+    /// Balance locked by a `list` operation, spendable by a `send`.
+    #[serde_as]
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct MarketListing {
+        pub tick: String,
+
+        /// Seller address.
+        pub from: String,
+
+        #[serde_as(as = "DisplayFromStr")]
+        pub amount: u128,
+
+        /// Transaction id of the listed (`send` P2SH) output.
+        #[serde(rename = "uTxid")]
+        pub utxo_txid: Hash,
+
+        #[serde(rename = "uAddr")]
+        pub utxo_address: String,
+
+        #[serde_as(as = "DisplayFromStr")]
+        #[serde(rename = "uAmt")]
+        pub utxo_amount: u64,
+
+        /// Hex-encoded redeem script of the listed output.
+        #[serde(rename = "uScript")]
+        pub utxo_script: String,
+
+        #[serde(rename = "opScoreAdd")]
+        pub op_score_add: OpScore,
+    }
+
+    impl MarketListing {
+        pub fn tick(&self) -> ValidationResult<Tick> {
+            self.tick.parse()
+        }
+    }
+
     pub struct TokenTransactionBuilder {
         protocol: Protocol,
         op: Op,
         tick: String,
+        mode: Option<Mode>,
+        name: Option<String>,
+        ca: Option<String>,
         max: Option<u128>,
         limit: Option<u128>,
         pre: Option<u128>,
@@ -512,14 +773,14 @@ pub mod krc20 {
         amount: Option<u128>,
         from: Option<String>,
         to: Option<String>,
-        op_score: Option<u64>,
+        op_score: Option<OpScore>,
         hash_rev: Option<Hash>,
         fee_rev: Option<String>,
         tx_accept: Option<String>,
         op_accept: Option<String>,
         op_error: Option<String>,
-        mts_add: Option<String>,
-        mts_mod: Option<String>,
+        mts_add: Option<Timestamp>,
+        mts_mod: Option<Timestamp>,
     }
 
     impl TokenTransactionBuilder {
@@ -528,6 +789,9 @@ pub mod krc20 {
                 protocol,
                 op,
                 tick: tick.to_string(),
+                mode: None,
+                name: None,
+                ca: None,
                 max: None,
                 limit: None,
                 pre: None,
@@ -574,11 +838,49 @@ pub mod krc20 {
             }
         }
 
+        pub fn pre(self, pre: u128) -> Self {
+            Self {
+                pre: Some(pre),
+                ..self
+            }
+        }
+
+        pub fn to<S: std::fmt::Display>(self, to: S) -> Self {
+            Self {
+                to: Some(to.to_string()),
+                ..self
+            }
+        }
+
+        pub fn mode(self, mode: Mode) -> Self {
+            Self {
+                mode: Some(mode),
+                ..self
+            }
+        }
+
+        pub fn name<S: std::fmt::Display>(self, name: S) -> Self {
+            Self {
+                name: Some(name.to_string()),
+                ..self
+            }
+        }
+
+        pub fn ca<S: std::fmt::Display>(self, ca: S) -> Self {
+            Self {
+                ca: Some(ca.to_string()),
+                ..self
+            }
+        }
+
         pub fn build(self) -> TokenTransaction {
             TokenTransaction {
                 protocol: self.protocol,
                 op: self.op,
                 tick: self.tick,
+                mode: self.mode,
+                name: self.name,
+                ca: self.ca,
                 max: self.max,
                 limit: self.limit,
                 pre: self.pre,
@@ -627,6 +929,64 @@ pub mod krc20 {
                 )
                 .amount(500)
                 .build()),
+            },
+            TestCase {
+                json_str: r#"
+                    {
+                        "p": "KRC-20",
+                        "op": "BURN",
+                        "tick": "SPARKL",
+                        "amt": "100"
+                    }
+                    "#,
+                expected: Ok(TokenTransactionBuilder::new(
+                    Protocol::Krc20,
+                    Op::Burn,
+                    "SPARKL".to_string(),
+                )
+                .amount(100)
+                .build()),
+            },
+            TestCase {
+                json_str: r#"
+                    {
+                        "p": "krc-20",
+                        "op": "blacklist",
+                        "ca": "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3",
+                        "mod": "add",
+                        "to": "kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya"
+                    }
+                    "#,
+                expected: Ok(TokenTransactionBuilder::new(Protocol::Krc20, Op::Blacklist, "")
+                    .ca("770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3")
+                    .mode(Mode::Add)
+                    .to("kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya")
+                    .build()),
+            },
+            TestCase {
+                json_str: r#"
+                    {
+                        "p": "krc-20",
+                        "op": "chown",
+                        "tick": "SPARKL"
+                    }
+                    "#,
+                expected: Ok(TokenTransactionBuilder::new(
+                    Protocol::Krc20,
+                    Op::Chown,
+                    "SPARKL".to_string(),
+                )
+                .build()),
+            },
+            TestCase {
+                json_str: r#"
+                    {
+                        "p": "krc-20",
+                        "op": "melt",
+                        "tick": "SPARKL"
+                    }
+                    "#,
+                expected: Err(serde::de::Error::custom("invalid op")),
             }]
         }
 
@@ -650,5 +1010,117 @@ pub mod krc20 {
                 run_test_case(test_case);
             }
         }
+
+        #[test]
+        fn test_op_round_trip() {
+            for op in [
+                Op::Deploy,
+                Op::Mint,
+                Op::Transfer,
+                Op::Burn,
+                Op::Issue,
+                Op::Chown,
+                Op::Blacklist,
+                Op::List,
+                Op::Send,
+            ] {
+                assert_eq!(Op::from_str(&op.to_string()).unwrap(), op);
+                assert_eq!(Op::from_str(&op.to_string().to_uppercase()).unwrap(), op);
+            }
+
+            let deploy = TokenTransactionBuilder::new(Protocol::Krc20, Op::Deploy, "")
+                .mode(Mode::Issue)
+                .name("SPARKL")
+                .max(1000)
+                .to("kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya")
+                .build();
+            let json = serde_json::to_string(&deploy).unwrap();
+            assert!(json.contains(r#""mod":"issue""#));
+            assert!(!json.contains("tick"));
+            assert_eq!(
+                serde_json::from_str::<TokenTransaction>(&json).unwrap(),
+                deploy
+            );
+            assert!(deploy.is_issue_mode());
+        }
+
+        #[test]
+        fn test_op_list_ordering() {
+            let json = r#"{
+                "message": "successful",
+                "prev": "",
+                "next": "",
+                "result": [
+                    {
+                        "p": "KRC-20",
+                        "op": "MINT",
+                        "tick": "KEKE",
+                        "opScore": "779066560001",
+                        "mtsAdd": "1712808990016",
+                        "mtsMod": "1712808990016"
+                    },
+                    {
+                        "p": "KRC-20",
+                        "op": "DEPLOY",
+                        "tick": "KEKE",
+                        "max": "2100000000000000",
+                        "lim": "100000000000",
+                        "opScore": "779066550003",
+                        "mtsAdd": "1712808987852",
+                        "mtsMod": "1712808990016"
+                    }
+                ]
+            }"#;
+            let mut response = serde_json::from_str::<TokenTransactionResponse>(json).unwrap();
+            response
+                .result
+                .sort_by_key(|transaction| transaction.op_score);
+
+            let deploy = &response.result[0];
+            assert_eq!(deploy.op, Op::Deploy);
+            let op_score = deploy.op_score.unwrap();
+            assert_eq!((op_score.daa_score(), op_score.index()), (77906655, 3));
+            assert_eq!(
+                deploy.mts_add.unwrap().to_string(),
+                "2024-04-11 04:16:27.852 UTC"
+            );
+            assert!(deploy.mts_add < response.result[1].mts_add);
+        }
+
+        #[test]
+        fn test_op_list_filter() {
+            assert!(OpListFilter::default().params().is_empty());
+            let filter = OpListFilter::default()
+                .tick(Tick::from_str("keke").unwrap())
+                .op(Op::Mint);
+            assert_eq!(
+                filter.params(),
+                [("tick", "KEKE".to_string()), ("op", "mint".to_string())]
+            );
+        }
+
+        #[test]
+        fn test_token_unused() {
+            let response = serde_json::from_str::<TokenHolderResponse>(
+                r#"{"message":"successful","result":{"tick":"ZZZZ","state":"unused"}}"#,
+            )
+            .unwrap();
+            let token = &response.result;
+            assert_eq!(token.state, State::Unused);
+            assert_eq!(token.max, 0);
+            assert!(token.holder.is_empty());
+            assert!(!TokenState::try_from(token).unwrap().is_deployed());
+        }
+
+        #[test]
+        fn test_token_balance_lenient_tick() {
+            // ticks the protocol rules reject do not fail the whole response
+            let balance = serde_json::from_str::<TokenBalance>(
+                r#"{"tick":"kas1","balance":"5","locked":"0","dec":"8","opScoreMod":"0"}"#,
+            )
+            .unwrap();
+            assert_eq!(balance.tick, "kas1");
+            assert!(balance.tick().is_err());
+        }
     }
 }
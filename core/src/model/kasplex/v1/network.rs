@@ -3,21 +3,27 @@ use crate::url::Url;
 use kaspa_consensus_core::network::NetworkId;
 use kaspa_consensus_core::network::NetworkType;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Network {
     Mainnet,
     Testnet10,
     Testnet11,
 }
 
-impl From<Network> for Url {
-    fn from(network: Network) -> Self {
-        let url = match network {
+impl Network {
+    /// URL of the public kasplex indexer of the network.
+    pub fn indexer_url(&self) -> &'static str {
+        match self {
             Network::Mainnet => "https://api.kasplex.org/v1",
-            Network::Testnet10 => panic!("Testnet10 is not supported"),
+            Network::Testnet10 => "https://tn10api.kasplex.org/v1",
             Network::Testnet11 => "https://tn11api.kasplex.org/v1",
-        };
+        }
+    }
+}
 
-        url.into()
+impl From<Network> for Url {
+    fn from(network: Network) -> Self {
+        network.indexer_url().into()
     }
 }
 
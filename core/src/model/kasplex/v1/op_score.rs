@@ -0,0 +1,107 @@
+//!
+//! Kasplex operation score.
+//!
+//! The indexer orders operations by `opScore`, the DAA score of the block
+//! accepting the transaction followed by 4 decimal digits of the operation
+//! index within that block (`779066550003` is operation 3 at DAA score
+//! `77906655`).
+//!
+
+use crate::imports::*;
+use std::fmt;
+use std::num::ParseIntError;
+
+/// Operation score, totally ordered by DAA score and then index.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    SerializeDisplay,
+    DeserializeFromStr,
+)]
+pub struct OpScore(u64);
+
+impl OpScore {
+    /// Multiplier of the DAA score, bounding the operation index.
+    pub const INDEX_RANGE: u64 = 10_000;
+
+    /// Returns `None` if `index` is out of range or the score overflows.
+    pub fn try_new(daa_score: u64, index: u64) -> Option<Self> {
+        (index < Self::INDEX_RANGE).then_some(())?;
+        Some(Self(
+            daa_score
+                .checked_mul(Self::INDEX_RANGE)?
+                .checked_add(index)?,
+        ))
+    }
+
+    /// DAA score of the block accepting the operation.
+    pub fn daa_score(&self) -> u64 {
+        self.0 / Self::INDEX_RANGE
+    }
+
+    /// Index of the operation within the accepting block.
+    pub fn index(&self) -> u64 {
+        self.0 % Self::INDEX_RANGE
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for OpScore {
+    fn from(score: u64) -> Self {
+        Self(score)
+    }
+}
+
+impl From<OpScore> for u64 {
+    fn from(score: OpScore) -> Self {
+        score.0
+    }
+}
+
+impl FromStr for OpScore {
+    type Err = ParseIntError;
+
+    fn from_str(score: &str) -> std::result::Result<Self, Self::Err> {
+        score.parse().map(Self)
+    }
+}
+
+impl fmt::Display for OpScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_score() {
+        let score = OpScore::from_str("779066550003").unwrap();
+        assert_eq!(score.daa_score(), 77906655);
+        assert_eq!(score.index(), 3);
+        assert_eq!(OpScore::try_new(77906655, 3), Some(score));
+        assert_eq!(OpScore::try_new(77906655, 10_000), None);
+        assert_eq!(OpScore::try_new(u64::MAX, 0), None);
+
+        assert!(OpScore::try_new(77906655, 9999).unwrap() < OpScore::try_new(77906656, 0).unwrap());
+        assert!(OpScore::try_new(77906655, 2).unwrap() < score);
+
+        assert_eq!(serde_json::to_string(&score).unwrap(), r#""779066550003""#);
+        assert_eq!(
+            serde_json::from_str::<OpScore>(r#""779066550003""#).unwrap(),
+            score
+        );
+    }
+}
@@ -1,7 +1,14 @@
+use super::krc20::TokenTransaction;
+use crate::constants::{KRC20_HEADER_LC, PROTOCOL_NAMESPACE};
+use crate::envelope::{Envelope, CONTENT_TYPE_JSON};
 use crate::imports::*;
+use crate::model::registry::{contains_header, ProtocolHandler, ProtocolOperation};
 use crate::result::Result;
 use std::fmt;
 
+/// Identifier of the KRC-20 protocol in the registry.
+pub const PROTOCOL_ID_KRC20: &str = "krc-20";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Protocol {
     Krc20,
@@ -10,7 +17,7 @@ pub enum Protocol {
 impl fmt::Display for Protocol {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Protocol::Krc20 => write!(f, "krc-20"),
+            Protocol::Krc20 => write!(f, "{PROTOCOL_ID_KRC20}"),
         }
     }
 }
@@ -25,3 +32,54 @@ impl FromStr for Protocol {
         }
     }
 }
+
+/// Operations of the KRC-20 protocol.
+pub const KRC20_OPS: &[&str] = &[
+    "deploy",
+    "mint",
+    "transfer",
+    "burn",
+    "issue",
+    "chown",
+    "blacklist",
+    "list",
+    "send",
+];
+
+/// KRC-20 operations, JSON-encoded under the `kasplex` namespace.
+pub struct Krc20Handler;
+
+impl ProtocolHandler for Krc20Handler {
+    fn id(&self) -> &'static str {
+        PROTOCOL_ID_KRC20
+    }
+
+    fn namespace(&self) -> &'static str {
+        PROTOCOL_NAMESPACE
+    }
+
+    fn ops(&self) -> &'static [&'static str] {
+        KRC20_OPS
+    }
+
+    fn detect(&self, envelope: &Envelope) -> bool {
+        envelope.has_namespace(PROTOCOL_NAMESPACE)
+            && envelope.content_type() == Ok(CONTENT_TYPE_JSON)
+            && contains_header(&envelope.payload, &KRC20_HEADER_LC)
+    }
+
+    fn decode(&self, envelope: &Envelope) -> Result<Box<dyn ProtocolOperation>> {
+        let transaction = serde_json::from_slice::<TokenTransaction>(&envelope.payload)?;
+        Ok(Box::new(transaction))
+    }
+}
+
+impl ProtocolOperation for TokenTransaction {
+    fn protocol(&self) -> &'static str {
+        PROTOCOL_ID_KRC20
+    }
+
+    fn op(&self) -> String {
+        self.op.to_string()
+    }
+}
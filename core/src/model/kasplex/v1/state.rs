@@ -3,6 +3,18 @@ use crate::imports::*;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum State {
+    /// Tick has not been deployed.
+    Unused,
     Deployed,
     Finished,
 }
+
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            State::Unused => write!(f, "unused"),
+            State::Deployed => write!(f, "deployed"),
+            State::Finished => write!(f, "finished"),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//!
+//! KRC-20 token tick.
+//!
+//! Ticks are case-insensitive; a [`Tick`] is validated on construction and
+//! kept in canonical uppercase so that `toitoi` and `TOITOI` compare, hash
+//! and store identically.
+//!
+
+use super::validator::{validate_tick, ValidationError, TICK_MAX_LENGTH};
+use crate::imports::*;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Validated, uppercase KRC-20 tick, usable as a map or database key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub struct Tick {
+    len: u8,
+    bytes: [u8; TICK_MAX_LENGTH],
+}
+
+impl Tick {
+    pub fn as_str(&self) -> &str {
+        // only ASCII letters are accepted on construction
+        std::str::from_utf8(self.as_ref()).unwrap()
+    }
+}
+
+impl FromStr for Tick {
+    type Err = ValidationError;
+
+    fn from_str(tick: &str) -> std::result::Result<Self, Self::Err> {
+        validate_tick(tick)?;
+        let mut bytes = [0; TICK_MAX_LENGTH];
+        bytes[..tick.len()].copy_from_slice(tick.to_ascii_uppercase().as_bytes());
+        Ok(Self {
+            len: tick.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl TryFrom<&str> for Tick {
+    type Error = ValidationError;
+
+    fn try_from(tick: &str) -> std::result::Result<Self, Self::Error> {
+        Self::from_str(tick)
+    }
+}
+
+impl AsRef<[u8]> for Tick {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl AsRef<str> for Tick {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Ord for Tick {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialOrd for Tick {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tick({})", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        let tick = Tick::from_str("toitoi").unwrap();
+        assert_eq!(tick.as_str(), "TOITOI");
+        assert_eq!(tick, Tick::from_str("TOITOI").unwrap());
+        assert_eq!(AsRef::<[u8]>::as_ref(&tick), b"TOITOI");
+        assert_eq!(
+            Tick::from_str("SPARKLES"),
+            Err(ValidationError::TickLength(8))
+        );
+        assert_eq!(
+            Tick::from_str("KAS1"),
+            Err(ValidationError::TickCharacters("KAS1".to_string()))
+        );
+
+        let mut ticks = ["toitoi", "Kasp", "ZZZZ", "kaspa"]
+            .map(|tick| Tick::from_str(tick).unwrap())
+            .to_vec();
+        ticks.sort();
+        assert_eq!(
+            ticks.iter().map(Tick::as_str).collect::<Vec<_>>(),
+            ["KASP", "KASPA", "TOITOI", "ZZZZ"]
+        );
+
+        let json = serde_json::to_string(&Tick::from_str("Kasp").unwrap()).unwrap();
+        assert_eq!(json, r#""KASP""#);
+        assert_eq!(
+            serde_json::from_str::<Tick>(r#""kasp""#).unwrap().as_str(),
+            "KASP"
+        );
+        assert!(serde_json::from_str::<Tick>(r#""k""#).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Kasplex millisecond timestamps.
+//!
+//! The indexer reports the time an operation was added (`mtsAdd`) and last
+//! modified (`mtsMod`) as strings of milliseconds since the Unix epoch.
+//!
+
+use crate::imports::*;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+/// UTC timestamp with millisecond precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    /// Returns `None` if `millis` is out of the supported range.
+    pub fn from_millis(millis: i64) -> Option<Self> {
+        Utc.timestamp_millis_opt(millis).single().map(Self)
+    }
+
+    pub fn as_millis(&self) -> i64 {
+        self.0.timestamp_millis()
+    }
+
+    pub fn as_datetime(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(datetime: DateTime<Utc>) -> Self {
+        Self(datetime)
+    }
+}
+
+impl From<Timestamp> for DateTime<Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(millis: &str) -> Result<Self> {
+        Self::from_millis(millis.parse()?)
+            .ok_or_else(|| Error::custom(format!("Invalid timestamp `{millis}`")))
+    }
+}
+
+/// Formats the timestamp as `2024-04-11 04:16:27.852 UTC`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%Y-%m-%d %H:%M:%S%.3f UTC"))
+    }
+}
+
+/// Serialized as the millisecond string used by kasplex.
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.as_millis())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let added = Timestamp::from_str("1712808987852").unwrap();
+        let modified = Timestamp::from_str("1712808990016").unwrap();
+        assert_eq!(added.as_millis(), 1712808987852);
+        assert!(added < modified);
+        assert_eq!(added.to_string(), "2024-04-11 04:16:27.852 UTC");
+        assert!(Timestamp::from_str("soon").is_err());
+
+        assert_eq!(serde_json::to_string(&added).unwrap(), r#""1712808987852""#);
+        assert_eq!(
+            serde_json::from_str::<Timestamp>(r#""1712808987852""#).unwrap(),
+            added
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//!
+//! KRC-20 operation validation.
+//!
+//! Checks a [`TokenTransaction`] against the kasplex v1 protocol rules
+//! and, optionally, against the current state of the token, so that
+//! operations the indexer would reject are caught before any funds are
+//! committed.
+//!
+
+use super::krc20::{Mode, Op, Token, TokenHolderResult, TokenTransaction};
+use super::{State, Tick};
+use crate::model::amount::MAX_DECIMALS;
+use crate::model::TokenAmount;
+use kaspa_addresses::Address;
+use thiserror::Error;
+
+/// Minimum tick length.
+pub const TICK_MIN_LENGTH: usize = 4;
+/// Maximum tick length.
+pub const TICK_MAX_LENGTH: usize = 6;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("Invalid tick length {0}, expecting {TICK_MIN_LENGTH}..={TICK_MAX_LENGTH} characters")]
+    TickLength(usize),
+
+    #[error("Invalid tick `{0}`, expecting ASCII letters only")]
+    TickCharacters(String),
+
+    #[error("Missing `{field}` for `{op}` operation")]
+    MissingField { op: Op, field: &'static str },
+
+    #[error("Zero `{field}` for `{op}` operation")]
+    ZeroField { op: Op, field: &'static str },
+
+    #[error("Mint limit {limit} exceeds max supply {max}")]
+    LimitExceedsMax { limit: u128, max: u128 },
+
+    #[error("Pre-allocation {pre} exceeds max supply {max}")]
+    PreExceedsMax { pre: u128, max: u128 },
+
+    #[error("Invalid decimals {0}, expecting at most {MAX_DECIMALS}")]
+    Decimals(u64),
+
+    #[error("Invalid recipient address `{0}`")]
+    Recipient(String),
+
+    #[error("Invalid mode `{mode}` for `{op}` operation")]
+    Mode { op: Op, mode: Mode },
+
+    #[error("Invalid contract address `{0}`, expecting a transaction id")]
+    ContractAddress(String),
+
+    #[error("Token `{0}` is issued by its owner and can not be minted")]
+    IssueMode(String),
+
+    #[error("Token `{0}` is already deployed")]
+    TokenExists(String),
+
+    #[error("Token `{0}` is not deployed")]
+    TokenNotFound(String),
+
+    #[error("Token `{0}` is fully minted")]
+    MintFinished(String),
+}
+
+pub type ValidationResult<T> = std::result::Result<T, ValidationError>;
+
+/// Token state relevant to operation validation, as reported by the
+/// kasplex indexer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenState {
+    pub tick: Tick,
+    pub max: u128,
+    pub limit: u128,
+    pub dec: u8,
+    pub minted: u128,
+    pub state: State,
+}
+
+impl TryFrom<&Token> for TokenState {
+    type Error = ValidationError;
+
+    fn try_from(token: &Token) -> ValidationResult<Self> {
+        Ok(Self {
+            tick: token.tick()?,
+            max: token.max,
+            limit: token.limit,
+            dec: token.dec,
+            minted: token.minted,
+            state: token.state,
+        })
+    }
+}
+
+impl TryFrom<&TokenHolderResult> for TokenState {
+    type Error = ValidationError;
+
+    fn try_from(token: &TokenHolderResult) -> ValidationResult<Self> {
+        Ok(Self {
+            tick: token.tick()?,
+            max: token.max,
+            limit: token.lim,
+            dec: token.dec,
+            minted: token.minted,
+            state: token.state,
+        })
+    }
+}
+
+impl TokenState {
+    pub fn is_deployed(&self) -> bool {
+        self.state != State::Unused
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Finished || self.minted >= self.max
+    }
+
+    /// Interprets `raw` in the token decimals.
+    pub fn amount(&self, raw: u128) -> TokenAmount {
+        TokenAmount::new(raw, self.dec)
+    }
+}
+
+impl TokenTransaction {
+    /// Checks the operation against the protocol rules.
+    pub fn validate(&self) -> ValidationResult<()> {
+        match self.op {
+            Op::Deploy => {
+                let max = match self.mode.unwrap_or(Mode::Mint) {
+                    Mode::Mint => {
+                        validate_tick(&self.tick)?;
+                        let max = require(self.op, "max", self.max)?;
+                        let limit = require(self.op, "lim", self.limit)?;
+                        if limit > max {
+                            return Err(ValidationError::LimitExceedsMax { limit, max });
+                        }
+                        max
+                    }
+                    Mode::Issue => {
+                        let name = self.name.as_deref().ok_or(ValidationError::MissingField {
+                            op: self.op,
+                            field: "name",
+                        })?;
+                        validate_tick(name)?;
+                        let max = require(self.op, "max", self.max)?;
+                        // owner of the token
+                        self.recipient()?;
+                        max
+                    }
+                    mode => return Err(ValidationError::Mode { op: self.op, mode }),
+                };
+                if let Some(pre) = self.pre {
+                    if pre > max {
+                        return Err(ValidationError::PreExceedsMax { pre, max });
+                    }
+                }
+                if let Some(dec) = self.dec {
+                    if dec > MAX_DECIMALS as u64 {
+                        return Err(ValidationError::Decimals(dec));
+                    }
+                }
+            }
+            Op::Mint => {
+                if let Some(ca) = self.ca.as_ref() {
+                    return Err(ValidationError::IssueMode(ca.clone()));
+                }
+                validate_tick(&self.tick)?;
+            }
+            Op::Transfer => {
+                self.validate_token()?;
+                require(self.op, "amt", self.amount)?;
+                self.recipient()?;
+            }
+            Op::Burn | Op::List => {
+                self.validate_token()?;
+                require(self.op, "amt", self.amount)?;
+            }
+            Op::Send => {
+                self.validate_token()?;
+            }
+            Op::Issue => {
+                self.contract_address()?;
+                require(self.op, "amt", self.amount)?;
+                if self.to.is_some() {
+                    self.recipient()?;
+                }
+            }
+            Op::Chown => {
+                self.contract_address()?;
+                self.recipient()?;
+            }
+            Op::Blacklist => {
+                self.contract_address()?;
+                match self.mode {
+                    Some(Mode::Add | Mode::Remove) => {}
+                    Some(mode) => return Err(ValidationError::Mode { op: self.op, mode }),
+                    None => {
+                        return Err(ValidationError::MissingField {
+                            op: self.op,
+                            field: "mod",
+                        })
+                    }
+                }
+                self.recipient()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the operation against the protocol rules and the current
+    /// `state` of the token (`None` if the token is unknown).
+    pub fn validate_with_state(&self, state: Option<&TokenState>) -> ValidationResult<()> {
+        self.validate()?;
+
+        let state = state.filter(|state| state.is_deployed());
+        match (self.op, state) {
+            (Op::Deploy, Some(_)) => Err(ValidationError::TokenExists(self.token_id().into())),
+            (Op::Deploy, None) => Ok(()),
+            (_, None) => Err(ValidationError::TokenNotFound(self.token_id().into())),
+            (Op::Mint, Some(state)) if state.is_finished() => {
+                Err(ValidationError::MintFinished(self.token_id().into()))
+            }
+            (_, Some(_)) => Ok(()),
+        }
+    }
+
+    /// Checks the token identifier: the contract address of issue-mode
+    /// tokens, the tick otherwise.
+    fn validate_token(&self) -> ValidationResult<()> {
+        if self.ca.is_some() {
+            self.contract_address().map(|_| ())
+        } else {
+            validate_tick(&self.tick)
+        }
+    }
+
+    fn contract_address(&self) -> ValidationResult<&str> {
+        let ca = self.ca.as_deref().ok_or(ValidationError::MissingField {
+            op: self.op,
+            field: "ca",
+        })?;
+        if ca.len() == 64 && ca.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Ok(ca)
+        } else {
+            Err(ValidationError::ContractAddress(ca.to_string()))
+        }
+    }
+
+    fn recipient(&self) -> ValidationResult<Address> {
+        let to = self.to.as_deref().ok_or(ValidationError::MissingField {
+            op: self.op,
+            field: "to",
+        })?;
+        Address::try_from(to).map_err(|_| ValidationError::Recipient(to.to_string()))
+    }
+}
+
+/// Checks that `tick` consists of 4 to 6 ASCII letters.
+pub fn validate_tick(tick: &str) -> ValidationResult<()> {
+    if !(TICK_MIN_LENGTH..=TICK_MAX_LENGTH).contains(&tick.len()) {
+        Err(ValidationError::TickLength(tick.len()))
+    } else if !tick.bytes().all(|b| b.is_ascii_alphabetic()) {
+        Err(ValidationError::TickCharacters(tick.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+fn require(op: Op, field: &'static str, value: Option<u128>) -> ValidationResult<u128> {
+    match value {
+        None => Err(ValidationError::MissingField { op, field }),
+        Some(0) => Err(ValidationError::ZeroField { op, field }),
+        Some(value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::krc20::TokenTransactionBuilder;
+    use super::super::Protocol;
+    use super::*;
+    use std::str::FromStr;
+
+    fn state(minted: u128, state: State) -> TokenState {
+        TokenState {
+            tick: Tick::from_str("SPARKL").unwrap(),
+            max: 1000,
+            limit: 10,
+            dec: 8,
+            minted,
+            state,
+        }
+    }
+
+    #[test]
+    fn test_validate_deploy() {
+        let deploy = |max, limit| {
+            TokenTransactionBuilder::new(Protocol::Krc20, Op::Deploy, "SPARKL")
+                .max(max)
+                .limit(limit)
+        };
+
+        assert_eq!(deploy(1000, 10).build().validate(), Ok(()));
+        assert_eq!(
+            deploy(10, 1000).build().validate(),
+            Err(ValidationError::LimitExceedsMax {
+                limit: 1000,
+                max: 10
+            })
+        );
+        assert_eq!(
+            deploy(1000, 10).dec(19).build().validate(),
+            Err(ValidationError::Decimals(19))
+        );
+        assert_eq!(
+            TokenTransactionBuilder::new(Protocol::Krc20, Op::Deploy, "SPARKL")
+                .limit(10)
+                .build()
+                .validate(),
+            Err(ValidationError::MissingField {
+                op: Op::Deploy,
+                field: "max"
+            })
+        );
+        assert_eq!(
+            TokenTransactionBuilder::new(Protocol::Krc20, Op::Deploy, "SPARKLES")
+                .build()
+                .validate(),
+            Err(ValidationError::TickLength(8))
+        );
+        assert_eq!(
+            TokenTransactionBuilder::new(Protocol::Krc20, Op::Deploy, "SPRK1")
+                .build()
+                .validate(),
+            Err(ValidationError::TickCharacters("SPRK1".to_string()))
+        );
+
+        let deployed = state(0, State::Deployed);
+        assert_eq!(
+            deploy(1000, 10)
+                .build()
+                .validate_with_state(Some(&deployed)),
+            Err(ValidationError::TokenExists("SPARKL".to_string()))
+        );
+        let unused = state(0, State::Unused);
+        assert_eq!(
+            deploy(1000, 10).build().validate_with_state(Some(&unused)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_transfer_and_mint() {
+        let transfer =
+            TokenTransactionBuilder::new(Protocol::Krc20, Op::Transfer, "SPARKL").build();
+        assert_eq!(
+            transfer.validate(),
+            Err(ValidationError::MissingField {
+                op: Op::Transfer,
+                field: "amt"
+            })
+        );
+
+        let mint = TokenTransactionBuilder::new(Protocol::Krc20, Op::Mint, "SPARKL").build();
+        assert_eq!(
+            mint.validate_with_state(None),
+            Err(ValidationError::TokenNotFound("SPARKL".to_string()))
+        );
+        assert_eq!(
+            mint.validate_with_state(Some(&state(1000, State::Deployed))),
+            Err(ValidationError::MintFinished("SPARKL".to_string()))
+        );
+        assert_eq!(
+            mint.validate_with_state(Some(&state(500, State::Deployed))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_issue_mode() {
+        const CA: &str = "770eb9819a31821d9d2399e2f35e2433b72637e393d71ecc9b8d0250f49153c3";
+        const OWNER: &str =
+            "kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya";
+        let op = |op| TokenTransactionBuilder::new(Protocol::Krc20, op, "");
+
+        let deploy = op(Op::Deploy).mode(Mode::Issue).name("SPARKL").max(1000);
+        assert_eq!(
+            deploy.build().validate(),
+            Err(ValidationError::MissingField {
+                op: Op::Deploy,
+                field: "to"
+            })
+        );
+        let deploy = op(Op::Deploy).mode(Mode::Issue).name("SPARKL").max(1000);
+        assert_eq!(deploy.to(OWNER).build().validate(), Ok(()));
+
+        assert_eq!(op(Op::Issue).ca(CA).amount(10).build().validate(), Ok(()));
+        assert_eq!(
+            op(Op::Issue).ca("SPARKL").amount(10).build().validate(),
+            Err(ValidationError::ContractAddress("SPARKL".to_string()))
+        );
+        assert_eq!(op(Op::Chown).ca(CA).to(OWNER).build().validate(), Ok(()));
+        assert_eq!(
+            op(Op::Blacklist).ca(CA).to(OWNER).build().validate(),
+            Err(ValidationError::MissingField {
+                op: Op::Blacklist,
+                field: "mod"
+            })
+        );
+        assert_eq!(
+            op(Op::Blacklist)
+                .ca(CA)
+                .mode(Mode::Issue)
+                .to(OWNER)
+                .build()
+                .validate(),
+            Err(ValidationError::Mode {
+                op: Op::Blacklist,
+                mode: Mode::Issue
+            })
+        );
+        assert_eq!(op(Op::Burn).ca(CA).amount(5).build().validate(), Ok(()));
+        assert_eq!(
+            op(Op::Mint).ca(CA).build().validate(),
+            Err(ValidationError::IssueMode(CA.to_string()))
+        );
+    }
+}
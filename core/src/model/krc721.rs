@@ -0,0 +1,129 @@
+//!
+//! KRC-721 (non-fungible token) operations.
+//!
+//! KRC-721 operations are JSON-encoded under the `kspr` envelope namespace:
+//!
+//! ```json
+//! { "p": "krc-721", "op": "deploy", "tick": "KSPRBOT", "max": "1000", "buri": "ipfs://..." }
+//! { "p": "krc-721", "op": "mint", "tick": "KSPRBOT", "to": "kaspa:..." }
+//! { "p": "krc-721", "op": "transfer", "tick": "KSPRBOT", "tid": "42", "to": "kaspa:..." }
+//! ```
+//!
+
+use crate::envelope::{Envelope, CONTENT_TYPE_JSON};
+use crate::imports::*;
+use crate::model::registry::{contains_header, ProtocolHandler, ProtocolOperation};
+use std::fmt;
+
+/// Identifier of the KRC-721 protocol in the registry.
+pub const PROTOCOL_ID_KRC721: &str = "krc-721";
+/// Envelope namespace of KRC-721 inscriptions.
+pub const PROTOCOL_NAMESPACE_KRC721: &str = "kspr";
+/// Operations of the KRC-721 protocol.
+pub const KRC721_OPS: &[&str] = &["deploy", "mint", "transfer"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Op {
+    Deploy,
+    Mint,
+    Transfer,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Deploy => write!(f, "deploy"),
+            Op::Mint => write!(f, "mint"),
+            Op::Transfer => write!(f, "transfer"),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "deploy" => Ok(Op::Deploy),
+            "mint" => Ok(Op::Mint),
+            "transfer" => Ok(Op::Transfer),
+            _ => Err(Error::Custom(format!("Invalid KRC-721 op: {}", s))),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NftTransaction {
+    #[serde(rename = "p")]
+    pub protocol: String,
+
+    #[serde_as(as = "DisplayFromStr")]
+    pub op: Op,
+
+    pub tick: String,
+
+    /// Maximum supply of a `deploy`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+
+    /// Base URI of the collection metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buri: Option<String>,
+
+    /// Token id of a `transfer`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tid: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+/// KRC-721 operations, JSON-encoded under the `kspr` namespace.
+pub struct Krc721Handler;
+
+impl ProtocolHandler for Krc721Handler {
+    fn id(&self) -> &'static str {
+        PROTOCOL_ID_KRC721
+    }
+
+    fn namespace(&self) -> &'static str {
+        PROTOCOL_NAMESPACE_KRC721
+    }
+
+    fn ops(&self) -> &'static [&'static str] {
+        KRC721_OPS
+    }
+
+    fn detect(&self, envelope: &Envelope) -> bool {
+        envelope.has_namespace(PROTOCOL_NAMESPACE_KRC721)
+            && envelope.content_type() == Ok(CONTENT_TYPE_JSON)
+            && contains_header(&envelope.payload, PROTOCOL_ID_KRC721.as_bytes())
+    }
+
+    fn decode(&self, envelope: &Envelope) -> Result<Box<dyn ProtocolOperation>> {
+        let transaction = serde_json::from_slice::<NftTransaction>(&envelope.payload)?;
+        if !transaction
+            .protocol
+            .eq_ignore_ascii_case(PROTOCOL_ID_KRC721)
+        {
+            return Err(Error::Custom(format!(
+                "Invalid protocol: {}",
+                transaction.protocol
+            )));
+        }
+        Ok(Box::new(transaction))
+    }
+}
+
+impl ProtocolOperation for NftTransaction {
+    fn protocol(&self) -> &'static str {
+        PROTOCOL_ID_KRC721
+    }
+
+    fn op(&self) -> String {
+        self.op.to_string()
+    }
+}
@@ -1 +1,7 @@
+pub mod amount;
+pub use amount::{AmountError, TokenAmount};
 pub mod kasplex;
+pub mod krc721;
+pub mod registry;
+pub mod sparkle;
+pub use registry::{ProtocolHandler, ProtocolOperation, ProtocolRegistry};
@@ -0,0 +1,192 @@
+//!
+//! Inscription protocol registry.
+//!
+//! Each protocol indexed by sparkle (KRC-20, KRC-721, sparkle) implements
+//! [`ProtocolHandler`], declaring the envelope namespace it is inscribed
+//! under, its operations and how to decode its payload. The analyzer
+//! dispatches revealed envelopes through a [`ProtocolRegistry`] so that new
+//! protocols can be indexed by registering a handler.
+//!
+
+use crate::envelope::Envelope;
+use crate::imports::*;
+use std::fmt;
+
+/// Operation decoded from an inscription payload, downcast to the concrete
+/// type of its protocol (e.g. [`TokenTransaction`](super::kasplex::v1::krc20::TokenTransaction)).
+pub trait ProtocolOperation: DowncastSync + fmt::Debug {
+    /// Identifier of the protocol, see [`ProtocolHandler::id`].
+    fn protocol(&self) -> &'static str;
+    /// Operation name (e.g. `mint`).
+    fn op(&self) -> String;
+}
+
+impl_downcast!(sync ProtocolOperation);
+
+pub trait ProtocolHandler: Send + Sync {
+    /// Unique protocol identifier (e.g. `krc-20`).
+    fn id(&self) -> &'static str;
+
+    /// Envelope namespace the protocol is inscribed under (e.g. `kasplex`).
+    fn namespace(&self) -> &'static str;
+
+    /// Operations supported by the protocol.
+    fn ops(&self) -> &'static [&'static str];
+
+    /// Returns `true` if `envelope` is likely to carry an operation of this
+    /// protocol. The default checks the namespace only.
+    fn detect(&self, envelope: &Envelope) -> bool {
+        envelope.has_namespace(self.namespace())
+    }
+
+    /// Decodes the operation carried by `envelope`.
+    fn decode(&self, envelope: &Envelope) -> Result<Box<dyn ProtocolOperation>>;
+}
+
+/// Set of protocol handlers, looked up by id or matched against envelopes
+/// in registration order.
+#[derive(Clone)]
+pub struct ProtocolRegistry {
+    handlers: Vec<Arc<dyn ProtocolHandler>>,
+}
+
+impl ProtocolRegistry {
+    /// Creates a registry without any protocol.
+    pub fn empty() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Registers `handler`, failing if a protocol with the same id is
+    /// already registered.
+    pub fn register(&mut self, handler: Arc<dyn ProtocolHandler>) -> Result<()> {
+        if self.get(handler.id()).is_some() {
+            return Err(Error::custom(format!(
+                "Protocol `{}` is already registered",
+                handler.id()
+            )));
+        }
+        self.handlers.push(handler);
+        Ok(())
+    }
+
+    /// Returns the handler of the protocol `id` (case-insensitive).
+    pub fn get(&self, id: &str) -> Option<&Arc<dyn ProtocolHandler>> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.id().eq_ignore_ascii_case(id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn ProtocolHandler>> {
+        self.handlers.iter()
+    }
+
+    /// Returns the first handler detecting `envelope`.
+    pub fn detect(&self, envelope: &Envelope) -> Option<&Arc<dyn ProtocolHandler>> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.detect(envelope))
+    }
+
+    /// Decodes `envelope` with the first handler detecting it; `None` if no
+    /// registered protocol matches.
+    pub fn decode(&self, envelope: &Envelope) -> Option<Result<Box<dyn ProtocolOperation>>> {
+        self.detect(envelope)
+            .map(|handler| handler.decode(envelope))
+    }
+}
+
+/// Registry of the protocols supported by sparkle.
+impl Default for ProtocolRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for handler in [
+            Arc::new(super::kasplex::v1::Krc20Handler) as Arc<dyn ProtocolHandler>,
+            Arc::new(super::krc721::Krc721Handler),
+            Arc::new(super::sparkle::SparkleHandler),
+        ] {
+            registry.register(handler).unwrap();
+        }
+        registry
+    }
+}
+
+/// Case-insensitive search of a protocol header (e.g. `"p":"krc-20"`
+/// values) in an inscription payload.
+pub fn contains_header(payload: &[u8], header: &[u8]) -> bool {
+    !header.is_empty()
+        && payload
+            .windows(header.len())
+            .any(|window| window.eq_ignore_ascii_case(header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::CONTENT_TYPE_JSON;
+    use crate::model::kasplex::v1::krc20::{Op, TokenTransaction};
+    use crate::model::kasplex::v1::Tick;
+    use crate::model::krc721::{self, NftTransaction};
+    use crate::model::sparkle::SparkleTransaction;
+
+    #[test]
+    fn test_registry_dispatch() {
+        let registry = ProtocolRegistry::default();
+        assert_eq!(registry.iter().count(), 3);
+        assert_eq!(registry.get("KRC-20").unwrap().namespace(), "kasplex");
+        assert!(registry.get("krc-721").unwrap().ops().contains(&"mint"));
+
+        let envelope = Envelope::new(
+            b"kasplex",
+            &[],
+            br#"{"p":"KRC-20","op":"mint","tick":"TOITOI"}"#,
+        );
+        let operation = registry.decode(&envelope).unwrap().unwrap();
+        assert_eq!(operation.protocol(), "krc-20");
+        assert_eq!(operation.op(), "mint");
+        let transaction = operation.downcast_ref::<TokenTransaction>().unwrap();
+        assert_eq!(transaction.op, Op::Mint);
+
+        let envelope = Envelope::with_content_type(
+            b"kspr",
+            CONTENT_TYPE_JSON,
+            br#"{"p":"krc-721","op":"mint","tick":"KSPRBOT"}"#,
+        );
+        let operation = registry.decode(&envelope).unwrap().unwrap();
+        assert_eq!(operation.protocol(), "krc-721");
+        let transaction = operation.downcast_ref::<NftTransaction>().unwrap();
+        assert_eq!(transaction.op, krc721::Op::Mint);
+
+        let mint = SparkleTransaction::mint(&Tick::from_str("SPARKL").unwrap(), 1_000);
+        let operation = registry
+            .decode(&mint.to_envelope().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (operation.protocol(), operation.op()),
+            ("sparkle", "mint".to_string())
+        );
+        assert_eq!(operation.downcast_ref::<SparkleTransaction>(), Some(&mint));
+
+        // kasplex namespace without a KRC-20 payload
+        let envelope = Envelope::new(b"kasplex", &[], br#"{"p":"brc-20"}"#);
+        assert!(registry.decode(&envelope).is_none());
+
+        let mut registry = ProtocolRegistry::empty();
+        registry
+            .register(Arc::new(crate::model::kasplex::v1::Krc20Handler))
+            .unwrap();
+        assert!(registry
+            .register(Arc::new(crate::model::kasplex::v1::Krc20Handler))
+            .is_err());
+    }
+
+    #[test]
+    fn test_contains_header() {
+        assert!(contains_header(br#"{"p":"KRC-20"}"#, b"krc-20"));
+        assert!(contains_header(br#"{"p":"krc-20"}"#, b"KRC-20"));
+        assert!(!contains_header(br#"{"p":"krc-721"}"#, b"krc-20"));
+        assert!(!contains_header(b"", b"krc-20"));
+    }
+}
@@ -0,0 +1,16 @@
+//!
+//! Sparkle-native token protocol.
+//!
+//! Operations are borsh-encoded ([`SparkleTransaction`]) and inscribed under
+//! the `sparkle` envelope namespace with the `application/borsh` content
+//! type, yielding payloads smaller than the equivalent kasplex JSON and
+//! therefore lighter reveal transactions. [`SparkleState`] replays the
+//! inscribed operations into token supplies and holder balances.
+//!
+
+pub mod model;
+pub use model::*;
+pub mod protocol;
+pub use protocol::*;
+pub mod state;
+pub use state::*;
@@ -0,0 +1,374 @@
+use crate::envelope::Envelope;
+use crate::imports::*;
+use crate::model::amount::MAX_DECIMALS;
+use crate::model::kasplex::v1::{Tick, ValidationError};
+use kaspa_addresses::{Address, Prefix};
+use kaspa_consensus_core::tx::ScriptPublicKey;
+use kaspa_txscript::{extract_script_pub_key_address, pay_to_address_script};
+use std::fmt;
+use thiserror::Error;
+
+/// Envelope namespace of sparkle inscriptions.
+pub const SPARKLE_NAMESPACE: &str = "sparkle";
+/// Current version of the payload schema.
+pub const SPARKLE_PROTOCOL_VERSION: u16 = 1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SparkleError {
+    #[error("Unsupported sparkle protocol version {0}")]
+    Version(u16),
+
+    #[error(transparent)]
+    Tick(#[from] ValidationError),
+
+    #[error("Zero `{field}` for `{op}` operation")]
+    ZeroField {
+        op: &'static str,
+        field: &'static str,
+    },
+
+    #[error("Mint limit {limit} exceeds max supply {max}")]
+    LimitExceedsMax { limit: u128, max: u128 },
+
+    #[error("Invalid decimals {0}, expecting at most {MAX_DECIMALS}")]
+    Decimals(u8),
+
+    #[error("Invalid recipient script")]
+    Recipient,
+
+    #[error("Token `{0}` already exists")]
+    TickExists(String),
+
+    #[error("Token `{0}` not found")]
+    TickNotFound(String),
+
+    #[error("Mint amount {amount} exceeds mint limit {limit}")]
+    MintExceedsLimit { amount: u128, limit: u128 },
+
+    #[error("Token `{0}` is fully minted")]
+    MintFinished(String),
+
+    #[error("Insufficient balance {balance}, expecting {amount}")]
+    BalanceInsufficient { balance: u128, amount: u128 },
+
+    #[error("Metadata of `{0}` can only be updated by its creator")]
+    NotCreator(String),
+}
+
+pub type SparkleResult<T> = std::result::Result<T, SparkleError>;
+
+/// Descriptive token metadata; unset fields are left unchanged by
+/// [`Operation::UpdateMetadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Icon or document URI (e.g. `ipfs://...`).
+    pub uri: Option<String>,
+}
+
+/// Recipient of a transfer, encoded as its script public key.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Recipient {
+    pub version: u16,
+    pub script: Vec<u8>,
+}
+
+impl Recipient {
+    pub fn address(&self, prefix: Prefix) -> SparkleResult<Address> {
+        let script_public_key = ScriptPublicKey::from_vec(self.version, self.script.clone());
+        extract_script_pub_key_address(&script_public_key, prefix)
+            .map_err(|_| SparkleError::Recipient)
+    }
+}
+
+impl From<&Address> for Recipient {
+    fn from(address: &Address) -> Self {
+        let script_public_key = pay_to_address_script(address);
+        Self {
+            version: script_public_key.version(),
+            script: script_public_key.script().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Create {
+    pub tick: String,
+    pub max: u128,
+    pub limit: u128,
+    pub dec: u8,
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Mint {
+    pub tick: String,
+    /// Minted amount, at most the mint limit of the token.
+    pub amount: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Transfer {
+    pub tick: String,
+    pub amount: u128,
+    pub to: Recipient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct UpdateMetadata {
+    pub tick: String,
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Operation {
+    Create(Create),
+    Mint(Mint),
+    Transfer(Transfer),
+    UpdateMetadata(UpdateMetadata),
+}
+
+impl Operation {
+    pub fn tick(&self) -> &str {
+        match self {
+            Operation::Create(create) => &create.tick,
+            Operation::Mint(mint) => &mint.tick,
+            Operation::Transfer(transfer) => &transfer.tick,
+            Operation::UpdateMetadata(update) => &update.tick,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Create(_) => write!(f, "create"),
+            Operation::Mint(_) => write!(f, "mint"),
+            Operation::Transfer(_) => write!(f, "transfer"),
+            Operation::UpdateMetadata(_) => write!(f, "metadata"),
+        }
+    }
+}
+
+/// Versioned sparkle protocol payload.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct SparkleTransaction {
+    pub version: u16,
+    pub op: Operation,
+}
+
+impl SparkleTransaction {
+    pub fn new(op: Operation) -> Self {
+        Self {
+            version: SPARKLE_PROTOCOL_VERSION,
+            op,
+        }
+    }
+
+    pub fn create(tick: &Tick, max: u128, limit: u128, dec: u8) -> Self {
+        Self::new(Operation::Create(Create {
+            tick: tick.to_string(),
+            max,
+            limit,
+            dec,
+            metadata: Metadata::default(),
+        }))
+    }
+
+    pub fn mint(tick: &Tick, amount: u128) -> Self {
+        Self::new(Operation::Mint(Mint {
+            tick: tick.to_string(),
+            amount,
+        }))
+    }
+
+    pub fn transfer(tick: &Tick, amount: u128, to: &Address) -> Self {
+        Self::new(Operation::Transfer(Transfer {
+            tick: tick.to_string(),
+            amount,
+            to: to.into(),
+        }))
+    }
+
+    pub fn update_metadata(tick: &Tick, metadata: Metadata) -> Self {
+        Self::new(Operation::UpdateMetadata(UpdateMetadata {
+            tick: tick.to_string(),
+            metadata,
+        }))
+    }
+
+    /// Sets the initial metadata of a `create` operation.
+    pub fn with_metadata(self, metadata: Metadata) -> Self {
+        match self.op {
+            Operation::Create(create) => Self {
+                op: Operation::Create(Create { metadata, ..create }),
+                ..self
+            },
+            op => Self { op, ..self },
+        }
+    }
+
+    /// Returns the tick of the operation if valid.
+    pub fn tick(&self) -> SparkleResult<Tick> {
+        Ok(self.op.tick().parse()?)
+    }
+
+    /// Checks the operation against the protocol rules.
+    pub fn validate(&self) -> SparkleResult<()> {
+        if self.version != SPARKLE_PROTOCOL_VERSION {
+            return Err(SparkleError::Version(self.version));
+        }
+        self.tick()?;
+
+        match &self.op {
+            Operation::Create(create) => {
+                nonzero("create", "max", create.max)?;
+                nonzero("create", "limit", create.limit)?;
+                if create.limit > create.max {
+                    return Err(SparkleError::LimitExceedsMax {
+                        limit: create.limit,
+                        max: create.max,
+                    });
+                }
+                if create.dec > MAX_DECIMALS {
+                    return Err(SparkleError::Decimals(create.dec));
+                }
+            }
+            Operation::Mint(mint) => nonzero("mint", "amount", mint.amount)?,
+            Operation::Transfer(transfer) => {
+                nonzero("transfer", "amount", transfer.amount)?;
+                // the prefix does not affect the script check
+                transfer.to.address(Prefix::Mainnet)?;
+            }
+            Operation::UpdateMetadata(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Borsh-encodes the operation in a `sparkle` envelope.
+    pub fn to_envelope(&self) -> Result<Envelope> {
+        Ok(Envelope::with_borsh(SPARKLE_NAMESPACE.as_bytes(), self)?)
+    }
+
+    /// Decodes and validates the operation carried by `envelope`.
+    pub fn try_from_envelope(envelope: &Envelope) -> Result<Self> {
+        if !envelope.has_namespace(SPARKLE_NAMESPACE) {
+            return Err(Error::custom(format!(
+                "Invalid namespace `{}`",
+                String::from_utf8_lossy(&envelope.namespace)
+            )));
+        }
+        let transaction = envelope.try_borsh::<Self>()?;
+        transaction.validate()?;
+        Ok(transaction)
+    }
+}
+
+fn nonzero(op: &'static str, field: &'static str, value: u128) -> SparkleResult<()> {
+    if value == 0 {
+        Err(SparkleError::ZeroField { op, field })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::kasplex::v1::krc20::{Op, TokenTransactionBuilder};
+    use crate::model::kasplex::v1::Protocol;
+
+    fn tick() -> Tick {
+        Tick::from_str("SPARKL").unwrap()
+    }
+
+    fn recipient() -> Address {
+        Address::try_from("kaspatest:qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhqrxplya")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sparkle_envelope_round_trip() {
+        let transactions = [
+            SparkleTransaction::create(&tick(), 21_000_000, 1_000, 8).with_metadata(Metadata {
+                name: Some("Sparkle".to_string()),
+                ..Default::default()
+            }),
+            SparkleTransaction::mint(&tick(), 1_000),
+            SparkleTransaction::transfer(&tick(), 500, &recipient()),
+            SparkleTransaction::update_metadata(
+                &tick(),
+                Metadata {
+                    uri: Some("ipfs://sparkle".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for transaction in transactions {
+            let envelope = transaction.to_envelope().unwrap();
+            assert!(envelope.has_namespace(SPARKLE_NAMESPACE));
+            assert_eq!(
+                SparkleTransaction::try_from_envelope(&envelope).unwrap(),
+                transaction
+            );
+        }
+
+        let transfer = SparkleTransaction::transfer(&tick(), 500, &recipient());
+        let Operation::Transfer(Transfer { to, .. }) = &transfer.op else {
+            panic!("expecting a transfer");
+        };
+        assert_eq!(to.address(Prefix::Testnet).unwrap(), recipient());
+    }
+
+    #[test]
+    fn test_sparkle_payload_size() {
+        let sparkle =
+            borsh::to_vec(&SparkleTransaction::transfer(&tick(), 500, &recipient())).unwrap();
+        let kasplex = serde_json::to_vec(
+            &TokenTransactionBuilder::new(Protocol::Krc20, Op::Transfer, tick())
+                .amount(500)
+                .to(recipient())
+                .build(),
+        )
+        .unwrap();
+        assert!(sparkle.len() < kasplex.len());
+    }
+
+    #[test]
+    fn test_sparkle_validate() {
+        assert_eq!(
+            SparkleTransaction::create(&tick(), 10, 1_000, 8).validate(),
+            Err(SparkleError::LimitExceedsMax {
+                limit: 1_000,
+                max: 10
+            })
+        );
+        assert_eq!(
+            SparkleTransaction::create(&tick(), 1_000, 10, 19).validate(),
+            Err(SparkleError::Decimals(19))
+        );
+        assert_eq!(
+            SparkleTransaction::mint(&tick(), 0).validate(),
+            Err(SparkleError::ZeroField {
+                op: "mint",
+                field: "amount"
+            })
+        );
+
+        let mut mint = SparkleTransaction::mint(&tick(), 1);
+        mint.version = 2;
+        assert_eq!(mint.validate(), Err(SparkleError::Version(2)));
+
+        let mint = SparkleTransaction::new(Operation::Mint(Mint {
+            tick: "SPARKLES".to_string(),
+            amount: 1,
+        }));
+        assert_eq!(
+            mint.validate(),
+            Err(SparkleError::Tick(ValidationError::TickLength(8)))
+        );
+    }
+}
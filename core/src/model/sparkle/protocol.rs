@@ -0,0 +1,45 @@
+use super::{SparkleTransaction, SPARKLE_NAMESPACE};
+use crate::envelope::{Envelope, CONTENT_TYPE_BORSH};
+use crate::imports::*;
+use crate::model::registry::{ProtocolHandler, ProtocolOperation};
+
+/// Identifier of the sparkle protocol in the registry.
+pub const PROTOCOL_ID_SPARKLE: &str = "sparkle";
+/// Operations of the sparkle protocol.
+pub const SPARKLE_OPS: &[&str] = &["create", "mint", "transfer", "metadata"];
+
+/// Sparkle operations, borsh-encoded under the `sparkle` namespace.
+pub struct SparkleHandler;
+
+impl ProtocolHandler for SparkleHandler {
+    fn id(&self) -> &'static str {
+        PROTOCOL_ID_SPARKLE
+    }
+
+    fn namespace(&self) -> &'static str {
+        SPARKLE_NAMESPACE
+    }
+
+    fn ops(&self) -> &'static [&'static str] {
+        SPARKLE_OPS
+    }
+
+    fn detect(&self, envelope: &Envelope) -> bool {
+        envelope.has_namespace(SPARKLE_NAMESPACE)
+            && envelope.content_type() == Ok(CONTENT_TYPE_BORSH)
+    }
+
+    fn decode(&self, envelope: &Envelope) -> Result<Box<dyn ProtocolOperation>> {
+        Ok(Box::new(SparkleTransaction::try_from_envelope(envelope)?))
+    }
+}
+
+impl ProtocolOperation for SparkleTransaction {
+    fn protocol(&self) -> &'static str {
+        PROTOCOL_ID_SPARKLE
+    }
+
+    fn op(&self) -> String {
+        self.op.to_string()
+    }
+}
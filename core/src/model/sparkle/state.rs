@@ -0,0 +1,289 @@
+//!
+//! Sparkle token state.
+//!
+//! Replays the sparkle operations inscribed on-chain, in the order they are
+//! received, into token supplies and holder balances. Operations are
+//! attributed to the P2PK address of the key revealing the inscription:
+//! `create` registers the token with that address as its creator, `mint`
+//! credits it (the last mint receives the remaining supply), `transfer`
+//! debits it and only the creator may update the metadata. Operations reorged
+//! out of the selected chain are not rolled back.
+//!
+
+use super::{Metadata, Operation, SparkleError, SparkleResult, SparkleTransaction};
+use crate::model::kasplex::v1::Tick;
+use kaspa_addresses::Address;
+use std::collections::BTreeMap;
+
+/// Token created by a sparkle `create` operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparkleToken {
+    pub tick: Tick,
+    pub max: u128,
+    pub limit: u128,
+    pub dec: u8,
+    pub metadata: Metadata,
+    pub creator: Address,
+    pub minted: u128,
+    /// DAA score of the `create` operation.
+    pub daa_score: u64,
+    holders: BTreeMap<String, u128>,
+}
+
+impl SparkleToken {
+    pub fn balance(&self, address: &Address) -> u128 {
+        self.holders
+            .get(&address.to_string())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Holders with a positive balance.
+    pub fn holders(&self) -> impl Iterator<Item = (&str, u128)> {
+        self.holders
+            .iter()
+            .filter(|(_, amount)| **amount > 0)
+            .map(|(address, amount)| (address.as_str(), *amount))
+    }
+
+    fn credit(&mut self, address: &Address, amount: u128) {
+        *self.holders.entry(address.to_string()).or_default() += amount;
+    }
+
+    fn debit(&mut self, address: &Address, amount: u128) -> SparkleResult<()> {
+        match self.holders.get_mut(&address.to_string()) {
+            Some(balance) if *balance >= amount => {
+                *balance -= amount;
+                Ok(())
+            }
+            balance => Err(SparkleError::BalanceInsufficient {
+                balance: balance.map_or(0, |balance| *balance),
+                amount,
+            }),
+        }
+    }
+}
+
+/// Sparkle token state built from the inscribed operations.
+#[derive(Debug, Clone, Default)]
+pub struct SparkleState {
+    tokens: BTreeMap<Tick, SparkleToken>,
+    daa_score: u64,
+}
+
+impl SparkleState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// DAA score of the last applied operation.
+    pub fn daa_score(&self) -> u64 {
+        self.daa_score
+    }
+
+    pub fn token(&self, tick: &Tick) -> Option<&SparkleToken> {
+        self.tokens.get(tick)
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &SparkleToken> {
+        self.tokens.values()
+    }
+
+    pub fn balance(&self, address: &Address, tick: &Tick) -> u128 {
+        self.token(tick)
+            .map(|token| token.balance(address))
+            .unwrap_or_default()
+    }
+
+    /// Applies `transaction` revealed by `from` at `daa_score`, leaving the
+    /// state unchanged if the operation is rejected.
+    pub fn apply(
+        &mut self,
+        transaction: &SparkleTransaction,
+        from: &Address,
+        daa_score: u64,
+    ) -> SparkleResult<()> {
+        transaction.validate()?;
+        let tick = transaction.tick()?;
+
+        match &transaction.op {
+            Operation::Create(create) => {
+                if self.tokens.contains_key(&tick) {
+                    return Err(SparkleError::TickExists(tick.to_string()));
+                }
+                self.tokens.insert(
+                    tick,
+                    SparkleToken {
+                        tick,
+                        max: create.max,
+                        limit: create.limit,
+                        dec: create.dec,
+                        metadata: create.metadata.clone(),
+                        creator: from.clone(),
+                        minted: 0,
+                        daa_score,
+                        holders: BTreeMap::new(),
+                    },
+                );
+            }
+            Operation::Mint(mint) => {
+                let token = self.token_mut(&tick)?;
+                if mint.amount > token.limit {
+                    return Err(SparkleError::MintExceedsLimit {
+                        amount: mint.amount,
+                        limit: token.limit,
+                    });
+                }
+                let remaining = token.max - token.minted;
+                if remaining == 0 {
+                    return Err(SparkleError::MintFinished(tick.to_string()));
+                }
+                let amount = mint.amount.min(remaining);
+                token.minted += amount;
+                token.credit(from, amount);
+            }
+            Operation::Transfer(transfer) => {
+                let to = transfer.to.address(from.prefix)?;
+                let token = self.token_mut(&tick)?;
+                token.debit(from, transfer.amount)?;
+                token.credit(&to, transfer.amount);
+            }
+            Operation::UpdateMetadata(update) => {
+                let token = self.token_mut(&tick)?;
+                if token.creator != *from {
+                    return Err(SparkleError::NotCreator(tick.to_string()));
+                }
+                let Metadata {
+                    name,
+                    description,
+                    uri,
+                } = update.metadata.clone();
+                token.metadata = Metadata {
+                    name: name.or(token.metadata.name.take()),
+                    description: description.or(token.metadata.description.take()),
+                    uri: uri.or(token.metadata.uri.take()),
+                };
+            }
+        }
+
+        self.daa_score = self.daa_score.max(daa_score);
+        Ok(())
+    }
+
+    fn token_mut(&mut self, tick: &Tick) -> SparkleResult<&mut SparkleToken> {
+        self.tokens
+            .get_mut(tick)
+            .ok_or_else(|| SparkleError::TickNotFound(tick.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn tick() -> Tick {
+        Tick::from_str("SPARKL").unwrap()
+    }
+
+    fn address(byte: u8) -> Address {
+        Address::new(
+            kaspa_addresses::Prefix::Testnet,
+            kaspa_addresses::Version::PubKey,
+            &[byte; 32],
+        )
+    }
+
+    #[test]
+    fn test_sparkle_state() {
+        let (alice, bob) = (address(1), address(2));
+        let mut state = SparkleState::new();
+
+        assert_eq!(
+            state.apply(&SparkleTransaction::mint(&tick(), 100), &alice, 1),
+            Err(SparkleError::TickNotFound(tick().to_string()))
+        );
+        state
+            .apply(&SparkleTransaction::create(&tick(), 250, 100, 8), &alice, 2)
+            .unwrap();
+        assert_eq!(
+            state.apply(&SparkleTransaction::create(&tick(), 1_000, 100, 8), &bob, 3),
+            Err(SparkleError::TickExists(tick().to_string()))
+        );
+
+        assert_eq!(
+            state.apply(&SparkleTransaction::mint(&tick(), 101), &alice, 4),
+            Err(SparkleError::MintExceedsLimit {
+                amount: 101,
+                limit: 100
+            })
+        );
+        state
+            .apply(&SparkleTransaction::mint(&tick(), 100), &alice, 5)
+            .unwrap();
+        state
+            .apply(&SparkleTransaction::mint(&tick(), 100), &bob, 6)
+            .unwrap();
+        // the last mint receives the remaining supply
+        state
+            .apply(&SparkleTransaction::mint(&tick(), 100), &bob, 7)
+            .unwrap();
+        assert_eq!(
+            state.apply(&SparkleTransaction::mint(&tick(), 100), &alice, 8),
+            Err(SparkleError::MintFinished(tick().to_string()))
+        );
+        assert_eq!(state.token(&tick()).unwrap().minted, 250);
+        assert_eq!(state.balance(&bob, &tick()), 150);
+
+        assert_eq!(
+            state.apply(&SparkleTransaction::transfer(&tick(), 101, &bob), &alice, 9),
+            Err(SparkleError::BalanceInsufficient {
+                balance: 100,
+                amount: 101
+            })
+        );
+        state
+            .apply(&SparkleTransaction::transfer(&tick(), 40, &bob), &alice, 10)
+            .unwrap();
+        assert_eq!(state.balance(&alice, &tick()), 60);
+        assert_eq!(state.balance(&bob, &tick()), 190);
+
+        let uri = Metadata {
+            uri: Some("ipfs://sparkle".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            state.apply(
+                &SparkleTransaction::update_metadata(&tick(), uri.clone()),
+                &bob,
+                11
+            ),
+            Err(SparkleError::NotCreator(tick().to_string()))
+        );
+        state
+            .apply(
+                &SparkleTransaction::create(&Tick::from_str("OTHER").unwrap(), 10, 10, 0)
+                    .with_metadata(Metadata {
+                        name: Some("Other".to_string()),
+                        ..Default::default()
+                    }),
+                &bob,
+                12,
+            )
+            .unwrap();
+        state
+            .apply(
+                &SparkleTransaction::update_metadata(&Tick::from_str("OTHER").unwrap(), uri),
+                &bob,
+                13,
+            )
+            .unwrap();
+        let other = state.token(&Tick::from_str("OTHER").unwrap()).unwrap();
+        assert_eq!(other.metadata.name.as_deref(), Some("Other"));
+        assert_eq!(other.metadata.uri.as_deref(), Some("ipfs://sparkle"));
+
+        let token = state.token(&tick()).unwrap();
+        assert_eq!(token.holders().count(), 2);
+        assert_eq!(state.daa_score(), 13);
+    }
+}
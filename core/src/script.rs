@@ -0,0 +1,332 @@
+//!
+//! Kaspa script disassembler and inscription inspector.
+//!
+
+use crate::envelope::{self, Envelope};
+use kaspa_consensus_core::tx::PopulatedTransaction;
+use kaspa_txscript::opcodes::codes::*;
+use kaspa_txscript::opcodes::deserialize_next_opcode;
+use kaspa_txscript_errors::TxScriptError;
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Single decoded script instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// Byte offset of the opcode within the script.
+    pub offset: usize,
+    pub opcode: u8,
+    /// Data pushed by the opcode (empty for non-push opcodes).
+    pub data: Vec<u8>,
+}
+
+impl Instruction {
+    pub fn name(&self) -> Cow<'static, str> {
+        opcode_name(self.opcode)
+    }
+
+    /// Returns `true` for opcodes carrying inline data (`OpData*`, `OpPushData*`).
+    pub fn has_data(&self) -> bool {
+        (OpData1..=OpPushData4).contains(&self.opcode)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}  {}", self.offset, self.name())?;
+        if self.has_data() {
+            write!(f, " [{}] {}", self.data.len(), hex(&self.data))?;
+            if let Some(text) = printable(&self.data) {
+                write!(f, " \"{text}\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disassembled script. Decoding stops at the first malformed opcode,
+/// the instructions preceding it are retained.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub instructions: Vec<Instruction>,
+    pub error: Option<(usize, TxScriptError)>,
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for instruction in self.instructions.iter() {
+            writeln!(f, "{instruction}")?;
+        }
+        if let Some((offset, err)) = self.error.as_ref() {
+            writeln!(f, "{offset:04x}  <malformed: {err}>")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes `script` into a list of instructions.
+pub fn disassemble(script: &[u8]) -> Disassembly {
+    let mut iter = script.iter();
+    let mut instructions = vec![];
+    let mut error = None;
+    loop {
+        let offset = script.len() - iter.as_slice().len();
+        match deserialize_next_opcode::<_, PopulatedTransaction<'static>>(&mut iter) {
+            Some(Ok(opcode)) => {
+                instructions.push(Instruction {
+                    offset,
+                    opcode: opcode.value(),
+                    data: opcode.get_data().to_vec(),
+                });
+            }
+            Some(Err(err)) => {
+                error = Some((offset, err));
+                break;
+            }
+            None => break,
+        }
+    }
+
+    Disassembly {
+        instructions,
+        error,
+    }
+}
+
+/// Produces a human-readable description of `script`. If the script is a
+/// signature script revealing an inscription (or the redeem script itself),
+/// the redeem script is disassembled as well and the envelope fields decoded.
+pub fn inspect(script: &[u8]) -> String {
+    let mut out = String::new();
+    let disassembly = disassemble(script);
+    write!(out, "{disassembly}").unwrap();
+
+    let redeem_script = match envelope::decode_redeem_script(script) {
+        Ok(redeem_script) => Ok(redeem_script),
+        Err(_) => {
+            // last push of the signature script
+            let redeem_script = disassembly
+                .instructions
+                .last()
+                .filter(|instruction| instruction.has_data())
+                .map(|instruction| instruction.data.as_slice());
+            if let Some(redeem_script) = redeem_script {
+                writeln!(out, "\nRedeem script:").unwrap();
+                for line in disassemble(redeem_script).to_string().lines() {
+                    writeln!(out, "    {line}").unwrap();
+                }
+            }
+            envelope::decode_signature_script(script)
+        }
+    };
+
+    match redeem_script {
+        Ok(redeem_script) => {
+            writeln!(out, "\nPublic key: {}", hex(&redeem_script.pubkey)).unwrap();
+            write_envelope(&mut out, &redeem_script.envelope);
+        }
+        Err(envelope::Error::NotFound) => {}
+        Err(err) => {
+            writeln!(out, "\nEnvelope error: {err}").unwrap();
+        }
+    }
+
+    out
+}
+
+fn write_envelope(out: &mut String, envelope: &Envelope) {
+    writeln!(out, "Envelope:").unwrap();
+    for (field, data) in [
+        ("namespace", &envelope.namespace),
+        ("metadata", &envelope.metadata),
+        ("payload", &envelope.payload),
+    ] {
+        match std::str::from_utf8(data) {
+            Ok(text) if printable(data).is_some() || data.is_empty() => {
+                writeln!(out, "    {field} [{}] {text}", data.len()).unwrap()
+            }
+            _ => writeln!(out, "    {field} [{}] {}", data.len(), hex(data)).unwrap(),
+        }
+    }
+}
+
+/// Returns the name of `opcode` as used by the rusty-kaspa `codes` module.
+pub fn opcode_name(opcode: u8) -> Cow<'static, str> {
+    let name = match opcode {
+        OpFalse => "OpFalse",
+        OpData1..=OpData75 => return format!("OpData{opcode}").into(),
+        OpPushData1 => "OpPushData1",
+        OpPushData2 => "OpPushData2",
+        OpPushData4 => "OpPushData4",
+        Op1Negate => "Op1Negate",
+        OpReserved => "OpReserved",
+        OpTrue => "OpTrue",
+        Op2..=Op16 => return format!("Op{}", opcode - OpTrue + 1).into(),
+        OpNop => "OpNop",
+        OpVer => "OpVer",
+        OpIf => "OpIf",
+        OpNotIf => "OpNotIf",
+        OpVerIf => "OpVerIf",
+        OpVerNotIf => "OpVerNotIf",
+        OpElse => "OpElse",
+        OpEndIf => "OpEndIf",
+        OpVerify => "OpVerify",
+        OpReturn => "OpReturn",
+        OpToAltStack => "OpToAltStack",
+        OpFromAltStack => "OpFromAltStack",
+        Op2Drop => "Op2Drop",
+        Op2Dup => "Op2Dup",
+        Op3Dup => "Op3Dup",
+        Op2Over => "Op2Over",
+        Op2Rot => "Op2Rot",
+        Op2Swap => "Op2Swap",
+        OpIfDup => "OpIfDup",
+        OpDepth => "OpDepth",
+        OpDrop => "OpDrop",
+        OpDup => "OpDup",
+        OpNip => "OpNip",
+        OpOver => "OpOver",
+        OpPick => "OpPick",
+        OpRoll => "OpRoll",
+        OpRot => "OpRot",
+        OpSwap => "OpSwap",
+        OpTuck => "OpTuck",
+        OpCat => "OpCat",
+        OpSubStr => "OpSubStr",
+        OpLeft => "OpLeft",
+        OpRight => "OpRight",
+        OpSize => "OpSize",
+        OpInvert => "OpInvert",
+        OpAnd => "OpAnd",
+        OpOr => "OpOr",
+        OpXor => "OpXor",
+        OpEqual => "OpEqual",
+        OpEqualVerify => "OpEqualVerify",
+        OpReserved1 => "OpReserved1",
+        OpReserved2 => "OpReserved2",
+        Op1Add => "Op1Add",
+        Op1Sub => "Op1Sub",
+        Op2Mul => "Op2Mul",
+        Op2Div => "Op2Div",
+        OpNegate => "OpNegate",
+        OpAbs => "OpAbs",
+        OpNot => "OpNot",
+        Op0NotEqual => "Op0NotEqual",
+        OpAdd => "OpAdd",
+        OpSub => "OpSub",
+        OpMul => "OpMul",
+        OpDiv => "OpDiv",
+        OpMod => "OpMod",
+        OpLShift => "OpLShift",
+        OpRShift => "OpRShift",
+        OpBoolAnd => "OpBoolAnd",
+        OpBoolOr => "OpBoolOr",
+        OpNumEqual => "OpNumEqual",
+        OpNumEqualVerify => "OpNumEqualVerify",
+        OpNumNotEqual => "OpNumNotEqual",
+        OpLessThan => "OpLessThan",
+        OpGreaterThan => "OpGreaterThan",
+        OpLessThanOrEqual => "OpLessThanOrEqual",
+        OpGreaterThanOrEqual => "OpGreaterThanOrEqual",
+        OpMin => "OpMin",
+        OpMax => "OpMax",
+        OpWithin => "OpWithin",
+        OpSHA256 => "OpSHA256",
+        OpCheckMultiSigECDSA => "OpCheckMultiSigECDSA",
+        OpBlake2b => "OpBlake2b",
+        OpCheckSigECDSA => "OpCheckSigECDSA",
+        OpCheckSig => "OpCheckSig",
+        OpCheckSigVerify => "OpCheckSigVerify",
+        OpCheckMultiSig => "OpCheckMultiSig",
+        OpCheckMultiSigVerify => "OpCheckMultiSigVerify",
+        OpCheckLockTimeVerify => "OpCheckLockTimeVerify",
+        OpCheckSequenceVerify => "OpCheckSequenceVerify",
+        OpSmallInteger => "OpSmallInteger",
+        OpPubKeys => "OpPubKeys",
+        OpPubKeyHash => "OpPubKeyHash",
+        OpPubKey => "OpPubKey",
+        OpInvalidOpCode => "OpInvalidOpCode",
+        _ => return format!("OpUnknown{opcode}").into(),
+    };
+    name.into()
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Returns `data` as text if it consists of printable ASCII only.
+fn printable(data: &[u8]) -> Option<&str> {
+    if !data.is_empty() && data.iter().all(|b| (0x20..=0x7e).contains(b)) {
+        std::str::from_utf8(data).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kaspa_txscript::script_builder::ScriptBuilder;
+
+    #[test]
+    fn test_opcode_names() {
+        assert_eq!(opcode_name(OpFalse), "OpFalse");
+        assert_eq!(opcode_name(OpData32), "OpData32");
+        assert_eq!(opcode_name(Op16), "Op16");
+        assert_eq!(opcode_name(OpCheckSig), "OpCheckSig");
+        assert_eq!(opcode_name(0xa6), "OpUnknown166");
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let envelope = Envelope::new(b"kasplex", &[], br#"{"p":"krc-20"}"#);
+        let redeem_script = envelope::encode_redeem_script(&[7; 32], &envelope).unwrap();
+
+        let disassembly = disassemble(&redeem_script);
+        assert!(disassembly.error.is_none());
+        let names = disassembly
+            .instructions
+            .iter()
+            .map(|instruction| instruction.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "OpData32",
+                "OpCheckSig",
+                "OpFalse",
+                "OpIf",
+                "OpData7",
+                "OpFalse",
+                "OpData14",
+                "OpEndIf"
+            ]
+        );
+        assert_eq!(
+            disassembly.instructions[4].to_string(),
+            "0024  OpData7 [7] 6b6173706c6578 \"kasplex\""
+        );
+
+        let truncated = disassemble(&redeem_script[..redeem_script.len() - 3]);
+        assert_eq!(truncated.instructions.len(), 6);
+        assert!(matches!(truncated.error, Some((0x2d, _))));
+    }
+
+    #[test]
+    fn test_inspect_signature_script() {
+        let envelope = Envelope::new(b"kasplex", &[], br#"{"p":"krc-20"}"#);
+        let redeem_script = envelope::encode_redeem_script(&[7; 32], &envelope).unwrap();
+        let signature_script = ScriptBuilder::new()
+            .add_data(&[1; 65])
+            .unwrap()
+            .add_data(&redeem_script)
+            .unwrap()
+            .drain();
+
+        let text = inspect(&signature_script);
+        assert!(text.contains("Redeem script:"));
+        assert!(text.contains("    namespace [7] kasplex"));
+        assert!(text.contains(r#"    payload [14] {"p":"krc-20"}"#));
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Url(String);
 
 impl From<&str> for Url {
@@ -24,6 +25,21 @@ impl Url {
     pub fn join<P: fmt::Display>(&self, path: P) -> Url {
         Url(format!("{}{}", self.0, path))
     }
+
+    /// Appends `params` as a percent-encoded query string.
+    pub fn with_query<N: AsRef<str>, V: AsRef<str>>(&self, params: &[(N, V)]) -> Url {
+        let query = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", encode(name.as_ref()), encode(value.as_ref())))
+            .collect::<Vec<_>>()
+            .join("&");
+        if query.is_empty() {
+            Url(self.0.clone())
+        } else {
+            let separator = if self.0.contains('?') { '&' } else { '?' };
+            Url(format!("{}{separator}{query}", self.0))
+        }
+    }
 }
 
 impl fmt::Display for Url {
@@ -37,3 +53,43 @@ impl From<Url> for String {
         url.0
     }
 }
+
+/// Percent-encodes everything but RFC 3986 unreserved characters.
+fn encode(component: &str) -> String {
+    component.bytes().fold(
+        String::with_capacity(component.len()),
+        |mut encoded, byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{byte:02X}"));
+            }
+            encoded
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_with_query() {
+        let url = Url::from("https://api.kasplex.org/v1/krc20/oplist");
+        assert_eq!(
+            url.with_query::<&str, &str>(&[]).to_string(),
+            url.to_string()
+        );
+        assert_eq!(
+            url.with_query(&[("tick", "KEKE"), ("next", "7790665/50003 +")])
+                .to_string(),
+            "https://api.kasplex.org/v1/krc20/oplist?tick=KEKE&next=7790665%2F50003%20%2B"
+        );
+        assert_eq!(
+            url.with_query(&[("a", "1")])
+                .with_query(&[("b", "2")])
+                .to_string(),
+            "https://api.kasplex.org/v1/krc20/oplist?a=1&b=2"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//!
+//! Kasplex v1 API served from the KRC-20 ledger of the analyzer.
+//!
+//! Mirrors the kasplex indexer endpoints read by the client `Indexer` so
+//! that the node's own index can be reconciled against the kasplex
+//! reference indexer. List endpoints are paginated with offset cursors of
+//! [`PAGE_SIZE`] items. Marketplace listings are not tracked.
+//!
+
+use crate::imports::*;
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::json;
+use sparkle_core::model::kasplex::v1::{krc20, IndexerStatusResponse, Ledger, Tick};
+use std::str::FromStr;
+
+/// Number of items per page of the list endpoints.
+pub const PAGE_SIZE: usize = 50;
+
+const SUCCESSFUL: &str = "successful";
+
+type SharedLedger = Arc<RwLock<Ledger>>;
+
+pub fn router(ledger: SharedLedger) -> Router {
+    Router::new()
+        .route("/v1/info", get(info))
+        .route("/v1/krc20/tokenlist", get(token_list))
+        .route("/v1/krc20/token/:tick", get(token))
+        .route("/v1/krc20/address/:address/tokenlist", get(balance_list))
+        .route("/v1/krc20/address/:address/token/:tick", get(balance))
+        .route("/v1/krc20/oplist", get(op_list))
+        .route("/v1/krc20/op/:id", get(op))
+        .with_state(ledger)
+}
+
+struct Page<T> {
+    items: Vec<T>,
+    next: String,
+    prev: String,
+}
+
+/// Page of `items` at the `next` or `prev` offset cursor of `query`.
+fn page<T>(
+    mut items: Vec<T>,
+    query: &HashMap<String, String>,
+) -> std::result::Result<Page<T>, Response> {
+    let start = match query.get("next").or_else(|| query.get("prev")) {
+        None => 0,
+        Some(cursor) => match cursor.parse::<usize>() {
+            Ok(start) => start.min(items.len()),
+            Err(_) => return Err(message("cursor invalid")),
+        },
+    };
+    let end = (start + PAGE_SIZE).min(items.len());
+
+    let next = if end < items.len() {
+        end.to_string()
+    } else {
+        String::new()
+    };
+    let prev = if start > 0 {
+        start.saturating_sub(PAGE_SIZE).to_string()
+    } else {
+        String::new()
+    };
+
+    items.truncate(end);
+    Ok(Page {
+        items: items.split_off(start),
+        next,
+        prev,
+    })
+}
+
+fn message(message: &str) -> Response {
+    Json(json!({ "message": message })).into_response()
+}
+
+async fn info(State(ledger): State<SharedLedger>) -> Response {
+    let result = ledger.read().unwrap().status();
+    Json(IndexerStatusResponse {
+        message: SUCCESSFUL.to_string(),
+        result,
+    })
+    .into_response()
+}
+
+async fn token_list(
+    State(ledger): State<SharedLedger>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let tokens = ledger.read().unwrap().token_list();
+    match page(tokens, &query) {
+        Ok(Page { items, next, prev }) => Json(krc20::TokenListResponse {
+            message: SUCCESSFUL.to_string(),
+            next,
+            prev,
+            result: items,
+        })
+        .into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn token(State(ledger): State<SharedLedger>, Path(tick): Path<String>) -> Response {
+    let Ok(tick) = Tick::from_str(&tick) else {
+        return message("tick invalid");
+    };
+
+    let result = ledger.read().unwrap().token(&tick);
+    Json(krc20::TokenHolderResponse {
+        message: SUCCESSFUL.to_string(),
+        result,
+    })
+    .into_response()
+}
+
+async fn balance_list(
+    State(ledger): State<SharedLedger>,
+    Path(address): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let balances = ledger.read().unwrap().balances(&address);
+    match page(balances, &query) {
+        Ok(Page { items, next, prev }) => Json(krc20::TokenBalanceListByAddressResponse {
+            message: SUCCESSFUL.to_string(),
+            next,
+            prev,
+            result: items,
+        })
+        .into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn balance(
+    State(ledger): State<SharedLedger>,
+    Path((address, tick)): Path<(String, String)>,
+) -> Response {
+    let Ok(tick) = Tick::from_str(&tick) else {
+        return message("tick invalid");
+    };
+
+    let result = ledger.read().unwrap().balance(&address, &tick);
+    Json(krc20::TokenBalanceResponse {
+        message: SUCCESSFUL.to_string(),
+        result,
+    })
+    .into_response()
+}
+
+async fn op_list(
+    State(ledger): State<SharedLedger>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let mut filter = krc20::OpListFilter::default();
+    if let Some(address) = query.get("address") {
+        filter = filter.address(address);
+    }
+    if let Some(tick) = query.get("tick") {
+        let Ok(tick) = Tick::from_str(tick) else {
+            return message("tick invalid");
+        };
+        filter = filter.tick(tick);
+    }
+    if let Some(op) = query.get("op") {
+        let Ok(op) = krc20::Op::from_str(op) else {
+            return message("op invalid");
+        };
+        filter = filter.op(op);
+    }
+
+    let ops = ledger.read().unwrap().ops(&filter);
+    match page(ops, &query) {
+        Ok(Page { items, next, prev }) => Json(krc20::TokenTransactionResponse {
+            message: SUCCESSFUL.to_string(),
+            next,
+            prev,
+            result: items,
+        })
+        .into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn op(State(ledger): State<SharedLedger>, Path(id): Path<String>) -> Response {
+    let result = ledger.read().unwrap().op(&id);
+    Json(krc20::TokenTransactionIdResponse {
+        message: SUCCESSFUL.to_string(),
+        result,
+    })
+    .into_response()
+}
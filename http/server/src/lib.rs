@@ -2,6 +2,7 @@ cfg_if::cfg_if! {
     if #[cfg(not(target_arch = "wasm32"))] {
         pub mod error;
         pub mod imports;
+        pub mod kasplex;
         pub mod limits;
         pub mod params;
         pub mod result;
@@ -24,13 +24,15 @@ use tower::{buffer::BufferLayer, limit::RateLimitLayer, ServiceBuilder};
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
+use sparkle_core::model::kasplex::v1::Ledger;
 use sparkle_core::runtime::{Runtime, Service, ServiceResult};
-use sparkle_nexus::prelude::Nexus;
+use sparkle_nexus::prelude::{Analyzer, Nexus};
 
 pub struct HttpServer {
     // listener: TcpListener,
     // router: Router,
     nexus: Nexus,
+    ledger: Arc<RwLock<Ledger>>,
     listen_address: String,
     rate_limit: Option<RateLimit>,
 }
@@ -38,6 +40,7 @@ pub struct HttpServer {
 impl HttpServer {
     pub async fn try_new(
         nexus: &Nexus,
+        analyzer: &Analyzer,
         listen_address: &str,
         rate_limit: Option<RateLimit>,
     ) -> Result<Self> {
@@ -45,6 +48,7 @@ impl HttpServer {
 
         Ok(Self {
             nexus: nexus.clone(),
+            ledger: analyzer.ledger().clone(),
             listen_address: listen_address.to_string(),
             rate_limit,
         })
@@ -89,6 +93,9 @@ impl Service for HttpServer {
             }),
         );
 
+        // kasplex v1 API served from the KRC-20 ledger
+        let app = app.merge(crate::kasplex::router(self.ledger.clone()));
+
         let app = if let Some(rate_limit) = self.rate_limit.as_ref() {
             log_info!(
                 "Setting rate limit to: {} requests per {} seconds",
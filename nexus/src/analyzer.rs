@@ -1,7 +1,12 @@
 use crate::imports::*;
-use serde_json::from_slice;
-use sparkle_core::inscription::ascii_debug_payload;
+use kaspa_consensus_core::tx::ScriptPublicKey;
+use sparkle_core::envelope::{self, Envelope};
+use sparkle_core::inscription::builder::script_address;
 use sparkle_core::model::kasplex;
+use sparkle_core::model::kasplex::v1::{Ledger, OpContext, Timestamp};
+use sparkle_core::model::sparkle::{SparkleState, SparkleTransaction};
+use sparkle_core::model::{ProtocolOperation, ProtocolRegistry};
+use sparkle_core::script::inspect;
 // use kaspa_rpc_core::model::*;
 
 pub enum AnalyzerEvent {
@@ -11,6 +16,9 @@ pub enum AnalyzerEvent {
 pub struct Inner {
     pub shutdown: DuplexChannel<()>,
     pub nexus: Nexus,
+    pub registry: ProtocolRegistry,
+    pub ledger: Arc<RwLock<Ledger>>,
+    pub sparkle: Arc<RwLock<SparkleState>>,
 }
 
 #[derive(Clone)]
@@ -23,6 +31,9 @@ impl Analyzer {
         let inner = Inner {
             shutdown: DuplexChannel::oneshot(),
             nexus: nexus.clone(),
+            registry: ProtocolRegistry::default(),
+            ledger: Arc::new(RwLock::new(Ledger::new())),
+            sparkle: Arc::new(RwLock::new(SparkleState::new())),
         };
 
         let analyzer = Self {
@@ -36,8 +47,97 @@ impl Analyzer {
         &self.inner.nexus
     }
 
+    /// Protocols detected by the analyzer.
+    pub fn registry(&self) -> &ProtocolRegistry {
+        &self.inner.registry
+    }
+
+    /// KRC-20 state built from the detected operations.
+    pub fn ledger(&self) -> &Arc<RwLock<Ledger>> {
+        &self.inner.ledger
+    }
+
+    /// Sparkle token state built from the detected operations.
+    pub fn sparkle(&self) -> &Arc<RwLock<SparkleState>> {
+        &self.inner.sparkle
+    }
+
+    /// Applies a detected sparkle operation to the sparkle token state.
+    fn apply_sparkle(
+        &self,
+        sparkle: &SparkleTransaction,
+        transaction: &RpcTransaction,
+        daa_score: u64,
+        network_id: &NetworkId,
+    ) {
+        let Some(from) = detect_sender(&transaction, network_id) else {
+            return;
+        };
+
+        match self
+            .sparkle()
+            .write()
+            .unwrap()
+            .apply(sparkle, &from, daa_score)
+        {
+            Ok(()) => log_trace!(
+                "Applied sparkle `{}` operation on `{}`",
+                sparkle.op,
+                sparkle.op.tick()
+            ),
+            Err(err) => log_trace!("Rejected sparkle `{}` operation: {err}", sparkle.op),
+        }
+    }
+
+    /// Applies a detected KRC-20 operation to the ledger.
+    fn apply(
+        &self,
+        token: &TokenTransaction,
+        transaction: &RpcTransaction,
+        daa_score: u64,
+        network_id: &NetworkId,
+    ) {
+        let Some(verbose_data) = transaction.verbose_data.as_ref() else {
+            log_trace!("Skipping `{}` operation without verbose data", token.op);
+            return;
+        };
+        let Some(from) = detect_sender(&transaction, network_id) else {
+            return;
+        };
+
+        // mempool transactions carry no block time
+        let millis = match verbose_data.block_time {
+            0 => unixtime_as_millis_f64() as u64,
+            block_time => block_time,
+        };
+        let Some(timestamp) = Timestamp::from_millis(millis as i64) else {
+            return;
+        };
+
+        let context = OpContext {
+            hash_rev: Hash::from_slice(&verbose_data.transaction_id.as_bytes()),
+            from,
+            daa_score,
+            timestamp,
+        };
+        if let Some(op) = self.ledger().write().unwrap().apply(token, &context) {
+            log_trace!(
+                "Applied `{}` operation {}: {}",
+                op.op,
+                context.hash_rev,
+                op.op_error
+                    .as_deref()
+                    .filter(|error| !error.is_empty())
+                    .unwrap_or("accepted")
+            );
+        }
+    }
+
     async fn task(self: Arc<Self>) -> Result<()> {
         let events = self.nexus().multiplexer().channel();
+        let network_id = self.nexus().network_id();
+        let filter_tick =
+            kasplex::v1::Tick::from_str("toitoi").map_err(sparkle_core::error::Error::from)?;
 
         loop {
             select_biased! {
@@ -48,18 +148,25 @@ impl Analyzer {
                             // handle RPC channel connection and disconnection events
                             #[allow(clippy::single_match)]
                             match &*msg {
-                                Event::Transaction { transaction } => {
-
-                                    if let Some(token) = detect_krc20(transaction){
-
-                                        if token.has_tick("toitoi") {
-                                            println!("Filter tick");
-                                            dbg!(&token);
-                                        }
-                                        // Debug
-                                        if token.op == kasplex::v1::krc20::Op::Deploy {
-                                            println!("Filter deploy");
-                                            dbg!(&token);
+                                Event::Transaction { transaction, daa_score } => {
+
+                                    if let Some(operation) = detect_operation(self.registry(), transaction, &network_id) {
+
+                                        if let Some(token) = operation.downcast_ref::<TokenTransaction>() {
+                                            self.apply(token, transaction, *daa_score, &network_id);
+                                            if token.has_tick(&filter_tick) {
+                                                println!("Filter tick");
+                                                dbg!(&token);
+                                            }
+                                            // Debug
+                                            if token.op == kasplex::v1::krc20::Op::Deploy {
+                                                println!("Filter deploy");
+                                                dbg!(&token);
+                                            }
+                                        } else if let Some(sparkle) = operation.downcast_ref::<SparkleTransaction>() {
+                                            self.apply_sparkle(sparkle, transaction, *daa_score, &network_id);
+                                        } else {
+                                            log_trace!("Detected {} `{}` operation", operation.protocol(), operation.op());
                                         }
 
                                     }
@@ -116,164 +223,100 @@ impl Service for Analyzer {
     }
 }
 
-#[inline]
-fn window_find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    // Ensure we don't start beyond the end of the haystack
-    let offset = 10;
-    if haystack.len() <= offset {
-        return None;
-    }
-
-    // Optization: iterate starting from the nth byte
-    for (position, window) in haystack[offset..].windows(needle.len()).enumerate() {
-        if window == needle {
-            return Some(position + offset); // Adjust the position to account for the byte offset
-        }
-    }
-    None
-}
-
-fn parse_script<T: VerifiableTransaction>(
-    script: &[u8],
-) -> impl Iterator<Item = std::result::Result<Box<dyn OpCodeImplementation<T>>, TxScriptError>> + '_
-{
-    script.iter().batching(|it| deserialize_next_opcode(it))
-}
-
 pub trait ITransaction {
     fn signature_script(&self) -> Option<&[u8]>;
-    fn rcv(&self) -> Address;
+    fn script_public_key(&self) -> Option<&ScriptPublicKey>;
+
+    /// Address of the first output on `network_id`.
+    fn rcv(&self, network_id: &NetworkId) -> Result<Address> {
+        let script_public_key = self
+            .script_public_key()
+            .ok_or_else(|| Error::custom("Transaction has no outputs"))?;
+        Ok(script_address(script_public_key, network_id)?)
+    }
 }
 
 impl ITransaction for &RpcTransaction {
     fn signature_script(&self) -> Option<&[u8]> {
-        Some(&self.inputs[0].signature_script[..])
+        self.inputs.first().map(|input| &input.signature_script[..])
     }
-    fn rcv(&self) -> Address {
-        extract_script_pub_key_address(
-            &self.outputs[0].script_public_key,
-            Prefix::try_from("kaspatest").unwrap(),
-        )
-        .unwrap()
+    fn script_public_key(&self) -> Option<&ScriptPublicKey> {
+        self.outputs.first().map(|output| &output.script_public_key)
     }
 }
 
 impl ITransaction for &Transaction {
     fn signature_script(&self) -> Option<&[u8]> {
-        Some(&self.inputs[0].signature_script[..])
+        self.inputs.first().map(|input| &input.signature_script[..])
     }
-    fn rcv(&self) -> Address {
-        extract_script_pub_key_address(
-            &self.outputs[0].script_public_key,
-            Prefix::try_from("kaspatest").unwrap(),
-        )
-        .unwrap()
+    fn script_public_key(&self) -> Option<&ScriptPublicKey> {
+        self.outputs.first().map(|output| &output.script_public_key)
     }
 }
 impl ITransaction for &Box<RpcTransaction> {
     fn signature_script(&self) -> Option<&[u8]> {
-        if self.inputs.is_empty() {
-            return None;
-        }
-        Some(&self.inputs[0].signature_script[..])
+        self.inputs.first().map(|input| &input.signature_script[..])
     }
-    fn rcv(&self) -> Address {
-        extract_script_pub_key_address(
-            &self.outputs[0].script_public_key,
-            Prefix::try_from("kaspatest").unwrap(),
-        )
-        .unwrap()
+    fn script_public_key(&self) -> Option<&ScriptPublicKey> {
+        self.outputs.first().map(|output| &output.script_public_key)
     }
 }
 
-pub fn detect_krc20_header(haystack: &[u8]) -> bool {
-    window_find(haystack, &KRC20_HEADER_UC).is_some()
-        || window_find(haystack, &KRC20_HEADER_LC).is_some()
+pub fn detect_krc20_receiver<T: ITransaction>(sigtx: T, network_id: &NetworkId) -> Result<Address> {
+    sigtx.rcv(network_id)
 }
 
-pub fn detect_kasplex_header(haystack: &[u8]) -> bool {
-    window_find(haystack, &KASPLEX_HEADER_LC).is_some()
-        || window_find(haystack, &KASPLEX_HEADER_UC).is_some()
+/// Extracts the inscription envelope (with the payload reassembled) revealed
+/// by the first input of `sigtx`.
+pub fn detect_inscription<T: ITransaction>(sigtx: &T) -> Option<Envelope> {
+    let signature_script = sigtx.signature_script()?;
+
+    match envelope::decode_signature_script(signature_script) {
+        Ok(redeem_script) => Some(redeem_script.envelope),
+        Err(envelope::Error::NotFound) => None,
+        Err(err) => {
+            log_trace!("Malformed envelope: {err}");
+            None
+        }
+    }
 }
 
-pub fn detect_krc20_receiver<T: ITransaction>(sigtx: T) -> Address {
-    sigtx.rcv()
+/// P2PK address of the key revealing the inscription in the first input
+/// of `sigtx`.
+pub fn detect_sender<T: ITransaction>(sigtx: &T, network_id: &NetworkId) -> Option<Address> {
+    let signature_script = sigtx.signature_script()?;
+    let redeem_script = envelope::decode_signature_script(signature_script).ok()?;
+    Some(Address::new(
+        (*network_id).into(),
+        kaspa_addresses::Version::PubKey,
+        &redeem_script.pubkey,
+    ))
 }
 
-pub fn detect_krc20<T: ITransaction>(sigtx: T) -> Option<TokenTransaction> {
-    let mut inscription: Option<TokenTransaction> = None;
-
-    if let Some(signature_script) = sigtx.signature_script() {
-        if detect_kasplex_header(signature_script) {
-            // Get the second opcode
-            let mut opcodes_iter = parse_script(signature_script);
-            let second_opcode: Option<
-                std::result::Result<
-                    Box<dyn OpCodeImplementation<PopulatedTransaction>>,
-                    TxScriptError,
-                >,
-            > = opcodes_iter.nth(1);
-
-            // println!("------------------ {} {}", sigtx.gas(), sigtx.mass());
-
-            match second_opcode {
-                Some(Ok(opcode)) => {
-                    if !opcode.is_empty()
-                        && opcode.is_push_opcode()
-                        && detect_krc20_header(opcode.get_data())
-                    {
-                        let inner_opcodes: Vec<_> =
-                            parse_script::<PopulatedTransaction>(opcode.get_data()).collect();
-                        if inner_opcodes.len() >= 2 {
-                            if let Some(Ok(second_to_last_opcode)) =
-                                inner_opcodes.get(inner_opcodes.len() - 2)
-                            {
-                                ascii_debug_payload(second_to_last_opcode.get_data());
-
-                                println!("Receiver {:}", sigtx.rcv().address_to_string());
-
-                                match from_slice::<TokenTransaction>(
-                                    second_to_last_opcode.get_data(),
-                                ) {
-                                    Ok(token_transaction) => {
-                                        // Debug
-                                        if token_transaction.op == kasplex::v1::krc20::Op::Transfer
-                                        {
-                                            ascii_debug_payload(opcode.get_data());
-                                        }
-                                        // Debug
-                                        if token_transaction.op == kasplex::v1::krc20::Op::Deploy {
-                                            ascii_debug_payload(opcode.get_data());
-                                        }
-                                        // Debug
-                                        if token_transaction.has_tick("toitoi") {
-                                            ascii_debug_payload(opcode.get_data());
-                                        }
-
-                                        inscription = Some(token_transaction);
-                                    }
-                                    Err(e) => {
-                                        ascii_debug_payload(second_to_last_opcode.get_data());
-
-                                        // Handle the error if necessary
-                                        eprintln!("Failed to deserialize: {:?}", e);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                Some(Err(e)) => {
-                    // Handle the error
-                    println!("Error while parsing opcodes: {:?}", e);
-                }
-                None => {
-                    // Handle the case where there are fewer than two opcodes
-                    println!("There are fewer than two opcodes in the script.");
+/// Decodes the operation inscribed in `sigtx` with the first protocol of
+/// `registry` detecting its envelope.
+pub fn detect_operation<T: ITransaction>(
+    registry: &ProtocolRegistry,
+    sigtx: T,
+    network_id: &NetworkId,
+) -> Option<Box<dyn ProtocolOperation>> {
+    let signature_script = sigtx.signature_script()?;
+    let envelope = detect_inscription(&sigtx)?;
+
+    match registry.decode(&envelope)? {
+        Ok(operation) => {
+            if sparkle_core::debug::debug() {
+                println!("{}", inspect(signature_script));
+                match sigtx.rcv(network_id) {
+                    Ok(receiver) => println!("Receiver {:}", receiver.address_to_string()),
+                    Err(err) => println!("Receiver error: {err}"),
                 }
             }
+            Some(operation)
+        }
+        Err(err) => {
+            log_trace!("Failed to decode protocol payload: {err}");
+            None
         }
     }
-
-    inscription
 }
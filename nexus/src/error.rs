@@ -41,6 +41,12 @@ pub enum Error {
     #[error("Node is not synced")]
     NodeNotSynced,
 
+    #[error("Historical sync aborted")]
+    SyncAborted,
+
+    #[error("Missing verbose data of block {0}")]
+    MissingVerboseData(String),
+
     #[error("Expecting listener id")]
     ListenerId,
 
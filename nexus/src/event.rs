@@ -20,6 +20,14 @@ pub enum Event {
 
     /// Data processor has started
     Start,
+    /// Historical sync progress
+    SyncProgress {
+        /// DAA score of the last ingested chain block
+        daa_score: u64,
+        virtual_daa_score: u64,
+        chain_blocks: u64,
+        blocks: u64,
+    },
     /// Data processor is synced
     Synced,
     /// Data processor has stopped (disconnected)
@@ -31,5 +39,8 @@ pub enum Event {
     /// Protocol transaction has been detected
     Transaction {
         transaction: Box<RpcTransaction>,
+        /// DAA score of the accepting chain block for synced transactions,
+        /// of the containing block for live ones
+        daa_score: u64,
     },
 }
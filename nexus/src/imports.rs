@@ -51,7 +51,7 @@ pub use crate::context::ContextT;
 pub use crate::error::Error;
 pub use crate::event::Event;
 pub use crate::nexus::Nexus;
-pub use crate::processor::{Ingest, Processor};
+pub use crate::processor::{ChainBlock, Ingest, Processor};
 // pub use crate::operations::{deserialize, BaseData};
 pub use crate::result::Result;
 pub use crate::utils::*;
@@ -14,7 +14,7 @@ cfg_if::cfg_if! {
         pub mod evm;
 
         pub mod prelude {
-            pub use crate::nexus::Nexus;
+            pub use crate::nexus::{Nexus, SyncConfig};
             pub use crate::analyzer::Analyzer;
             pub use crate::context::ContextT;
         }
@@ -1,14 +1,17 @@
 use crate::imports::*;
 // use kaspa_notify::notification::test_helpers::BlockAddedNotification;
 use kaspa_rpc_core::api::ctl::{RpcCtl, RpcState};
-use kaspa_rpc_core::RpcBlock;
 use kaspa_rpc_core::{
     api::ops::{RPC_API_REVISION, RPC_API_VERSION},
-    model::{GetServerInfoResponse, RpcTransaction},
+    model::{
+        GetBlockDagInfoResponse, GetServerInfoResponse, GetVirtualChainFromBlockResponse,
+        RpcTransaction,
+    },
     notify::connection::{ChannelConnection, ChannelType},
     BlockAddedNotification, Notification, VirtualChainChangedNotification,
     VirtualDaaScoreChangedNotification,
 };
+use kaspa_rpc_core::{RpcBlock, RpcHash};
 use kaspa_wallet_core::rpc::{DynRpcApi, Rpc};
 
 use kaspa_notify::{
@@ -16,6 +19,143 @@ use kaspa_notify::{
     scope::{BlockAddedScope, Scope, VirtualChainChangedScope, VirtualDaaScoreChangedScope},
 };
 use kaspa_wrpc_client::prelude::{ConnectOptions, KaspaRpcClient, Resolver, WrpcEncoding};
+use std::collections::HashSet;
+
+/// Number of chain blocks between historical sync progress events.
+const SYNC_PROGRESS_INTERVAL: u64 = 1000;
+
+/// Starting point of the historical sync.
+#[derive(Debug, Clone, Default)]
+pub struct SyncConfig {
+    /// Chain block to walk the selected chain from. If `None`, the walk
+    /// starts from the last chain block preceding the protocol genesis, or
+    /// from the pruning point if the genesis score is 0.
+    pub low_hash: Option<RpcHash>,
+    /// DAA score at which the protocol was activated; earlier chain blocks
+    /// are walked without being ingested.
+    pub genesis_daa_score: u64,
+}
+
+impl SyncConfig {
+    pub fn with_low_hash(self, low_hash: Option<RpcHash>) -> Self {
+        Self { low_hash, ..self }
+    }
+
+    pub fn with_genesis_daa_score(self, genesis_daa_score: u64) -> Self {
+        Self {
+            genesis_daa_score,
+            ..self
+        }
+    }
+}
+
+/// Blocks ingested by the historical sync that are also received as
+/// notifications queued while the sync was running.
+#[derive(Debug, Default)]
+struct Handoff {
+    /// Virtual DAA score taken before registering for notifications, blocks
+    /// from this score onwards are also notified.
+    live_daa_score: u64,
+    /// Virtual DAA score of the DAG snapshot the sync stops at, taken once
+    /// registered for notifications.
+    sync_daa_score: u64,
+    chain_blocks: HashSet<RpcHash>,
+    blocks: HashSet<RpcHash>,
+}
+
+impl Handoff {
+    fn new(live_daa_score: u64) -> Self {
+        Self {
+            live_daa_score,
+            ..Default::default()
+        }
+    }
+
+    /// Records the notified blocks among a synced chain block and its mergeset.
+    fn record(&mut self, chain_block: &ChainBlock) {
+        if chain_block.daa_score >= self.live_daa_score {
+            self.chain_blocks.insert(chain_block.hash);
+        }
+        self.blocks.extend(
+            chain_block
+                .mergeset
+                .iter()
+                .filter(|block| block.header.daa_score >= self.live_daa_score)
+                .map(|block| block.header.hash),
+        );
+    }
+
+    /// Closes the handoff once the virtual has moved past the sync target.
+    /// Every notification of a synced block was emitted before the snapshot
+    /// and has been handled by then, the remaining hashes are never notified.
+    fn close(&mut self, virtual_daa_score: u64) {
+        if virtual_daa_score > self.sync_daa_score {
+            *self = Self::default();
+        }
+    }
+
+    /// Forgets the synced chain blocks reorged out of the selected chain.
+    fn remove_chain_blocks(&mut self, hashes: &[RpcHash]) {
+        hashes.iter().for_each(|hash| {
+            self.chain_blocks.remove(hash);
+        });
+    }
+
+    /// Returns `true` if the notified block `hash` has already been synced.
+    fn take_block(&mut self, hash: &RpcHash) -> bool {
+        self.blocks.remove(hash)
+    }
+
+    /// Strips the synced chain blocks from a chain change notification,
+    /// returning `None` if nothing is left to ingest.
+    fn filter(
+        &mut self,
+        notification: VirtualChainChangedNotification,
+    ) -> Option<VirtualChainChangedNotification> {
+        if self.chain_blocks.is_empty() {
+            return Some(notification);
+        }
+
+        let VirtualChainChangedNotification {
+            removed_chain_block_hashes,
+            added_chain_block_hashes,
+            accepted_transaction_ids,
+        } = notification;
+
+        let added_chain_block_hashes = added_chain_block_hashes
+            .iter()
+            .filter(|hash| !self.chain_blocks.remove(*hash))
+            .cloned()
+            .collect::<Vec<_>>();
+        if removed_chain_block_hashes.is_empty() && added_chain_block_hashes.is_empty() {
+            return None;
+        }
+
+        let accepted_transaction_ids = accepted_transaction_ids
+            .iter()
+            .filter(|accepted| added_chain_block_hashes.contains(&accepted.accepting_block_hash))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Some(VirtualChainChangedNotification {
+            removed_chain_block_hashes,
+            added_chain_block_hashes: Arc::new(added_chain_block_hashes),
+            accepted_transaction_ids: Arc::new(accepted_transaction_ids),
+        })
+    }
+}
+
+/// Sorts the mergeset of a chain block in acceptance order: the selected
+/// parent first, then the remaining blocks by ascending blue work.
+fn acceptance_order(mergeset: &mut [RpcBlock], selected_parent: RpcHash) {
+    mergeset.sort_by_key(|block| {
+        (
+            block.header.hash != selected_parent,
+            block.header.blue_work,
+            block.header.hash,
+        )
+    });
+}
 
 struct Inner {
     multiplexer: Multiplexer<Box<Event>>,
@@ -28,7 +168,11 @@ struct Inner {
     // are we synced with the DAG?
     is_synced: AtomicBool,
     current_daa_score: AtomicU64,
-    pending: Mutex<Vec<RpcTransaction>>,
+    pending: Mutex<Vec<(RpcTransaction, u64)>>,
+
+    sync_config: SyncConfig,
+    handoff: Mutex<Handoff>,
+    is_terminating: AtomicBool,
 
     processor: Arc<Processor>,
     sender: mpsc::Sender<Ingest>,
@@ -43,7 +187,11 @@ pub struct Nexus {
 }
 
 impl Nexus {
-    pub async fn try_new(network_id: NetworkId, url: Option<&str>) -> Result<Self> {
+    pub async fn try_new(
+        network_id: NetworkId,
+        url: Option<&str>,
+        sync_config: SyncConfig,
+    ) -> Result<Self> {
         println!("NEXUS init...");
         println!("PROCESSOR init...");
         let processor = Arc::new(Processor::try_new()?);
@@ -76,6 +224,9 @@ impl Nexus {
                 is_synced: AtomicBool::new(false),
                 current_daa_score: AtomicU64::new(0),
                 pending: Mutex::new(Vec::new()),
+                sync_config,
+                handoff: Mutex::new(Handoff::default()),
+                is_terminating: AtomicBool::new(false),
                 processor,
                 sender,
                 shutdown: DuplexChannel::oneshot(),
@@ -227,17 +378,23 @@ impl Nexus {
         self.inner.is_connected.store(true, Ordering::SeqCst);
 
         self.inner.is_synced.store(false, Ordering::SeqCst);
+        // snapshot the virtual before registering so that every block added
+        // past it is known to be notified
+        let live_daa_score = self.rpc_api().get_block_dag_info().await?.virtual_daa_score;
+        *self.inner.handoff.lock().unwrap() = Handoff::new(live_daa_score);
         self.register_notification_listener().await?;
         self.notify(Event::Start).await?;
 
-        // TODO:
-        // obtain blocks using low-hash
-        self.sync().await?;
-        // TODO:
-        // 1. drain data from sync
-        // 2. drain data from transactions that came in during sync
-        self.drain().await?;
+        // the sync stops at the sink observed once registered, blocks added
+        // later are only notified
+        let dag_info = self.rpc_api().get_block_dag_info().await?;
+        self.inner.handoff.lock().unwrap().sync_daa_score = dag_info.virtual_daa_score;
+
+        // walk the selected chain up to the snapshot sink, notifications
+        // received meanwhile are queued and handled once the sync is complete
+        self.sync(dag_info).await?;
         self.inner.is_synced.store(true, Ordering::SeqCst);
+        self.drain().await?;
         self.notify(Event::Synced).await?;
 
         Ok(())
@@ -245,6 +402,8 @@ impl Nexus {
 
     pub async fn handle_connect(&self) -> Result<()> {
         match self.handle_connect_impl().await {
+            // do not reconnect while shutting down
+            Err(err) if self.inner.is_terminating.load(Ordering::SeqCst) => Err(err),
             Err(err) => {
                 log_error!("Error while connecting to node: {err}");
                 // force disconnect the client if we have failed
@@ -272,6 +431,7 @@ impl Nexus {
     pub async fn cleanup(&self) -> Result<()> {
         // TODO - determine if pending cleanup should occur on disconnect
         self.inner.pending.lock().unwrap().clear();
+        *self.inner.handoff.lock().unwrap() = Handoff::default();
         Ok(())
     }
 
@@ -328,11 +488,22 @@ impl Nexus {
             Notification::BlockAdded(block_added_notification) => {
                 let BlockAddedNotification { block } = block_added_notification;
 
+                // already ingested by the historical sync
+                if self
+                    .inner
+                    .handoff
+                    .lock()
+                    .unwrap()
+                    .take_block(&block.header.hash)
+                {
+                    return Ok(());
+                }
+
                 let block = Arc::try_unwrap(block)
                     .expect("Unable to unwrap block in BlockAddedNotification");
 
                 let RpcBlock {
-                    header: _,
+                    header,
                     transactions,
                     verbose_data: _,
                 } = block;
@@ -341,7 +512,7 @@ impl Nexus {
                 // for tx in block_added_notification.block.transactions.iter().skip(1) {
                 // for tx in transactions.into_iter().skip(1) {
                 for tx in transactions.into_iter() {
-                    self.handle_transaction(tx)?;
+                    self.handle_transaction(tx, header.daa_score)?;
                 }
 
                 self.drain().await?;
@@ -387,6 +558,7 @@ impl Nexus {
         self.inner
             .current_daa_score
             .store(current_daa_score, Ordering::SeqCst);
+        self.inner.handoff.lock().unwrap().close(current_daa_score);
         self.notify(Event::DaaScoreChange { current_daa_score })
             .await?;
 
@@ -396,7 +568,7 @@ impl Nexus {
     }
 
     #[inline]
-    fn handle_transaction(&self, transaction: RpcTransaction) -> Result<()> {
+    fn handle_transaction(&self, transaction: RpcTransaction, daa_score: u64) -> Result<()> {
         // TODO
         // Ignore standard transactions
         // Place protocol transactions into a pending queue
@@ -416,7 +588,11 @@ impl Nexus {
         // if txid.as_bytes()[0] < 200 {
         // return Ok(());
         // } else {
-        self.inner.pending.lock().unwrap().push(transaction.clone());
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .push((transaction.clone(), daa_score));
         // }
 
         Ok(())
@@ -427,6 +603,11 @@ impl Nexus {
         &self,
         notification: VirtualChainChangedNotification,
     ) -> Result<()> {
+        // skip chain blocks already ingested by the historical sync
+        let Some(notification) = self.inner.handoff.lock().unwrap().filter(notification) else {
+            return Ok(());
+        };
+
         // TODO
         self.sender()
             .send(Ingest::VirtualChainChanged(notification.into()))?;
@@ -434,13 +615,199 @@ impl Nexus {
         Ok(())
     }
 
-    async fn sync(&self) -> Result<()> {
-        // TODO get low hash and loop over blocks
+    /// Walks the selected chain from the configured low hash (or the chain
+    /// block preceding the protocol genesis) up to the sink of `dag_info`,
+    /// feeding the chain blocks and their mergesets to the processor in
+    /// acceptance order. `dag_info` is the DAG snapshot taken once registered
+    /// for notifications, later chain blocks are left to the notifications.
+    async fn sync(&self, dag_info: GetBlockDagInfoResponse) -> Result<()> {
+        let rpc_api = self.rpc_api();
+        let SyncConfig {
+            low_hash,
+            genesis_daa_score,
+        } = self.inner.sync_config.clone();
+
+        let mut low_hash = match low_hash {
+            Some(low_hash) => low_hash,
+            None => self.sync_start(&dag_info, genesis_daa_score).await?,
+        };
+        let sink = dag_info.sink;
+        let sink_daa_score = rpc_api.get_block(sink, false).await?.header.daa_score;
+        let virtual_daa_score = dag_info.virtual_daa_score;
+
+        log_info!(
+            "Syncing from {low_hash} to {sink} (protocol genesis DAA score {genesis_daa_score})"
+        );
+
+        let mut daa_score = 0;
+        let mut chain_blocks = 0;
+        let mut blocks = 0;
+
+        'sync: loop {
+            // acceptance data is not requested, the chain is walked block by
+            // block and a node may cap the number of hashes returned
+            let GetVirtualChainFromBlockResponse {
+                removed_chain_block_hashes,
+                added_chain_block_hashes,
+                accepted_transaction_ids: _,
+            } = rpc_api
+                .get_virtual_chain_from_block(low_hash, false)
+                .await?;
+
+            if !removed_chain_block_hashes.is_empty() {
+                // the low hash has been reorged out of the selected chain,
+                // roll back the chain blocks ingested past the common ancestor
+                self.inner
+                    .handoff
+                    .lock()
+                    .unwrap()
+                    .remove_chain_blocks(&removed_chain_block_hashes);
+                let notification = VirtualChainChangedNotification {
+                    removed_chain_block_hashes: Arc::new(removed_chain_block_hashes),
+                    added_chain_block_hashes: Default::default(),
+                    accepted_transaction_ids: Default::default(),
+                };
+                self.sender()
+                    .send(Ingest::VirtualChainChanged(notification.into()))?;
+            }
+
+            if added_chain_block_hashes.is_empty() {
+                break;
+            }
+
+            for hash in added_chain_block_hashes {
+                if self.inner.is_terminating.load(Ordering::SeqCst) {
+                    return Err(Error::SyncAborted);
+                }
+
+                let block = rpc_api.get_block(hash, false).await?;
+                // the snapshot sink has been reorged out, the chain past
+                // it is notified
+                if block.header.daa_score > sink_daa_score {
+                    break 'sync;
+                }
+
+                if block.header.daa_score >= genesis_daa_score {
+                    let chain_block = self.sync_chain_block(block).await?;
+                    daa_score = chain_block.daa_score;
+                    blocks += chain_block.mergeset.len() as u64;
+
+                    self.inner.handoff.lock().unwrap().record(&chain_block);
+                    self.ingest_chain_block(chain_block)?;
+                }
+
+                chain_blocks += 1;
+                low_hash = hash;
+                if chain_blocks % SYNC_PROGRESS_INTERVAL == 0 {
+                    self.notify(Event::SyncProgress {
+                        daa_score,
+                        virtual_daa_score,
+                        chain_blocks,
+                        blocks,
+                    })
+                    .await?;
+                }
+
+                if hash == sink {
+                    break 'sync;
+                }
+            }
+        }
+
+        self.notify(Event::SyncProgress {
+            daa_score,
+            virtual_daa_score,
+            chain_blocks,
+            blocks,
+        })
+        .await?;
+
+        log_info!("Synced {chain_blocks} chain blocks, {blocks} blocks ingested up to {low_hash}");
+
+        Ok(())
+    }
+
+    /// Locates the last chain block preceding the protocol genesis by
+    /// bisecting the selected chain from the pruning point, which is
+    /// returned if the genesis precedes it.
+    async fn sync_start(
+        &self,
+        dag_info: &GetBlockDagInfoResponse,
+        genesis_daa_score: u64,
+    ) -> Result<RpcHash> {
+        let pruning_point = dag_info.pruning_point_hash;
+        if genesis_daa_score == 0 {
+            return Ok(pruning_point);
+        }
+
+        let rpc_api = self.rpc_api();
+        let chain = rpc_api
+            .get_virtual_chain_from_block(pruning_point, false)
+            .await?
+            .added_chain_block_hashes;
+
+        // chain blocks are in ascending DAA score order
+        let (mut low, mut high) = (0, chain.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let daa_score = rpc_api.get_block(chain[mid], false).await?.header.daa_score;
+            if daa_score < genesis_daa_score {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        match low.checked_sub(1) {
+            Some(index) => Ok(chain[index]),
+            None => {
+                log_warn!(
+                    "Protocol genesis DAA score {genesis_daa_score} precedes the pruning point, \
+                    syncing from {pruning_point}"
+                );
+                Ok(pruning_point)
+            }
+        }
+    }
+
+    /// Fetches the mergeset of the chain block `block` in acceptance order.
+    async fn sync_chain_block(&self, block: RpcBlock) -> Result<Arc<ChainBlock>> {
+        let rpc_api = self.rpc_api();
+        let hash = block.header.hash;
+
+        // the transactions of a block are accepted by the chain block merging it
+        let verbose_data = block
+            .verbose_data
+            .ok_or_else(|| Error::MissingVerboseData(hash.to_string()))?;
+        let mut mergeset = try_join_all(
+            verbose_data
+                .merge_set_blues_hashes
+                .iter()
+                .chain(verbose_data.merge_set_reds_hashes.iter())
+                .map(|hash| rpc_api.get_block(*hash, true)),
+        )
+        .await?;
+        acceptance_order(&mut mergeset, verbose_data.selected_parent_hash);
+
+        Ok(Arc::new(ChainBlock {
+            hash,
+            daa_score: block.header.daa_score,
+            mergeset,
+        }))
+    }
+
+    /// Ingests the transactions of the mergeset of a synced chain block, as
+    /// for block added notifications, followed by the chain block itself.
+    fn ingest_chain_block(&self, chain_block: Arc<ChainBlock>) -> Result<()> {
+        for block in chain_block.mergeset.iter() {
+            for tx in block.transactions.iter() {
+                self.handle_transaction(tx.clone(), chain_block.daa_score)?;
+            }
+        }
+        self.sender().send(Ingest::ChainBlock(chain_block))?;
 
-        // Check if transactions / blocks have already been processed
-        // process transactions as they come in
-        // (while this occurs we are also receiving current block notifications
-        // where the results are going into the pending queue)
+        // notifications are not handled during the sync
+        self.drain_pending();
 
         Ok(())
     }
@@ -460,19 +827,24 @@ impl Nexus {
         // all received notifications should be placed in the pending queue
         // and then drained
 
+        self.drain_pending();
+
+        Ok(())
+    }
+
+    fn drain_pending(&self) {
         self.inner
             .pending
             .lock()
             .unwrap()
             .drain(..)
-            .for_each(|transaction| {
+            .for_each(|(transaction, daa_score)| {
                 self.try_notify(Event::Transaction {
                     transaction: Box::new(transaction),
+                    daa_score,
                 })
                 .unwrap_or_else(|err| log_error!("Unable to post transaction event: {err}"));
             });
-
-        Ok(())
     }
 
     async fn task(self: Arc<Self>) -> Result<()> {
@@ -606,6 +978,7 @@ impl Service for Nexus {
 
     fn terminate(self: Arc<Self>) {
         // log_trace!("sending an exit signal to {SERVICE}");
+        self.inner.is_terminating.store(true, Ordering::SeqCst);
         self.inner.shutdown.request.try_send(()).unwrap();
 
         self.inner.processor.clone().terminate();
@@ -619,3 +992,128 @@ impl Service for Nexus {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kaspa_consensus_core::header::Header;
+    use kaspa_rpc_core::RpcAcceptedTransactionIds;
+
+    fn hash(word: u64) -> RpcHash {
+        RpcHash::from_u64_word(word)
+    }
+
+    fn block(word: u64, daa_score: u64, blue_work: u64) -> RpcBlock {
+        let mut header = Header::from_precomputed_hash(hash(word), vec![]);
+        header.daa_score = daa_score;
+        header.blue_work = blue_work.into();
+        RpcBlock {
+            header,
+            transactions: vec![],
+            verbose_data: None,
+        }
+    }
+
+    fn chain_block(word: u64, daa_score: u64, mergeset: Vec<RpcBlock>) -> ChainBlock {
+        ChainBlock {
+            hash: hash(word),
+            daa_score,
+            mergeset,
+        }
+    }
+
+    fn accepted(word: u64) -> RpcAcceptedTransactionIds {
+        RpcAcceptedTransactionIds {
+            accepting_block_hash: hash(word),
+            accepted_transaction_ids: vec![hash(word + 1000)],
+        }
+    }
+
+    #[test]
+    fn test_acceptance_order() {
+        let mut mergeset = vec![
+            block(3, 0, 20),
+            block(1, 0, 30),
+            block(4, 0, 10),
+            block(2, 0, 10),
+        ];
+        acceptance_order(&mut mergeset, hash(1));
+
+        let order = mergeset
+            .iter()
+            .map(|block| block.header.hash)
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![hash(1), hash(2), hash(4), hash(3)]);
+    }
+
+    #[test]
+    fn test_handoff() {
+        let mut handoff = Handoff::new(100);
+        handoff.record(&chain_block(20, 99, vec![block(21, 98, 0)]));
+        handoff.record(&chain_block(
+            10,
+            101,
+            vec![block(11, 99, 0), block(12, 100, 0)],
+        ));
+
+        // only the blocks notified past the snapshot are skipped, once
+        assert!(!handoff.take_block(&hash(21)));
+        assert!(!handoff.take_block(&hash(11)));
+        assert!(handoff.take_block(&hash(12)));
+        assert!(!handoff.take_block(&hash(12)));
+
+        // synced chain blocks are stripped along with their accepted transactions
+        let notification = handoff
+            .filter(VirtualChainChangedNotification {
+                removed_chain_block_hashes: Default::default(),
+                added_chain_block_hashes: Arc::new(vec![hash(10), hash(30)]),
+                accepted_transaction_ids: Arc::new(vec![accepted(10), accepted(30)]),
+            })
+            .unwrap();
+        assert_eq!(*notification.added_chain_block_hashes, vec![hash(30)]);
+        assert_eq!(notification.accepted_transaction_ids.len(), 1);
+        assert_eq!(
+            notification.accepted_transaction_ids[0].accepting_block_hash,
+            hash(30)
+        );
+
+        // notifications of synced chain blocks only are dropped
+        handoff.record(&chain_block(40, 150, vec![]));
+        handoff.record(&chain_block(50, 151, vec![]));
+        assert!(handoff
+            .filter(VirtualChainChangedNotification {
+                removed_chain_block_hashes: Default::default(),
+                added_chain_block_hashes: Arc::new(vec![hash(40)]),
+                accepted_transaction_ids: Arc::new(vec![accepted(40)]),
+            })
+            .is_none());
+
+        // reorged chain blocks are notified again
+        handoff.remove_chain_blocks(&[hash(50)]);
+        let notification = handoff
+            .filter(VirtualChainChangedNotification {
+                removed_chain_block_hashes: Default::default(),
+                added_chain_block_hashes: Arc::new(vec![hash(50)]),
+                accepted_transaction_ids: Default::default(),
+            })
+            .unwrap();
+        assert_eq!(*notification.added_chain_block_hashes, vec![hash(50)]);
+    }
+
+    #[test]
+    fn test_handoff_close() {
+        let mut handoff = Handoff::new(100);
+        handoff.sync_daa_score = 120;
+        handoff.record(&chain_block(10, 110, vec![block(11, 105, 0)]));
+        handoff.record(&chain_block(20, 120, vec![block(21, 115, 0)]));
+
+        // still within the window, the queued notifications may follow
+        handoff.close(120);
+        assert!(handoff.take_block(&hash(11)));
+
+        // the virtual moved past the sync target, the leftovers are dropped
+        handoff.close(121);
+        assert!(handoff.chain_blocks.is_empty() && handoff.blocks.is_empty());
+        assert!(!handoff.take_block(&hash(21)));
+    }
+}
@@ -3,14 +3,27 @@ use crate::imports::*;
 use std::thread;
 // use workflow_core::
 use kaspa_rpc_core::{
+    RpcBlock,
+    RpcHash,
     VirtualChainChangedNotification, //, BlockAddedNotification,
 };
 use sparkle_database::prelude::*;
 use std::fs;
 
+/// Chain block walked by the historical sync along with the blocks of its
+/// mergeset, whose transactions it accepts.
+pub struct ChainBlock {
+    pub hash: RpcHash,
+    pub daa_score: u64,
+    /// Mergeset blocks in acceptance (consensus) order.
+    pub mergeset: Vec<RpcBlock>,
+}
+
 pub enum Ingest {
     // NoOp,
     // Block(Block),
+    /// Chain block from the historical sync, sent in acceptance order.
+    ChainBlock(Arc<ChainBlock>),
     VirtualChainChanged(Arc<VirtualChainChangedNotification>),
     Transaction(Arc<RpcTransaction>),
 
@@ -80,6 +93,9 @@ impl Processor {
         loop {
             match receiver.recv() {
                 Ok(msg) => match msg {
+                    Ingest::ChainBlock(_chain_block) => {
+                        // println!("[PROC] Received chain block: {}", chain_block.hash);
+                    }
                     Ingest::Transaction(_tx) => {
                         // println!("[PROC] Received transaction: {:?}", tx);
                     }
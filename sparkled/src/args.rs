@@ -1,6 +1,8 @@
 use kaspa_consensus_core::network::{NetworkId, NetworkType};
+use kaspa_rpc_core::RpcHash;
 use kaspa_utils::networking::ContextualNetAddress;
 use kaspa_wrpc_client::WrpcEncoding;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct Args {
@@ -11,6 +13,9 @@ pub struct Args {
     pub rpc_listen: ContextualNetAddress,
     pub network_id: NetworkId,
     pub node_rpc: Option<String>,
+    /// Chain block to start the historical sync from.
+    pub low_hash: Option<RpcHash>,
+    pub genesis_daa_score: u64,
 }
 
 impl Args {
@@ -28,7 +33,7 @@ impl Args {
             .arg(arg!(--version "Display software version"))
             .arg(arg!(--trace "Enable trace log level"))
             .arg(arg!(--debug "Enable debug mode"))
-            .arg(arg!(--http "Enable HTTP Server"))
+            .arg(arg!(--http "Enable HTTP Server, serving the kasplex v1 API under /v1"))
             .arg(
                 Arg::new("rpc-listen")
                     .long("rpc-listen")
@@ -67,6 +72,32 @@ impl Args {
                     .num_args(0..=1)
                     .require_equals(true)
                     .help("wRPC URL of the node (disables resolver)."),
+            )
+            .arg(
+                Arg::new("low-hash")
+                    .long("low-hash")
+                    .value_name("hash")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .value_parser(|hash: &str| {
+                        RpcHash::from_str(hash).map_err(|err| err.to_string())
+                    })
+                    .help(
+                        "Chain block to start the historical sync from (default: the chain block \
+                        preceding the protocol genesis, or the pruning point).",
+                    ),
+            )
+            .arg(
+                Arg::new("genesis-daa-score")
+                    .long("genesis-daa-score")
+                    .value_name("score")
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .value_parser(clap::value_parser!(u64))
+                    .help(
+                        "DAA score of the protocol genesis, the historical sync starts from the \
+                        chain block preceding it and earlier blocks are not ingested (default: 0).",
+                    ),
             );
 
         let matches = cmd.get_matches();
@@ -95,6 +126,13 @@ impl Args {
 
         let node_rpc = matches.get_one::<String>("node-rpc").cloned();
 
+        let low_hash = matches.get_one::<RpcHash>("low-hash").cloned();
+
+        let genesis_daa_score = matches
+            .get_one::<u64>("genesis-daa-score")
+            .cloned()
+            .unwrap_or(0);
+
         if let Some(node_url) = &node_rpc {
             if let Err(err) = kaspa_wrpc_client::KaspaRpcClient::parse_url(
                 node_url.to_string(),
@@ -118,6 +156,8 @@ impl Args {
                 rpc_listen,
                 network_id,
                 node_rpc,
+                low_hash,
+                genesis_daa_score,
             }
         }
     }
@@ -1,6 +1,6 @@
 use sparkle_core::runtime::Runtime;
 use sparkle_http_server::HttpServer;
-use sparkle_nexus::prelude::{Analyzer, Nexus};
+use sparkle_nexus::prelude::{Analyzer, Nexus, SyncConfig};
 use sparkle_rpc_server::{WrpcOptions, WrpcService};
 use std::sync::Arc;
 #[allow(unused_imports)]
@@ -22,6 +22,8 @@ impl Server {
             http_listen,
             rpc_listen,
             node_rpc,
+            low_hash,
+            genesis_daa_score,
         } = Args::parse();
 
         if trace_log_level {
@@ -32,7 +34,10 @@ impl Server {
 
         // --- Services ---
 
-        let nexus = Nexus::try_new(network_id, node_rpc.as_deref())
+        let sync_config = SyncConfig::default()
+            .with_low_hash(low_hash)
+            .with_genesis_daa_score(genesis_daa_score);
+        let nexus = Nexus::try_new(network_id, node_rpc.as_deref(), sync_config)
             .await
             .expect("Unable to create nexus instance.");
         runtime.bind(Arc::new(nexus.clone()));
@@ -40,7 +45,7 @@ impl Server {
         let analyzer = Analyzer::try_new(&nexus)
             .await
             .expect("Unable to create analyzer instance.");
-        runtime.bind(Arc::new(analyzer));
+        runtime.bind(Arc::new(analyzer.clone()));
 
         let wrpc_options = WrpcOptions::default().listen(rpc_listen.to_string().as_str());
         let wrpc_server = WrpcService::try_new(&nexus, wrpc_options)
@@ -49,9 +54,10 @@ impl Server {
         runtime.bind(Arc::new(wrpc_server));
 
         if enable_http_server {
-            let http_server = HttpServer::try_new(&nexus, http_listen.to_string().as_str(), None)
-                .await
-                .expect("Unable to create HTTP server.");
+            let http_server =
+                HttpServer::try_new(&nexus, &analyzer, http_listen.to_string().as_str(), None)
+                    .await
+                    .expect("Unable to create HTTP server.");
             runtime.bind(Arc::new(http_server));
         }
 